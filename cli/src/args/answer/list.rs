@@ -0,0 +1,71 @@
+use crate::{profiles::Profiles, utils::ProfileName, OUTPUT_JSON};
+use clap::Parser;
+use color_eyre::eyre::{eyre, ContextCompat, Result};
+use tracing::instrument;
+
+/// Display the past queries of the profile, the oldest first.
+/// Continue a conversation with `eve ask --resume <QUERY_ID>`
+#[derive(Debug, Parser)]
+pub(crate) struct List {
+    #[clap(flatten)]
+    profile: ProfileName,
+
+    /// Maximum number of queries
+    #[arg(short, long, default_value_t = 20)]
+    limit: usize,
+
+    /// Number of queries to skip
+    #[arg(short, long, default_value_t = 0)]
+    offset: usize,
+
+    /// JSON output
+    #[arg(short, long)]
+    json: bool,
+}
+
+impl List {
+    #[instrument(level = "debug")]
+    pub(crate) async fn execute(&self) -> Result<()> {
+        OUTPUT_JSON.set(self.json)?;
+
+        let profile = Profiles::load()?
+            .get(&self.profile)
+            .cloned()
+            .with_context(|| eyre!("Profile `{}` not found", self.profile))?;
+        let queries = profile.client()?.queries(self.limit, self.offset).await?;
+
+        // display the response
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&queries)?);
+            return Ok(());
+        }
+
+        if queries.is_empty() {
+            println!("No queries found");
+            return Ok(());
+        }
+
+        println!("Queries:");
+        for query in &queries {
+            println!("   Query ID: {}", query.id);
+            println!("   Timestamp: {}", query.timestamp);
+            println!(
+                "   State: {}, relevance {}",
+                if query.complete {
+                    "complete"
+                } else {
+                    "in progress"
+                },
+                query
+                    .relevance
+                    .as_ref()
+                    .map(|relevance| format!("{relevance}%"))
+                    .unwrap_or(" - ".into())
+            );
+            println!("   Message: {}", query.message);
+            println!();
+        }
+
+        Ok(())
+    }
+}
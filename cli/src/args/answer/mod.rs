@@ -0,0 +1,145 @@
+pub(crate) mod list;
+
+use crate::{
+    display::{node_state, DisplayAnswer},
+    echoln,
+    profiles::{Profiles, DEFAULT_SESSION_NAME},
+    utils::{check_name, ProfileName},
+    OUTPUT_JSON,
+};
+use clap::Parser;
+use color_eyre::eyre::{eyre, ContextCompat, Result};
+use futures::StreamExt as _;
+use list::List;
+use orchestrator_client::ClientWithKey;
+use std::{pin::pin, time::Duration};
+use tracing::instrument;
+use types::ai::{
+    query::{Query, QueryId},
+    stream::QueryEvent,
+};
+
+/// Request a repeat response based on the question ID
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub(crate) struct Answer {
+    #[command(subcommand)]
+    command: Option<AnswerCommand>,
+
+    /// Session name(chat name). Used to store the history
+    #[arg(value_parser = check_name, default_value = DEFAULT_SESSION_NAME)]
+    session: String,
+
+    /// Request to AI
+    #[arg(short, long)]
+    query_id: Option<QueryId>,
+
+    #[clap(flatten)]
+    profile: ProfileName,
+
+    /// JSON output
+    #[arg(short, long)]
+    json: bool,
+
+    /// Show the reasoning of the models. The JSON output always includes it
+    #[arg(long)]
+    reasoning: bool,
+
+    /// Print the state changes of the nodes until the query is complete
+    #[arg(short, long)]
+    follow: bool,
+}
+
+#[derive(Debug, Parser)]
+enum AnswerCommand {
+    #[command(name = "list", visible_alias = "history")]
+    List(List),
+}
+
+impl Answer {
+    #[instrument(level = "debug")]
+    pub(crate) async fn execute(&self) -> Result<()> {
+        if let Some(AnswerCommand::List(cmd)) = &self.command {
+            return cmd.execute().await;
+        }
+
+        if self.json {
+            OUTPUT_JSON.set(self.json)?;
+        }
+
+        let profile = Profiles::load()?
+            .get(&self.profile)
+            .cloned()
+            .with_context(|| eyre!("Profile `{}` not found", self.profile))?;
+        let client = profile.client()?;
+
+        let query_id = match self.query_id {
+            Some(id) => id,
+            None => profile.session(&self.session).context("The session does not exist. Specify an existing `session` or `request ID` for the request")?,
+        };
+
+        echoln!("The request has been sent. QueryID: {query_id}");
+        echoln!("Getting a response: ...");
+
+        let mut result = if self.follow {
+            follow(&client, &query_id).await?
+        } else {
+            client.answer(&query_id).await?
+        };
+
+        // display the response
+        if self.json {
+            let mut result = result.response;
+            result.sort_by(|a, b| b.cmp(a));
+
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            return Ok(());
+        }
+
+        // the JSON output keeps the reasoning, see `Query::hide_reasoning`
+        if !self.reasoning {
+            result.hide_reasoning();
+        }
+        let mut display: DisplayAnswer = result.into();
+        display.draw()?;
+
+        Ok(())
+    }
+}
+
+/// Time without events after which the answer is polled.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Prints the state transitions of the query until it is complete.
+async fn follow(client: &ClientWithKey, query_id: &QueryId) -> Result<Query> {
+    let mut events = pin!(client.events(Some(query_id), None).await?);
+    // the query may be complete before the subscription
+    if let Some(query) = complete_answer(client, query_id).await {
+        return Ok(query);
+    }
+
+    loop {
+        match tokio::time::timeout(POLL_INTERVAL, events.next()).await {
+            Ok(Some(event)) => match event? {
+                QueryEvent::Transition { result, .. } => echoln!("{}", node_state(&result)),
+                QueryEvent::Complete { .. } => break,
+            },
+            Ok(None) => break,
+            // the events are lost if the feed lags
+            Err(_) => {
+                if let Some(query) = complete_answer(client, query_id).await {
+                    return Ok(query);
+                }
+            }
+        }
+    }
+    client.answer(query_id).await
+}
+
+async fn complete_answer(client: &ClientWithKey, query_id: &QueryId) -> Option<Query> {
+    client
+        .answer(query_id)
+        .await
+        .ok()
+        .filter(Query::is_complete)
+}
@@ -39,6 +39,23 @@ impl List {
                     .map(|address| address.to_string())
                     .unwrap_or(" - ".into())
             );
+            println!(
+                "   Reputation: relevance {:.1}%, timeouts {:.1}%, errors {:.1}%, latency {:.1}s, tasks {}",
+                node.reputation.relevance,
+                node.reputation.timeout_rate * 100.0,
+                node.reputation.error_rate * 100.0,
+                node.reputation.latency,
+                node.reputation.tasks
+            );
+            if let Some(capabilities) = &node.capabilities {
+                println!(
+                    "   Model: {}, context {}, jobs {}, hardware {:?}",
+                    capabilities.model,
+                    capabilities.context_length,
+                    capabilities.max_jobs,
+                    capabilities.hardware
+                );
+            }
             println!();
         }
 
@@ -8,9 +8,13 @@ use crate::{
 use clap::Parser;
 use cli_utils::Prompt;
 use color_eyre::eyre::{ensure, eyre, ContextCompat, Result};
+use futures::StreamExt as _;
 use orchestrator_client::ClientWithKey;
 use rustyline::DefaultEditor;
-use std::io::{stdin, stdout, Write};
+use std::{
+    io::{stdin, stdout, Write},
+    pin::pin,
+};
 use termion::{
     clear,
     color::{Fg, Reset},
@@ -20,6 +24,11 @@ use termion::{
     raw::IntoRawMode,
 };
 use tracing::{error, instrument};
+use types::ai::{
+    query::{Query, QueryId},
+    request::AiRequestOptions,
+    stream::StreamEvent,
+};
 
 /// Send a question and expect a response
 #[derive(Debug, Parser)]
@@ -43,10 +52,50 @@ pub(crate) struct Question {
     #[arg(short, long, visible_aliases = ["new", "erase"])]
     clean: bool,
 
+    /// Continue the conversation from a past query instead of the last one of the session
+    #[arg(long, conflicts_with = "clean")]
+    resume: Option<QueryId>,
+
     /// JSON output
     #[arg(short, long)]
     json: bool,
 
+    /// Show the reasoning of the models. The JSON output always includes it
+    #[arg(long)]
+    reasoning: bool,
+
+    /// Sampling temperature, from 0 to 2
+    #[arg(long)]
+    temperature: Option<f32>,
+
+    /// Nucleus sampling probability, from 0 to 1
+    #[arg(long)]
+    top_p: Option<f32>,
+
+    /// Maximum number of tokens in the answer
+    #[arg(long)]
+    max_tokens: Option<u32>,
+
+    /// Model family to answer the request, e.g. `deepseek-r1`
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Minimum context window of the model in tokens
+    #[arg(long)]
+    min_context: Option<u64>,
+
+    /// Number of nodes to answer the request
+    #[arg(long)]
+    replication: Option<u64>,
+
+    /// URL the orchestrator posts the completed query to
+    #[arg(long)]
+    callback: Option<String>,
+
+    /// Encrypt the request to the connected nodes, only they and you can read the conversation
+    #[arg(long)]
+    confidential: bool,
+
     #[command(flatten)]
     prompt: Prompt,
 }
@@ -67,7 +116,7 @@ impl Question {
         let mut client: ClientWithKey = profile.client()?;
 
         // session
-        let mut back_query = profile.session(&self.session);
+        let mut back_query = self.resume.or_else(|| profile.session(&self.session));
         if back_query.is_some() && self.clean && self.prompt.prompt_yes("Start a new session?") {
             back_query = None;
         }
@@ -102,7 +151,22 @@ impl Question {
                 client.history(back_query).await?;
             }
 
-            let query_id = client.query(&query).await?;
+            let query_id = if self.confidential {
+                let nodes = client
+                    .nodes()
+                    .await?
+                    .into_iter()
+                    .filter(|node| node.connected)
+                    .map(|node| node.key)
+                    .collect::<Vec<_>>();
+                // the response is inspected by another node
+                ensure!(nodes.len() >= 2, "There are less than 2 connected nodes");
+                client
+                    .query_confidential(&query, self.options(), &nodes)
+                    .await?
+            } else {
+                client.query_with_options(&query, self.options()).await?
+            };
             echoln!("The request has been sent. QueryID: {query_id}");
 
             profiles.set_and_save_session(&self.profile, &self.session, query_id)?;
@@ -110,7 +174,24 @@ impl Question {
             // wait
             echoln!("Waiting for a response...");
 
-            let result = client.answer_wait(&query_id, None).await?;
+            let wait = async {
+                // the confidential answers are not streamed
+                if self.json || self.confidential {
+                    client.answer_wait(&query_id, None).await
+                } else {
+                    answer_stream(&client, &query_id).await
+                }
+            };
+            let mut result = tokio::select! {
+                result = wait => result?,
+                _ = tokio::signal::ctrl_c() => {
+                    echoln!("\nCancelling the request...");
+                    if let Err(err) = client.cancel(&query_id).await {
+                        echoln!("Failed to cancel the request: {err}");
+                    }
+                    client.answer_wait(&query_id, None).await?
+                }
+            };
 
             // display the response
             if self.json {
@@ -121,6 +202,10 @@ impl Question {
                 return Ok(());
             }
 
+            // the JSON output keeps the reasoning, see `Query::hide_reasoning`
+            if !self.reasoning {
+                result.hide_reasoning();
+            }
             let mut display: DisplayAnswer = result.into();
             display.draw()?;
 
@@ -129,6 +214,48 @@ impl Question {
 
         Ok(())
     }
+
+    fn options(&self) -> AiRequestOptions {
+        AiRequestOptions {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            model: self.model.clone(),
+            min_context: self.min_context,
+            replication: self.replication,
+            callback: self.callback.clone(),
+        }
+    }
+}
+
+/// Prints the answer of the first responding node while it is generated.
+async fn answer_stream(client: &ClientWithKey, query_id: &QueryId) -> Result<Query> {
+    let mut events = pin!(client.answer_stream(query_id).await?);
+    let mut node = None;
+    while let Some(event) = events.next().await {
+        match event? {
+            StreamEvent::Chunk {
+                node: key, text, ..
+            } => {
+                if *node.get_or_insert(key) == key {
+                    print!("{text}");
+                    stdout().flush()?;
+                }
+            }
+            StreamEvent::Query(query) if query.is_complete() => {
+                if node.is_some() {
+                    println!("\n");
+                }
+                return Ok(query);
+            }
+            StreamEvent::Query(_) => {}
+        }
+    }
+
+    if node.is_some() {
+        println!("\n");
+    }
+    client.answer_wait(query_id, None).await
 }
 
 fn ask() -> Option<String> {
@@ -11,6 +11,7 @@ use termion::{
     style::{Reset as style_reset, Underline as style_underline},
 };
 use types::ai::{
+    aggregation::FinalAnswer,
     query::{NodeResult, Query},
     response::AiResponse,
 };
@@ -186,7 +187,12 @@ impl From<Query> for DisplayAnswer {
 
         let mut response = value.response;
         response.sort_by(|a, b| b.cmp(a));
-        let answers: Vec<Answer> = response.iter().map(Into::into).collect();
+        let answers: Vec<Answer> = value
+            .answer
+            .iter()
+            .map(Into::into)
+            .chain(response.iter().map(Into::into))
+            .collect();
 
         Self {
             index: 0,
@@ -198,6 +204,11 @@ impl From<Query> for DisplayAnswer {
     }
 }
 
+/// The node and its state, e.g. `1a2b3c4.. (VERIFIED)(90%)`.
+pub(crate) fn node_state(result: &NodeResult) -> String {
+    Answer::from(result).name
+}
+
 #[derive(Debug)]
 struct Answer {
     name: String,
@@ -213,6 +224,11 @@ impl From<&NodeResult> for Answer {
                 body: Body::Error(error.clone()),
                 comment: None,
             },
+            NodeResult::Cancelled(node) => Answer {
+                name: format!("{:.7}.. (CANCELLED)", node.to_string()),
+                body: Body::Error("The query was cancelled".to_string()),
+                comment: None,
+            },
             NodeResult::SentRequest(node) => Answer {
                 name: format!("{:.7}.. (SENDING)", node.to_string()),
                 body: Body::Error("The response has not been received yet".to_string()),
@@ -228,6 +244,12 @@ impl From<&NodeResult> for Answer {
                 ans.name = format!("{:.7}.. (AWAITING VERIFICATION)", ans.name);
                 ans
             }
+            NodeResult::Unverified(response, error) => {
+                let mut ans: Answer = { &response.node_response }.into();
+                ans.name = format!("{:.7}.. (UNVERIFIED)", ans.name);
+                ans.comment = Some(error.clone());
+                ans
+            }
             NodeResult::Verified(response) => {
                 let relevance = &response.result.relevance;
                 let ans: Answer = { &response.result.material.node_response }.into();
@@ -241,12 +263,42 @@ impl From<&NodeResult> for Answer {
     }
 }
 
+impl From<&FinalAnswer> for Answer {
+    fn from(value: &FinalAnswer) -> Self {
+        Answer {
+            name: format!(
+                "{:.7}.. (FINAL)({}, {} agree)",
+                value.node.to_string(),
+                value.strategy,
+                value.supporters.len()
+            ),
+            comment: None,
+            body: Body::Success(value.content.clone()),
+        }
+    }
+}
+
 impl From<&AiResponse> for Answer {
     fn from(value: &AiResponse) -> Self {
+        let body = match &value.reasoning {
+            Some(reasoning) => format!("Reasoning:\n{reasoning}\n\nAnswer:\n{}", value.response),
+            None => value.response.clone(),
+        };
+        let comment = value.truncation.map(|truncation| {
+            let action = if truncation.summarized {
+                "summarized"
+            } else {
+                "removed"
+            };
+            format!(
+                "{} oldest turns of the history were {action} to fit the context of the model",
+                truncation.removed_turns
+            )
+        });
         Answer {
             name: format!("{:.7}..", value.pubkey),
-            comment: None,
-            body: Body::Success(value.response.clone()),
+            comment,
+            body: Body::Success(body),
         }
     }
 }
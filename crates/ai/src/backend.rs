@@ -0,0 +1,120 @@
+#[cfg(feature = "gguf")]
+use crate::gguf::Gguf;
+#[cfg(feature = "mock")]
+use crate::mock::Mock;
+#[cfg(feature = "ollama")]
+use crate::ollama::Llm;
+#[cfg(feature = "openai")]
+use crate::openai::OpenAi;
+#[cfg(feature = "pool")]
+use crate::pool::AiPool;
+use crate::{error::AiError, Ai, Answer, ChunkSender, Question};
+use futures::future::BoxFuture;
+use node_config::llm::LlmConfig;
+
+/// Backend chosen by the configuration.
+#[derive(Clone)]
+pub enum Backend {
+    #[cfg(feature = "ollama")]
+    Ollama(Llm),
+    #[cfg(feature = "openai")]
+    OpenAi(OpenAi),
+    #[cfg(feature = "gguf")]
+    Gguf(Gguf),
+    #[cfg(feature = "pool")]
+    Pool(AiPool<Backend>),
+    #[cfg(feature = "mock")]
+    Mock(Mock),
+}
+
+impl Backend {
+    pub async fn new(config: &LlmConfig) -> Result<Self, AiError> {
+        match config {
+            #[cfg(feature = "ollama")]
+            LlmConfig::Ollama(config) => Ok(Self::Ollama(Llm::new(config).await?)),
+            #[cfg(not(feature = "ollama"))]
+            LlmConfig::Ollama(_) => Err(AiError::Disabled("ollama")),
+            #[cfg(feature = "openai")]
+            LlmConfig::OpenAi(config) => Ok(Self::OpenAi(OpenAi::new(config)?)),
+            #[cfg(not(feature = "openai"))]
+            LlmConfig::OpenAi(_) => Err(AiError::Disabled("openai")),
+            #[cfg(feature = "gguf")]
+            LlmConfig::Gguf(config) => Ok(Self::Gguf(Gguf::new(config).await?)),
+            #[cfg(not(feature = "gguf"))]
+            LlmConfig::Gguf(_) => Err(AiError::Disabled("gguf")),
+            #[cfg(feature = "pool")]
+            LlmConfig::Pool(config) => {
+                let mut members = Vec::with_capacity(config.members.len());
+                for member in &config.members {
+                    members.push(Box::pin(Self::new(&member.llm)).await?);
+                }
+                Ok(Self::Pool(AiPool::new(config, members)?))
+            }
+            #[cfg(not(feature = "pool"))]
+            LlmConfig::Pool(_) => Err(AiError::Disabled("pool")),
+            #[cfg(feature = "mock")]
+            LlmConfig::Mock(config) => Ok(Self::Mock(Mock::new(config))),
+            #[cfg(not(feature = "mock"))]
+            LlmConfig::Mock(_) => Err(AiError::Disabled("mock")),
+        }
+    }
+}
+
+// The futures are boxed: a pool of backends is a backend too, their types would be recursive.
+#[allow(refining_impl_trait)]
+impl Ai for Backend {
+    fn ask(&self, ask: Question) -> BoxFuture<'_, Result<Answer, AiError>> {
+        Box::pin(async move {
+            match self {
+                #[cfg(feature = "ollama")]
+                Backend::Ollama(llm) => llm.ask(ask).await,
+                #[cfg(feature = "openai")]
+                Backend::OpenAi(llm) => llm.ask(ask).await,
+                #[cfg(feature = "gguf")]
+                Backend::Gguf(llm) => llm.ask(ask).await,
+                #[cfg(feature = "pool")]
+                Backend::Pool(llm) => llm.ask(ask).await,
+                #[cfg(feature = "mock")]
+                Backend::Mock(llm) => llm.ask(ask).await,
+            }
+        })
+    }
+
+    fn ask_stream(
+        &self,
+        ask: Question,
+        chunks: ChunkSender,
+    ) -> BoxFuture<'_, Result<Answer, AiError>> {
+        Box::pin(async move {
+            match self {
+                #[cfg(feature = "ollama")]
+                Backend::Ollama(llm) => llm.ask_stream(ask, chunks).await,
+                #[cfg(feature = "openai")]
+                Backend::OpenAi(llm) => llm.ask_stream(ask, chunks).await,
+                #[cfg(feature = "gguf")]
+                Backend::Gguf(llm) => llm.ask_stream(ask, chunks).await,
+                #[cfg(feature = "pool")]
+                Backend::Pool(llm) => llm.ask_stream(ask, chunks).await,
+                #[cfg(feature = "mock")]
+                Backend::Mock(llm) => llm.ask_stream(ask, chunks).await,
+            }
+        })
+    }
+
+    fn embed(&self, texts: Vec<String>) -> BoxFuture<'_, Result<Vec<Vec<f32>>, AiError>> {
+        Box::pin(async move {
+            match self {
+                #[cfg(feature = "ollama")]
+                Backend::Ollama(llm) => llm.embed(texts).await,
+                #[cfg(feature = "openai")]
+                Backend::OpenAi(llm) => llm.embed(texts).await,
+                #[cfg(feature = "gguf")]
+                Backend::Gguf(llm) => llm.embed(texts).await,
+                #[cfg(feature = "pool")]
+                Backend::Pool(llm) => llm.embed(texts).await,
+                #[cfg(feature = "mock")]
+                Backend::Mock(llm) => llm.embed(texts).await,
+            }
+        })
+    }
+}
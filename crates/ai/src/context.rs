@@ -0,0 +1,241 @@
+//! Fitting of the questions into the context window of the model.
+//!
+//! The backends truncate the prompts longer than the context silently, dropping the
+//! beginning of the conversation together with the system instructions.
+//! [`ContextWindow`] cuts the history by the [`ContextPolicy`] before the question is asked.
+
+use crate::{
+    error::AiError, reasoning::split_reasoning, Ai, Answer, ChunkSender, Question, QuestionOptions,
+};
+use node_config::llm::{ContextConfig, ContextPolicy};
+use types::ai::{
+    request::{History, Role},
+    response::Truncation,
+};
+
+/// Tokens of the chat template around each message.
+const MESSAGE_TOKENS: u64 = 4;
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation below. Keep the facts, names, \
+    numbers and decisions needed to continue it. Answer with the summary only.";
+
+/// Approximate number of tokens of a message, with the template around it.
+pub fn approximate_tokens(text: &str, chars_per_token: u64) -> u64 {
+    (text.chars().count() as u64).div_ceil(chars_per_token.max(1)) + MESSAGE_TOKENS
+}
+
+/// Approximate number of tokens of the message and the history of the question.
+pub fn approximate_question_tokens(question: &Question, chars_per_token: u64) -> u64 {
+    approximate_tokens(&question.message, chars_per_token)
+        + question
+            .history
+            .iter()
+            .map(|turn| approximate_tokens(&turn.content, chars_per_token))
+            .sum::<u64>()
+}
+
+/// Applies the [`ContextPolicy`] to the questions of the wrapped backend.
+/// The number of tokens is approximated by the number of characters.
+#[derive(Clone)]
+pub struct ContextWindow<A> {
+    ai: A,
+    context_length: u64,
+    config: ContextConfig,
+}
+
+/// Question cut to fit the context.
+struct Fitted {
+    question: Question,
+    truncation: Option<Truncation>,
+    /// Tokens spent on the summary.
+    tokens: u64,
+}
+
+impl<A> ContextWindow<A>
+where
+    A: Ai + Sync,
+{
+    /// `context_length` is the context of the model in tokens.
+    pub fn new(ai: A, config: &ContextConfig, context_length: u64) -> Self {
+        Self {
+            ai,
+            context_length,
+            config: config.clone(),
+        }
+    }
+
+    /// Approximate number of tokens of a message.
+    pub fn count(&self, text: &str) -> u64 {
+        approximate_tokens(text, self.config.chars_per_token)
+    }
+
+    fn question_tokens(&self, question: &Question) -> u64 {
+        approximate_question_tokens(question, self.config.chars_per_token)
+    }
+
+    /// Tokens of the question, the rest of the context is left for the answer.
+    fn limit(&self, options: &QuestionOptions) -> u64 {
+        let reserve = options
+            .max_tokens
+            .map_or(self.config.reserve_tokens, u64::from);
+        self.context_length.saturating_sub(reserve)
+    }
+
+    async fn fit(&self, question: Question) -> Result<Fitted, AiError> {
+        let limit = self.limit(&question.options);
+        let tokens_before = self.question_tokens(&question);
+        if tokens_before <= limit {
+            return Ok(Fitted {
+                question,
+                truncation: None,
+                tokens: 0,
+            });
+        }
+        let overflow = AiError::ContextOverflow {
+            tokens: tokens_before,
+            limit,
+        };
+
+        let summarize = match self.config.policy {
+            ContextPolicy::Reject => return Err(overflow),
+            ContextPolicy::DropOldest => false,
+            ContextPolicy::Summarize => true,
+        };
+        // the room for the summary
+        let target = if summarize {
+            limit.saturating_sub(u64::from(self.config.summary_tokens) + MESSAGE_TOKENS)
+        } else {
+            limit
+        };
+
+        let Question {
+            message,
+            history,
+            options,
+        } = question;
+        let mut tokens = tokens_before;
+        let mut removed = vec![];
+        let mut kept = vec![];
+        for turn in history {
+            if tokens > target && turn.role != Role::System {
+                tokens -= self.count(&turn.content);
+                removed.push(turn);
+            } else {
+                kept.push(turn);
+            }
+        }
+        if tokens > target {
+            return Err(overflow);
+        }
+
+        let mut summary_tokens = 0;
+        if summarize {
+            let summary = self.summarize(&removed, &options).await?;
+            summary_tokens = summary.tokens;
+            let (_, text) = split_reasoning(&summary.message);
+            let text = text.trim();
+            if !text.is_empty() {
+                // in place of the removed turns, after the leading system turns
+                let index = kept
+                    .iter()
+                    .position(|turn| turn.role != Role::System)
+                    .unwrap_or(kept.len());
+                kept.insert(
+                    index,
+                    History {
+                        content: format!("Summary of the earlier conversation: {text}"),
+                        role: Role::System,
+                    },
+                );
+            }
+        }
+
+        let question = Question {
+            message,
+            history: kept,
+            options,
+        };
+        let truncation = Truncation {
+            removed_turns: removed.len() as u32,
+            summarized: summarize,
+            tokens_before,
+            tokens_after: self.question_tokens(&question),
+        };
+        tracing::info!(?truncation, "the history doesn't fit the context");
+
+        Ok(Fitted {
+            question,
+            truncation: Some(truncation),
+            tokens: summary_tokens,
+        })
+    }
+
+    /// Summarizes the newest of the `turns` fitting the context, the older ones are lost.
+    async fn summarize(
+        &self,
+        turns: &[History],
+        options: &QuestionOptions,
+    ) -> Result<Answer, AiError> {
+        let summary_options = QuestionOptions {
+            seed: options.seed,
+            max_tokens: Some(self.config.summary_tokens),
+            ..Default::default()
+        };
+        let mut budget = self
+            .limit(&summary_options)
+            .saturating_sub(self.count(SUMMARY_PROMPT));
+
+        let mut transcript = vec![];
+        for turn in turns.iter().rev() {
+            let line = format!("{}: {}", role_name(&turn.role), turn.content);
+            let tokens = self.count(&line);
+            if tokens > budget {
+                break;
+            }
+            budget -= tokens;
+            transcript.push(line);
+        }
+        transcript.reverse();
+
+        self.ai
+            .ask(Question {
+                message: format!("{SUMMARY_PROMPT}\n\n{}", transcript.join("\n\n")),
+                history: vec![],
+                options: summary_options,
+            })
+            .await
+    }
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::User => "User",
+        Role::Assistant => "Assistant",
+        Role::System => "System",
+    }
+}
+
+impl<A> Ai for ContextWindow<A>
+where
+    A: Ai + Send + Sync,
+{
+    async fn ask(&self, ask: Question) -> Result<Answer, AiError> {
+        let fitted = self.fit(ask).await?;
+        let mut answer = self.ai.ask(fitted.question).await?;
+        answer.tokens += fitted.tokens;
+        answer.truncation = fitted.truncation;
+        Ok(answer)
+    }
+
+    async fn ask_stream(&self, ask: Question, chunks: ChunkSender) -> Result<Answer, AiError> {
+        let fitted = self.fit(ask).await?;
+        let mut answer = self.ai.ask_stream(fitted.question, chunks).await?;
+        answer.tokens += fitted.tokens;
+        answer.truncation = fitted.truncation;
+        Ok(answer)
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AiError> {
+        self.ai.embed(texts).await
+    }
+}
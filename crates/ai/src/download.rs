@@ -0,0 +1,158 @@
+use crate::error::AiError;
+use node_config::llm::GgufConfig;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tracing::{info, warn};
+
+/// Size of the buffer the model file is hashed with.
+const READ_BUFFER: usize = 1 << 20;
+
+/// Downloads the model to `config.path`. The file appears only after its checksum is verified.
+pub async fn download(config: &GgufConfig) -> Result<(), AiError> {
+    if config.sha256.is_none() && !config.allow_unverified {
+        return Err(AiError::Backend(format!(
+            "The checksum of {} is not configured, set `sha256` or `allow_unverified`",
+            config.url
+        )));
+    }
+    info!(url = %config.url, path = ?config.path, "downloading the model");
+    if let Some(dir) = config.path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let partial = config.path.with_extension("part");
+
+    let mut response = reqwest::get(config.url.as_str())
+        .await?
+        .error_for_status()?;
+    let mut file = tokio::fs::File::create(&partial).await?;
+    let mut hasher = Sha256::new();
+    while let Some(bytes) = response.chunk().await? {
+        hasher.update(&bytes);
+        file.write_all(&bytes).await?;
+    }
+    file.sync_all().await?;
+    drop(file);
+
+    let sha256 = hex::encode(hasher.finalize());
+    match &config.sha256 {
+        Some(expected) if !expected.eq_ignore_ascii_case(&sha256) => {
+            tokio::fs::remove_file(&partial).await?;
+            return Err(AiError::Backend(format!(
+                "Checksum mismatch of {}: expected {expected}, got {sha256}",
+                config.url
+            )));
+        }
+        Some(_) => {}
+        None => warn!(%sha256, "the download of the model is not verified"),
+    }
+    tokio::fs::rename(&partial, &config.path).await?;
+
+    Ok(())
+}
+
+/// Checks the model file already at `config.path` against `config.sha256`.
+/// The file is left in place on mismatch, it may be the one the operator chose.
+pub async fn verify(config: &GgufConfig) -> Result<(), AiError> {
+    let Some(expected) = &config.sha256 else {
+        return Ok(());
+    };
+    info!(path = ?config.path, "verifying the model");
+    let sha256 = file_sha256(&config.path).await?;
+    if !expected.eq_ignore_ascii_case(&sha256) {
+        return Err(AiError::Backend(format!(
+            "Checksum mismatch of {}: expected {expected}, got {sha256}, \
+             remove the file to download it again",
+            config.path.display()
+        )));
+    }
+    Ok(())
+}
+
+async fn file_sha256(path: &Path) -> Result<String, AiError> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; READ_BUFFER];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poem::{get, handler, listener::TcpAcceptor, Route, Server};
+    use tempdir::TempDir;
+
+    const MODEL: &[u8] = b"GGUF model";
+
+    #[handler]
+    fn model() -> &'static [u8] {
+        MODEL
+    }
+
+    fn serve_model() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let app = Route::new().at("/model.gguf", get(model));
+        tokio::spawn(Server::new_with_acceptor(TcpAcceptor::from_std(listener).unwrap()).run(app));
+        format!("http://{addr}/model.gguf")
+    }
+
+    #[tokio::test]
+    async fn test_download() {
+        let tmp = TempDir::new("gguf").unwrap();
+        let url = serve_model();
+        let mut config = GgufConfig {
+            path: tmp.path().join("models").join("model.gguf"),
+            url: url.parse().unwrap(),
+            sha256: Some(hex::encode(Sha256::digest(b"another model"))),
+            ..Default::default()
+        };
+
+        assert!(download(&config).await.is_err());
+        assert!(!config.path.exists());
+        assert!(!config.path.with_extension("part").exists());
+
+        config.sha256 = None;
+        assert!(download(&config).await.is_err());
+        assert!(!config.path.with_extension("part").exists());
+
+        config.sha256 = Some(hex::encode(Sha256::digest(MODEL)).to_uppercase());
+        download(&config).await.unwrap();
+        assert_eq!(std::fs::read(&config.path).unwrap(), MODEL);
+
+        std::fs::remove_file(&config.path).unwrap();
+        config.sha256 = None;
+        config.allow_unverified = true;
+        download(&config).await.unwrap();
+        assert_eq!(std::fs::read(&config.path).unwrap(), MODEL);
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let tmp = TempDir::new("gguf").unwrap();
+        let mut config = GgufConfig {
+            path: tmp.path().join("model.gguf"),
+            sha256: Some(hex::encode(Sha256::digest(MODEL))),
+            ..Default::default()
+        };
+        assert!(verify(&config).await.is_err());
+
+        std::fs::write(&config.path, MODEL).unwrap();
+        verify(&config).await.unwrap();
+
+        std::fs::write(&config.path, b"tampered model").unwrap();
+        assert!(verify(&config).await.is_err());
+        assert!(config.path.exists());
+
+        config.sha256 = None;
+        verify(&config).await.unwrap();
+    }
+}
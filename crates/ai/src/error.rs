@@ -7,7 +7,17 @@ pub enum AiError {
     Ollama(#[from] ollama_rs::error::OllamaError),
     #[error("Internal error")]
     InternalError,
-    #[cfg(feature = "ollama")]
+    #[error("{0} are not supported by the backend")]
+    Unsupported(&'static str),
+    #[cfg(any(feature = "ollama", feature = "openai", feature = "download"))]
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Backend error: {0}")]
+    Backend(String),
+    #[error("The {0} backend is not enabled")]
+    Disabled(&'static str),
+    #[error("The question of about {tokens} tokens doesn't fit the context of {limit} tokens")]
+    ContextOverflow { tokens: u64, limit: u64 },
 }
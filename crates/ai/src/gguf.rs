@@ -0,0 +1,262 @@
+use crate::{
+    download::{download, verify},
+    error::AiError,
+    utf8::take_text,
+    Ai, Answer, ChunkSender, Question, QuestionOptions,
+};
+use llama_cpp_2::{
+    context::params::LlamaContextParams,
+    llama_backend::LlamaBackend,
+    llama_batch::LlamaBatch,
+    model::{params::LlamaModelParams, LlamaChatMessage, LlamaChatTemplate, LlamaModel},
+    sampling::LlamaSampler,
+    send_logs_to_tracing, LogOptions,
+};
+use node_config::llm::GgufConfig;
+use std::{
+    fmt::Display,
+    iter::once,
+    num::NonZeroU32,
+    sync::{Arc, LazyLock, Mutex},
+};
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+use types::ai::request::{History, Role};
+
+/// Restricts the answers to the JSON values, the grammar of llama.cpp `grammars/json.gbnf`.
+const JSON_GRAMMAR: &str = r#"
+root   ::= object
+value  ::= object | array | string | number | ("true" | "false" | "null") ws
+object ::= "{" ws ( string ":" ws value ("," ws string ":" ws value)* )? "}" ws
+array  ::= "[" ws ( value ("," ws value)* )? "]" ws
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" (["\\bfnrt] | "u" [0-9a-fA-F]{4}) )* "\"" ws
+number ::= ("-"? ([0-9] | [1-9] [0-9]{0,15})) ("." [0-9]+)? ([eE] [-+]? [0-9] [1-9]{0,15})? ws
+ws     ::= | " " | "\n" [ \t]{0,20}
+"#;
+
+/// GGUF model run by llama.cpp on the CPU of the node.
+///
+/// The model is loaded once, each request gets its own context of `context_length` tokens.
+#[derive(Clone)]
+pub struct Gguf {
+    model: Arc<LlamaModel>,
+    template: LlamaChatTemplate,
+    context_length: NonZeroU32,
+    threads: Option<i32>,
+    jobs: Arc<Semaphore>,
+    /// llama.cpp can't create the contexts of a model in parallel.
+    new_context: Arc<Mutex<()>>,
+}
+
+impl Gguf {
+    /// Loads the model, downloads it first if the file is missing.
+    /// The existing file is verified against the configured checksum too.
+    pub async fn new(config: &GgufConfig) -> Result<Self, AiError> {
+        if tokio::fs::try_exists(&config.path).await? {
+            verify(config).await?;
+        } else {
+            download(config).await?;
+        }
+
+        let backend = llama_backend()?;
+        let path = config.path.clone();
+        info!(?path, "loading the model");
+        let model = tokio::task::spawn_blocking(move || {
+            LlamaModel::load_from_file(backend, path, &LlamaModelParams::default())
+        })
+        .await
+        .map_err(|_| AiError::InternalError)?
+        .map_err(backend_error)?;
+
+        let template = match model.chat_template(None) {
+            Ok(template) => template,
+            Err(err) => {
+                warn!(%err, "the model has no chat template, using chatml");
+                LlamaChatTemplate::new("chatml").map_err(backend_error)?
+            }
+        };
+        let context_length = u32::try_from(config.context_length)
+            .ok()
+            .and_then(NonZeroU32::new)
+            .ok_or_else(|| {
+                AiError::Backend(format!("Invalid context length {}", config.context_length))
+            })?;
+
+        Ok(Self {
+            model: Arc::new(model),
+            template,
+            context_length,
+            threads: i32::try_from(config.threads).ok().filter(|n| *n > 0),
+            jobs: Arc::new(Semaphore::new(config.max_jobs.max(1) as usize)),
+            new_context: Arc::new(Mutex::new(())),
+        })
+    }
+
+    async fn run(&self, ask: Question, chunks: Option<ChunkSender>) -> Result<Answer, AiError> {
+        let permit = self
+            .jobs
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| AiError::InternalError)?;
+        let llm = self.clone();
+        // The generation can't be cancelled, the job is held until it ends.
+        tokio::task::spawn_blocking(move || {
+            let answer = llm.generate(ask, chunks);
+            drop(permit);
+            answer
+        })
+        .await
+        .map_err(|_| AiError::InternalError)?
+    }
+
+    fn prompt(&self, message: String, history: Vec<History>) -> Result<String, AiError> {
+        let chat = history
+            .into_iter()
+            .map(|History { content, role }| {
+                let role = match role {
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::System => "system",
+                };
+                (role, content)
+            })
+            .chain(once(("user", message)))
+            .map(|(role, content)| LlamaChatMessage::new(role.to_string(), content))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(backend_error)?;
+        self.model
+            .apply_chat_template(&self.template, &chat, true)
+            .map_err(backend_error)
+    }
+
+    fn generate(&self, ask: Question, chunks: Option<ChunkSender>) -> Result<Answer, AiError> {
+        let Question {
+            message,
+            history,
+            options,
+        } = ask;
+        let prompt = self.prompt(message, history)?;
+        let vocab = self.model.vocab();
+        // the chat template already has the special tokens
+        let tokens = vocab.tokenize(prompt.as_bytes(), false, true);
+
+        let n_ctx = self.context_length.get() as usize;
+        if tokens.is_empty() || tokens.len() >= n_ctx {
+            return Err(AiError::Backend(format!(
+                "The prompt of {} tokens doesn't fit the context of {n_ctx} tokens",
+                tokens.len()
+            )));
+        }
+        let limit = options
+            .max_tokens
+            .map_or(n_ctx, |max| n_ctx.min(tokens.len() + max as usize));
+
+        let mut params = LlamaContextParams::default()
+            .with_n_ctx(Some(self.context_length))
+            .with_n_batch(self.context_length.get());
+        if let Some(threads) = self.threads {
+            params = params.with_n_threads(threads).with_n_threads_batch(threads);
+        }
+        let mut ctx = {
+            let _guard = self
+                .new_context
+                .lock()
+                .map_err(|_| AiError::InternalError)?;
+            self.model
+                .new_context(llama_backend()?, params)
+                .map_err(backend_error)?
+        };
+        let mut sampler = sampler(&self.model, &options)?;
+
+        let mut batch = LlamaBatch::new(n_ctx, 1);
+        let last = tokens.len() - 1;
+        for (pos, token) in tokens.iter().enumerate() {
+            batch
+                .add(*token, pos as i32, &[0], pos == last)
+                .map_err(backend_error)?;
+        }
+        ctx.decode(&mut batch).map_err(backend_error)?;
+
+        let mut answer = String::new();
+        let mut send = |text: String| {
+            if let Some(chunks) = &chunks {
+                // the receiver is allowed to stop listening
+                let _ = chunks.unbounded_send(text.clone());
+            }
+            answer.push_str(&text);
+        };
+        let mut pending = Vec::new();
+        let mut pos = tokens.len();
+        while pos < limit {
+            let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+            if vocab.is_eog(token) {
+                break;
+            }
+            pending.extend(vocab.token_to_piece(token, false, None));
+            let text = take_text(&mut pending);
+            if !text.is_empty() {
+                send(text);
+            }
+
+            batch.clear();
+            batch
+                .add(token, pos as i32, &[0], true)
+                .map_err(backend_error)?;
+            pos += 1;
+            ctx.decode(&mut batch).map_err(backend_error)?;
+        }
+        if !pending.is_empty() {
+            send(String::from_utf8_lossy(&pending).into_owned());
+        }
+
+        Ok(Answer {
+            message: answer,
+            // the prompt and the generated tokens
+            tokens: pos as u64,
+            truncation: None,
+        })
+    }
+}
+
+impl Ai for Gguf {
+    async fn ask(&self, ask: Question) -> Result<Answer, AiError> {
+        self.run(ask, None).await
+    }
+
+    async fn ask_stream(&self, ask: Question, chunks: ChunkSender) -> Result<Answer, AiError> {
+        self.run(ask, Some(chunks)).await
+    }
+}
+
+/// llama.cpp is initialized once per process.
+fn llama_backend() -> Result<&'static LlamaBackend, AiError> {
+    static BACKEND: LazyLock<Result<LlamaBackend, String>> = LazyLock::new(|| {
+        send_logs_to_tracing(LogOptions::default());
+        LlamaBackend::init().map_err(|err| err.to_string())
+    });
+    BACKEND
+        .as_ref()
+        .map_err(|err| AiError::Backend(err.clone()))
+}
+
+fn backend_error(err: impl Display) -> AiError {
+    AiError::Backend(err.to_string())
+}
+
+fn sampler(model: &LlamaModel, options: &QuestionOptions) -> Result<LlamaSampler, AiError> {
+    let mut samplers = Vec::new();
+    if options.json {
+        samplers.push(LlamaSampler::grammar(model, JSON_GRAMMAR, "root").map_err(backend_error)?);
+    }
+    if options.temperature > 0.0 {
+        if let Some(top_p) = options.top_p {
+            samplers.push(LlamaSampler::top_p(top_p, 1));
+        }
+        samplers.push(LlamaSampler::temp(options.temperature));
+        samplers.push(LlamaSampler::dist(options.seed as u32));
+    } else {
+        samplers.push(LlamaSampler::greedy());
+    }
+    Ok(LlamaSampler::chain_simple(samplers))
+}
@@ -0,0 +1,178 @@
+//! Grading of the AI responses by a model, shared by the orchestrator and the nodes.
+
+use crate::{error::AiError, reasoning::split_reasoning, Ai, Question};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::HashMap;
+use thiserror::Error;
+use tracing::warn;
+use types::ai::{
+    query::QueryId,
+    request::{AiRequest, History, Role},
+};
+
+pub const SYSTEM_PROMPT: &str = "You act as an evaluator of an AI's performance. You will be provided with a conversation history between a human and an AI. Your task is to analyze the AI's response, assess its quality, and provide a brief verdict in JSON format consisting of two fields:
+'relevance' — a number from 0 to 100, where 0 means the response is completely irrelevant, and 100 means the response fully meets expectations and is accurate.
+'description' — a short textual explanation of the given score.
+Return only a JSON object. Do not include any additional text or commentary before or after the JSON object.";
+
+const REPAIR_PROMPT: &str = "Your answer is not a valid verdict. Answer again with only the JSON object with the requested fields, without any text before or after it. The problem:";
+
+/// Number of times the judge is asked to fix an invalid verdict.
+pub const REPAIR_ATTEMPTS: usize = 2;
+
+#[derive(Debug, Error)]
+pub enum VerdictError {
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(&'static str),
+    #[error("Invalid AI response: {0}")]
+    InvalidAiResponse(#[from] serde_json::Error),
+    #[error("Score of {0} is missing or out of range")]
+    InvalidScore(String),
+    #[error("Ai error: {0}")]
+    Ai(#[from] AiError),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Verdict {
+    #[serde(default)]
+    pub relevance: Option<u8>,
+    pub description: String,
+    /// Scores of the rubric criteria, if requested.
+    #[serde(default)]
+    pub scores: HashMap<String, u8>,
+}
+
+impl Verdict {
+    /// The relevance is required unless the response is scored by the criteria.
+    fn check(&self, criteria: &[String]) -> Result<(), VerdictError> {
+        let in_range = |score: Option<&u8>| score.is_some_and(|score| *score <= 100);
+        if (criteria.is_empty() || self.relevance.is_some()) && !in_range(self.relevance.as_ref()) {
+            return Err(VerdictError::InvalidScore("relevance".to_owned()));
+        }
+        match criteria
+            .iter()
+            .find(|criterion| !in_range(self.scores.get(*criterion)))
+        {
+            Some(criterion) => Err(VerdictError::InvalidScore(criterion.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The conversation and the response to grade, as presented to the judge.
+pub fn question(id: &QueryId, request: &AiRequest, response: &str) -> String {
+    let mut question = String::new();
+
+    let id = id.to_hex();
+    question.push_str(&format!("id: {}\n", id));
+    question.push_str(&format!("history section start {}\n", id));
+    request.history.iter().for_each(|message| {
+        question.push_str(&format!("{}:\n{}\n", message.role, message.content));
+    });
+    question.push_str(&format!("history section end {}\n", id));
+
+    question.push_str(&format!(
+        "user request with id {}:\n{}\n",
+        id, request.message
+    ));
+    question.push_str(&format!("ai response with id {}:\n{}\n", id, response));
+    question
+}
+
+/// Asks the judge for the verdict, the judge is asked to fix the invalid ones.
+/// `criteria` are the names of the scores required in the verdict.
+pub async fn ask_verdict<A: Ai + Sync>(
+    ai: &A,
+    question: Question,
+    criteria: &[String],
+) -> Result<Verdict, VerdictError> {
+    ask_json(ai, question, |answer| parse_verdict(answer, criteria)).await
+}
+
+/// Asks for a JSON answer parsed by `parse`, the model is asked to fix the invalid ones.
+pub async fn ask_json<A, T>(
+    ai: &A,
+    mut question: Question,
+    parse: impl Fn(&str) -> Result<T, VerdictError>,
+) -> Result<T, VerdictError>
+where
+    A: Ai + Sync,
+{
+    question.options.json = true;
+    let mut attempt = 0;
+    loop {
+        let answer = ai.ask(question.clone()).await?;
+        match parse(&answer.message) {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < REPAIR_ATTEMPTS => {
+                warn!("Invalid verdict, asking the judge to repair it: {err}");
+                attempt += 1;
+                question.history.push(History {
+                    content: std::mem::take(&mut question.message),
+                    role: Role::User,
+                });
+                question.history.push(History {
+                    content: split_reasoning(&answer.message).1,
+                    role: Role::Assistant,
+                });
+                question.message = format!("{REPAIR_PROMPT} {err}");
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Extracts the JSON verdict from the judge answer.
+/// The reasoning and the text around the JSON object are ignored.
+pub fn parse_verdict(answer: &str, criteria: &[String]) -> Result<Verdict, VerdictError> {
+    parse_json(answer, |verdict: &Verdict| verdict.check(criteria))
+}
+
+/// Extracts the first JSON object passing the `check` from the answer.
+/// The reasoning and the text around the JSON object are ignored.
+pub fn parse_json<T: DeserializeOwned>(
+    answer: &str,
+    check: impl Fn(&T) -> Result<(), VerdictError>,
+) -> Result<T, VerdictError> {
+    let (_, answer) = split_reasoning(answer);
+    let mut error = VerdictError::InvalidJson("No JSON object found");
+    for (start, _) in answer.match_indices('{') {
+        let mut objects = serde_json::Deserializer::from_str(&answer[start..]).into_iter::<T>();
+        match objects.next() {
+            Some(Ok(value)) => match check(&value) {
+                Ok(()) => return Ok(value),
+                Err(err) => error = err,
+            },
+            Some(Err(err)) if matches!(error, VerdictError::InvalidJson(_)) => error = err.into(),
+            _ => {}
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_verdict;
+
+    #[test]
+    fn test_parse_verdict() {
+        let verdict = parse_verdict(
+            "<think>The answer {maybe} is fine</think>\n```json\n{\"relevance\": 80, \"description\": \"{ok}\"}\n```",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(verdict.relevance, Some(80));
+        assert_eq!(verdict.description, "{ok}");
+
+        let verdict = parse_verdict(
+            "{\"description\": \"ok\", \"scores\": {\"accuracy\": 70}}",
+            &["accuracy".to_owned()],
+        )
+        .unwrap();
+        assert_eq!(verdict.scores["accuracy"], 70);
+
+        assert!(parse_verdict("{\"description\": \"ok\"}", &[]).is_err());
+        assert!(parse_verdict("{\"relevance\": 180, \"description\": \"ok\"}", &[]).is_err());
+        assert!(parse_verdict("no verdict", &[]).is_err());
+    }
+}
@@ -1,14 +1,45 @@
+#[cfg(any(
+    feature = "ollama",
+    feature = "openai",
+    feature = "gguf",
+    feature = "pool",
+    feature = "mock"
+))]
+pub mod backend;
+pub mod context;
+#[cfg(feature = "download")]
+pub mod download;
 pub mod error;
+#[cfg(feature = "gguf")]
+pub mod gguf;
+pub mod judge;
+#[cfg(feature = "mock")]
+pub mod mock;
 #[cfg(feature = "ollama")]
 pub mod ollama;
+#[cfg(feature = "openai")]
+pub mod openai;
+#[cfg(feature = "pool")]
+pub mod pool;
+pub mod reasoning;
+pub mod utf8;
 
 use error::AiError;
-use types::ai::request::History;
+use futures::channel::mpsc::UnboundedSender;
+use types::ai::{request::History, response::Truncation};
 
-#[derive(Debug)]
+/// Receives parts of the answer text as they are generated.
+pub type ChunkSender = UnboundedSender<String>;
+
+#[derive(Debug, Clone)]
 pub struct QuestionOptions {
     pub seed: i32,
     pub temperature: f32,
+    pub top_p: Option<f32>,
+    /// Maximum number of tokens in the answer.
+    pub max_tokens: Option<u32>,
+    /// The answer must be a JSON object. Enforced by the backends supporting structured output.
+    pub json: bool,
 }
 
 impl Default for QuestionOptions {
@@ -16,11 +47,14 @@ impl Default for QuestionOptions {
         Self {
             seed: 0,
             temperature: 0.0,
+            top_p: None,
+            max_tokens: None,
+            json: false,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Question {
     pub message: String,
     pub history: Vec<History>,
@@ -38,7 +72,11 @@ impl Question {
 #[derive(Debug)]
 pub struct Answer {
     pub message: String,
+    /// Tokens used by the request, the prompt and the generated ones, like the usage
+    /// reported by Ollama and the OpenAI-compatible servers.
     pub tokens: u64,
+    /// Set by [`context::ContextWindow`] if the history didn't fit the context.
+    pub truncation: Option<Truncation>,
 }
 
 pub trait Ai {
@@ -46,4 +84,36 @@ pub trait Ai {
         &self,
         ask: Question,
     ) -> impl std::future::Future<Output = Result<Answer, AiError>> + Send;
+
+    /// Same as [`Ai::ask`], but also sends the answer text to `chunks` while it is generated.
+    /// The concatenated chunks are equal to the returned message.
+    fn ask_stream(
+        &self,
+        ask: Question,
+        chunks: ChunkSender,
+    ) -> impl std::future::Future<Output = Result<Answer, AiError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let answer = self.ask(ask).await?;
+            // the receiver is allowed to stop listening
+            let _ = chunks.unbounded_send(answer.message.clone());
+            Ok(answer)
+        }
+    }
+
+    /// Returns an embedding vector for each of the texts, in the same order.
+    fn embed(
+        &self,
+        texts: Vec<String>,
+    ) -> impl std::future::Future<Output = Result<Vec<Vec<f32>>, AiError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            drop(texts);
+            Err(AiError::Unsupported("embeddings"))
+        }
+    }
 }
@@ -0,0 +1,172 @@
+use crate::{error::AiError, Ai, Answer, Question};
+use node_config::llm::MockConfig;
+use rand::Rng as _;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+const EMBEDDING_LEN: usize = 32;
+
+/// Answers without a model. The answer depends on the question only, the verdicts follow
+/// the script, the latency and the injected failures are random.
+#[derive(Clone)]
+pub struct Mock {
+    config: MockConfig,
+    /// Number of the verdicts given, shared by the clones.
+    verdicts: Arc<AtomicUsize>,
+}
+
+impl Mock {
+    pub fn new(config: &MockConfig) -> Self {
+        Self {
+            config: config.clone(),
+            verdicts: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn answer(&self, ask: &Question) -> String {
+        if ask.options.json {
+            let index = self.verdicts.fetch_add(1, Ordering::Relaxed);
+            let last = self.config.verdicts.len().saturating_sub(1);
+            return self
+                .config
+                .verdicts
+                .get(index.min(last))
+                .cloned()
+                .unwrap_or_default();
+        }
+        if self.config.answers.is_empty() {
+            return ask.message.clone();
+        }
+        let index = ask.options.seed.unsigned_abs() as usize % self.config.answers.len();
+        self.config.answers[index].clone()
+    }
+
+    fn latency(&self) -> Duration {
+        let MockConfig {
+            min_latency_millis: min,
+            max_latency_millis: max,
+            ..
+        } = self.config;
+        let millis = if max > min {
+            rand::thread_rng().gen_range(min..=max)
+        } else {
+            min
+        };
+        Duration::from_millis(millis)
+    }
+}
+
+impl Ai for Mock {
+    async fn ask(&self, ask: Question) -> Result<Answer, AiError> {
+        tokio::time::sleep(self.latency()).await;
+
+        let roll = rand::thread_rng().gen_range(0..100u8);
+        if roll < self.config.failure_percent {
+            return Err(AiError::Backend("Injected failure".to_string()));
+        }
+        if roll
+            < self
+                .config
+                .failure_percent
+                .saturating_add(self.config.timeout_percent)
+        {
+            std::future::pending::<()>().await;
+        }
+
+        let message = self.answer(&ask);
+        let tokens = (ask.length() + message.len()) as u64;
+        Ok(Answer {
+            message,
+            tokens,
+            truncation: None,
+        })
+    }
+
+    /// The equal texts have the equal embeddings.
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AiError> {
+        Ok(texts
+            .iter()
+            .map(|text| {
+                let mut embedding = vec![0.0; EMBEDDING_LEN];
+                for byte in text.bytes() {
+                    embedding[byte as usize % EMBEDDING_LEN] += 1.0;
+                }
+                embedding
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QuestionOptions;
+
+    fn question(message: &str, seed: i32, json: bool) -> Question {
+        Question {
+            message: message.to_string(),
+            history: vec![],
+            options: QuestionOptions {
+                seed,
+                json,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_answers() {
+        let echo = Mock::new(&MockConfig::default());
+        let answer = echo.ask(question("hello", 0, false)).await.unwrap();
+        assert_eq!(answer.message, "hello");
+        let verdict = echo.ask(question("hello", 0, true)).await.unwrap();
+        assert_eq!(verdict.message, MockConfig::default().verdicts[0]);
+
+        let scripted = Mock::new(&MockConfig {
+            verdicts: vec!["first".to_string(), "second".to_string()],
+            ..Default::default()
+        });
+        for expected in ["first", "second", "second"] {
+            let verdict = scripted
+                .clone()
+                .ask(question("hello", 0, true))
+                .await
+                .unwrap();
+            assert_eq!(verdict.message, expected);
+        }
+
+        let canned = Mock::new(&MockConfig {
+            answers: vec!["first".to_string(), "second".to_string()],
+            ..Default::default()
+        });
+        for seed in [3, 3, -3] {
+            let answer = canned.ask(question("hello", seed, false)).await.unwrap();
+            assert_eq!(answer.message, "second");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_injection() {
+        let failing = Mock::new(&MockConfig {
+            failure_percent: 100,
+            ..Default::default()
+        });
+        assert!(failing.ask(question("hello", 0, false)).await.is_err());
+
+        let silent = Mock::new(&MockConfig {
+            timeout_percent: 100,
+            ..Default::default()
+        });
+        let answer = tokio::time::timeout(
+            Duration::from_millis(100),
+            silent.ask(question("hello", 0, false)),
+        )
+        .await;
+        assert!(answer.is_err());
+    }
+}
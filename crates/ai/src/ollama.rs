@@ -1,13 +1,16 @@
 extern crate ollama_rs;
 
-use crate::{error::AiError, Ai, Answer, Question};
+use crate::{error::AiError, Ai, Answer, ChunkSender, Question};
 use backon::{FibonacciBuilder, Retryable};
+use futures::StreamExt as _;
 use node_config::llm::OllamaConfig;
 use ollama_rs::{
     error::OllamaError,
     generation::{
         chat::{request::ChatMessageRequest, ChatMessage},
+        embeddings::request::GenerateEmbeddingsRequest,
         options::GenerationOptions,
+        parameters::FormatType,
     },
     Ollama,
 };
@@ -20,6 +23,7 @@ use types::ai::request::{History, Role};
 pub struct Llm {
     ollama: Ollama,
     model: String,
+    context_length: u64,
     limiter: Arc<Ratelimiter>,
     retry_limit: usize,
 }
@@ -65,6 +69,7 @@ impl Llm {
         Ok(Llm {
             ollama,
             model: config.model.clone(),
+            context_length: config.context_length,
             limiter,
             retry_limit: config.retry_limit,
         })
@@ -73,16 +78,13 @@ impl Llm {
     pub fn update_model(&mut self, model: String) {
         self.model = model;
     }
-}
 
-impl Ai for Llm {
-    async fn ask(&self, ask: Question) -> Result<Answer, AiError> {
+    fn chat_request(&self, ask: Question) -> ChatMessageRequest {
         let Question {
             message,
             history,
             options,
         } = ask;
-        let msg_len = message.len();
         let history = history
             .into_iter()
             .map(|History { content, role }| match role {
@@ -92,29 +94,52 @@ impl Ai for Llm {
             })
             .chain(once(ChatMessage::user(message)))
             .collect::<Vec<ChatMessage>>();
-        let req = ChatMessageRequest::new(self.model.clone(), history).options(
-            GenerationOptions::default()
-                .temperature(options.temperature)
-                .seed(options.seed),
-        );
+        let mut generation = GenerationOptions::default()
+            .temperature(options.temperature)
+            .seed(options.seed)
+            .num_ctx(self.context_length);
+        if let Some(top_p) = options.top_p {
+            generation = generation.top_p(top_p);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            generation = generation.num_predict(max_tokens.try_into().unwrap_or(i32::MAX));
+        }
+        let request = ChatMessageRequest::new(self.model.clone(), history).options(generation);
+        if options.json {
+            request.format(FormatType::Json)
+        } else {
+            request
+        }
+    }
 
+    async fn wait_limit(&self) {
         if let Err(sleep) = self.limiter.try_wait() {
             tracing::warn!(?sleep, "Rate limit exceeded");
             tokio::time::sleep(sleep).await;
         }
+    }
+}
+
+fn is_retryable(err: &OllamaError) -> bool {
+    if let OllamaError::ReqwestError(err) = err {
+        err.is_timeout()
+    } else {
+        true
+    }
+}
+
+impl Ai for Llm {
+    async fn ask(&self, ask: Question) -> Result<Answer, AiError> {
+        let msg_len = ask.message.len();
+        let req = self.chat_request(ask);
+        self.wait_limit().await;
 
         let response = (|| async { self.ollama.send_chat_messages(req.clone()).await })
             .retry(FibonacciBuilder::default().with_max_times(self.retry_limit))
             .notify(|e, _| {
                 tracing::error!(%e,"error when request ollama");
             })
-            .when(|e| {
-                if let OllamaError::ReqwestError(err) = e {
-                    err.is_timeout()
-                } else {
-                    true
-                }
-            })
+            .when(is_retryable)
             .await?;
 
         let tokens = match response.final_data {
@@ -128,6 +153,70 @@ impl Ai for Llm {
         Ok(Answer {
             message: response.message.content,
             tokens,
+            truncation: None,
         })
     }
+
+    async fn ask_stream(&self, ask: Question, chunks: ChunkSender) -> Result<Answer, AiError> {
+        let msg_len = ask.message.len();
+        let req = self.chat_request(ask);
+        self.wait_limit().await;
+
+        // Only opening the stream is retried: the sent chunks can't be taken back.
+        let mut stream = (|| async { self.ollama.send_chat_messages_stream(req.clone()).await })
+            .retry(FibonacciBuilder::default().with_max_times(self.retry_limit))
+            .notify(|e, _| {
+                tracing::error!(%e,"error when request ollama");
+            })
+            .when(is_retryable)
+            .await?;
+
+        let mut message = String::new();
+        let mut final_data = None;
+        while let Some(response) = stream.next().await {
+            let response = response.map_err(|_| {
+                tracing::error!("ollama stream was interrupted");
+                AiError::InternalError
+            })?;
+            if !response.message.content.is_empty() {
+                // the receiver is allowed to stop listening
+                let _ = chunks.unbounded_send(response.message.content.clone());
+                message.push_str(&response.message.content);
+            }
+            if response.final_data.is_some() {
+                final_data = response.final_data;
+            }
+        }
+
+        let tokens = match final_data {
+            Some(data) => (data.prompt_eval_count + data.eval_count) as u64,
+            None => {
+                warn!("response.final_data is None!");
+                (message.len() + msg_len) as u64
+            }
+        };
+
+        Ok(Answer {
+            message,
+            tokens,
+            truncation: None,
+        })
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AiError> {
+        self.wait_limit().await;
+
+        let response = (|| async {
+            let req = GenerateEmbeddingsRequest::new(self.model.clone(), texts.clone().into());
+            self.ollama.generate_embeddings(req).await
+        })
+        .retry(FibonacciBuilder::default().with_max_times(self.retry_limit))
+        .notify(|e, _| {
+            tracing::error!(%e,"error when request ollama");
+        })
+        .when(is_retryable)
+        .await?;
+
+        Ok(response.embeddings)
+    }
 }
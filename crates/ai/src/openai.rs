@@ -0,0 +1,263 @@
+use crate::{
+    context::{approximate_question_tokens, approximate_tokens},
+    error::AiError,
+    Ai, Answer, ChunkSender, Question,
+};
+use backon::{FibonacciBuilder, Retryable};
+use node_config::llm::{ContextConfig, OpenAiConfig};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::{iter::once, time::Duration};
+use tracing::warn;
+use types::ai::request::{History, Role};
+
+/// Client of a server with the OpenAI-compatible `/chat/completions` API.
+#[derive(Clone)]
+pub struct OpenAi {
+    client: reqwest::Client,
+    endpoint: Url,
+    api_key: Option<String>,
+    model: String,
+    retry_limit: usize,
+}
+
+impl OpenAi {
+    pub fn new(config: &OpenAiConfig) -> Result<Self, AiError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout))
+            .build()?;
+        let endpoint = format!(
+            "{}/chat/completions",
+            config.url.as_str().trim_end_matches('/')
+        )
+        .parse()
+        .map_err(|err| AiError::Backend(format!("Invalid URL: {err}")))?;
+
+        Ok(Self {
+            client,
+            endpoint,
+            api_key: config.api_key.clone(),
+            model: config.model.clone(),
+            retry_limit: config.retry_limit,
+        })
+    }
+
+    fn chat_request(&self, ask: Question, stream: bool) -> ChatRequest {
+        let Question {
+            message,
+            history,
+            options,
+        } = ask;
+        let messages = history
+            .into_iter()
+            .map(|History { content, role }| ChatMessage {
+                role: match role {
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::System => "system",
+                },
+                content,
+            })
+            .chain(once(ChatMessage {
+                role: "user",
+                content: message,
+            }))
+            .collect();
+
+        ChatRequest {
+            model: self.model.clone(),
+            messages,
+            temperature: options.temperature,
+            top_p: options.top_p,
+            max_tokens: options.max_tokens,
+            seed: options.seed,
+            stream,
+            stream_options: stream.then_some(StreamOptions {
+                include_usage: true,
+            }),
+            response_format: options.json.then_some(ResponseFormat {
+                kind: "json_object",
+            }),
+        }
+    }
+
+    async fn send(&self, request: &ChatRequest) -> Result<reqwest::Response, AiError> {
+        let response = (|| async {
+            let mut builder = self.client.post(self.endpoint.clone()).json(request);
+            if let Some(api_key) = &self.api_key {
+                builder = builder.bearer_auth(api_key);
+            }
+            builder.send().await?.error_for_status()
+        })
+        .retry(FibonacciBuilder::default().with_max_times(self.retry_limit))
+        .notify(|e, _| {
+            tracing::error!(%e,"error when request the OpenAI API");
+        })
+        .when(is_retryable)
+        .await?;
+
+        Ok(response)
+    }
+}
+
+fn is_retryable(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => err.is_timeout() || err.is_connect(),
+    }
+}
+
+/// Tokens of the question and the answer. The servers not reporting the usage are
+/// counted like the [`ContextWindow`](crate::context::ContextWindow) does by default.
+fn tokens(usage: Option<Usage>, question_tokens: u64, message: &str) -> u64 {
+    match usage {
+        Some(usage) => usage.total_tokens,
+        None => {
+            warn!("the response has no usage");
+            question_tokens + approximate_tokens(message, ContextConfig::default().chars_per_token)
+        }
+    }
+}
+
+fn question_tokens(ask: &Question) -> u64 {
+    approximate_question_tokens(ask, ContextConfig::default().chars_per_token)
+}
+
+impl Ai for OpenAi {
+    async fn ask(&self, ask: Question) -> Result<Answer, AiError> {
+        let question_tokens = question_tokens(&ask);
+        let request = self.chat_request(ask, false);
+
+        let response: ChatResponse = self.send(&request).await?.json().await?;
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or_else(|| AiError::Backend("The response has no choices".to_string()))?;
+        let tokens = tokens(response.usage, question_tokens, &message);
+
+        Ok(Answer {
+            message,
+            tokens,
+            truncation: None,
+        })
+    }
+
+    async fn ask_stream(&self, ask: Question, chunks: ChunkSender) -> Result<Answer, AiError> {
+        let question_tokens = question_tokens(&ask);
+        let request = self.chat_request(ask, true);
+
+        // Only opening the stream is retried: the sent chunks can't be taken back.
+        let mut response = self.send(&request).await?;
+
+        let mut message = String::new();
+        let mut usage = None;
+        let mut buf = Vec::new();
+        'stream: while let Some(bytes) = response.chunk().await? {
+            buf.extend_from_slice(&bytes);
+            while let Some(end) = buf.iter().position(|b| *b == b'\n') {
+                let line = buf.drain(..=end).collect::<Vec<_>>();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim_start();
+                if data == "[DONE]" {
+                    break 'stream;
+                }
+
+                let chunk: ChatChunk = serde_json::from_str(data).map_err(|err| {
+                    AiError::Backend(format!("Invalid stream chunk `{data}`: {err}"))
+                })?;
+                if let Some(content) = chunk
+                    .choices
+                    .into_iter()
+                    .filter_map(|choice| choice.delta.content)
+                    .find(|content| !content.is_empty())
+                {
+                    // the receiver is allowed to stop listening
+                    let _ = chunks.unbounded_send(content.clone());
+                    message.push_str(&content);
+                }
+                if chunk.usage.is_some() {
+                    usage = chunk.usage;
+                }
+            }
+        }
+        let tokens = tokens(usage, question_tokens, &message);
+
+        Ok(Answer {
+            message,
+            tokens,
+            truncation: None,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    seed: i32,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    delta: ResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    total_tokens: u64,
+}
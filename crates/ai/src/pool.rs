@@ -0,0 +1,343 @@
+use crate::{error::AiError, Ai, Answer, ChunkSender, Question, QuestionOptions};
+use futures::{
+    channel::mpsc,
+    future::{join, join_all, ready},
+    StreamExt as _,
+};
+use node_config::llm::{PoolConfig, PoolStrategy};
+use ratelimit::Ratelimiter;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Several backends answering as one. A request goes to one of the available
+/// backends and fails over to the others on errors.
+///
+/// The circuit of a backend opens after `failure_threshold` consecutive failures,
+/// the backend is skipped for `open_secs`, then it gets a request again.
+/// The health checks close the circuits of the recovered backends.
+pub struct AiPool<A> {
+    members: Arc<Vec<Member<A>>>,
+    strategy: PoolStrategy,
+    next: Arc<AtomicUsize>,
+}
+
+impl<A> Clone for AiPool<A> {
+    fn clone(&self) -> Self {
+        Self {
+            members: self.members.clone(),
+            strategy: self.strategy,
+            next: self.next.clone(),
+        }
+    }
+}
+
+struct Member<A> {
+    ai: A,
+    name: String,
+    in_flight: AtomicUsize,
+    limiter: Option<Ratelimiter>,
+    circuit: Mutex<Circuit>,
+    failure_threshold: u32,
+    open_for: Duration,
+}
+
+#[derive(Default)]
+struct Circuit {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl<A> AiPool<A>
+where
+    A: Ai + Clone + Send + Sync + 'static,
+{
+    /// `backends` are built from `config.members`, in the same order.
+    /// All the members must serve the same model: the pool reports the model of the first one.
+    /// Starts the health checks, must be called within the Tokio runtime.
+    pub fn new(config: &PoolConfig, backends: Vec<A>) -> Result<Self, AiError> {
+        let Some(first) = config.members.first() else {
+            return Err(AiError::Backend("The pool has no members".to_string()));
+        };
+        let model = first.llm.capabilities().model;
+        if let Some(other) = config
+            .members
+            .iter()
+            .map(|member| member.llm.capabilities().model)
+            .find(|other| *other != model)
+        {
+            return Err(AiError::Backend(format!(
+                "The members of the pool serve different models: {model} and {other}"
+            )));
+        }
+        if backends.len() != config.members.len() {
+            return Err(AiError::Backend(format!(
+                "The pool has {} members but {} backends",
+                config.members.len(),
+                backends.len()
+            )));
+        }
+        let members = backends
+            .into_iter()
+            .zip(&config.members)
+            .enumerate()
+            .map(|(index, (ai, member))| {
+                let name = format!("{index}:{}", member.llm.capabilities().model);
+                let limiter = if member.req_per_time > 0 {
+                    if member.time_millis == 0 {
+                        return Err(AiError::Backend(format!(
+                            "The rate limit of the member {name} has no time_millis"
+                        )));
+                    }
+                    let limiter = Ratelimiter::builder(
+                        member.req_per_time,
+                        Duration::from_millis(member.time_millis),
+                    )
+                    .max_tokens(member.req_per_time)
+                    .initial_available(member.req_per_time)
+                    .build()
+                    .map_err(|err| {
+                        AiError::Backend(format!("Invalid rate limit of the member {name}: {err}"))
+                    })?;
+                    Some(limiter)
+                } else {
+                    None
+                };
+                Ok(Member {
+                    ai,
+                    name,
+                    in_flight: AtomicUsize::new(0),
+                    limiter,
+                    circuit: Mutex::default(),
+                    failure_threshold: config.failure_threshold.max(1),
+                    open_for: Duration::from_secs(config.open_secs),
+                })
+            })
+            .collect::<Result<Vec<_>, AiError>>()?;
+        let pool = Self {
+            members: Arc::new(members),
+            strategy: config.strategy,
+            next: Arc::new(AtomicUsize::new(0)),
+        };
+
+        if config.health_check_secs > 0 {
+            let members = Arc::downgrade(&pool.members);
+            let interval = Duration::from_secs(config.health_check_secs);
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    // the pool is dropped
+                    let Some(members) = members.upgrade() else {
+                        break;
+                    };
+                    join_all(members.iter().map(Member::check)).await;
+                }
+            });
+        }
+        Ok(pool)
+    }
+
+    /// Number of the backends with the closed circuits.
+    pub fn available(&self) -> usize {
+        let now = Instant::now();
+        self.members
+            .iter()
+            .filter(|member| member.is_available(now))
+            .count()
+    }
+
+    /// Available backends in the order of the strategy, except the tried ones.
+    fn candidates(&self, tried: &[bool]) -> Vec<usize> {
+        let now = Instant::now();
+        let len = self.members.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut candidates = (0..len)
+            .map(|i| (start + i) % len)
+            .filter(|&i| !tried[i] && self.members[i].is_available(now))
+            .collect::<Vec<_>>();
+        if self.strategy == PoolStrategy::LeastLoaded {
+            // the stable sort keeps the round robin among the equally loaded ones
+            candidates.sort_by_key(|&i| self.members[i].in_flight.load(Ordering::Relaxed));
+        }
+        candidates
+    }
+
+    /// Chooses the next backend, waits if all of them are rate limited.
+    async fn acquire(&self, tried: &[bool]) -> Option<usize> {
+        loop {
+            let candidates = self.candidates(tried);
+            if candidates.is_empty() {
+                return None;
+            }
+            let mut wait = Duration::MAX;
+            for index in candidates {
+                let limit = self.members[index]
+                    .limiter
+                    .as_ref()
+                    .map_or(Ok(()), Ratelimiter::try_wait);
+                match limit {
+                    Ok(()) => return Some(index),
+                    Err(sleep) => wait = wait.min(sleep),
+                }
+            }
+            warn!(?wait, "all the backends of the pool are rate limited");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Calls the backends until one succeeds or `retry` forbids the failover.
+    async fn call<T, F, Fut>(&self, f: F, retry: impl Fn() -> bool) -> Result<T, AiError>
+    where
+        F: Fn(A) -> Fut,
+        Fut: Future<Output = Result<T, AiError>>,
+    {
+        let mut tried = vec![false; self.members.len()];
+        let mut last_error = None;
+        while let Some(index) = self.acquire(&tried).await {
+            tried[index] = true;
+            let member = &self.members[index];
+
+            let result = {
+                let _load = Load::new(&member.in_flight);
+                f(member.ai.clone()).await
+            };
+
+            match result {
+                Ok(value) => {
+                    member.succeeded();
+                    return Ok(value);
+                }
+                // not a failure of the backend, the others may support it
+                Err(err @ AiError::Unsupported(_)) => last_error = Some(err),
+                Err(err) => {
+                    warn!(backend = member.name, %err, "pool backend failed");
+                    member.failed();
+                    if !retry() {
+                        return Err(err);
+                    }
+                    last_error = Some(err);
+                }
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| AiError::Backend("No available backends in the pool".to_string())))
+    }
+}
+
+impl<A> Member<A>
+where
+    A: Ai + Sync,
+{
+    fn is_available(&self, now: Instant) -> bool {
+        let circuit = self.circuit.lock().unwrap();
+        circuit.open_until.map_or(true, |until| now >= until)
+    }
+
+    fn succeeded(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+        if circuit.open_until.is_some() {
+            info!(backend = self.name, "pool backend recovered");
+        }
+        *circuit = Circuit::default();
+    }
+
+    fn failed(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+        circuit.failures = circuit.failures.saturating_add(1);
+        if circuit.failures >= self.failure_threshold {
+            warn!(
+                backend = self.name,
+                failures = circuit.failures,
+                "pool backend circuit is open"
+            );
+            circuit.open_until = Some(Instant::now() + self.open_for);
+        }
+    }
+
+    async fn check(&self) {
+        let ping = Question {
+            message: "ping".to_string(),
+            history: vec![],
+            options: QuestionOptions {
+                max_tokens: Some(1),
+                ..Default::default()
+            },
+        };
+        match self.ai.ask(ping).await {
+            Ok(_) => self.succeeded(),
+            Err(err) => {
+                warn!(backend = self.name, %err, "pool backend health check failed");
+                self.failed();
+            }
+        }
+    }
+}
+
+/// Request in progress on a backend.
+struct Load<'a>(&'a AtomicUsize);
+
+impl<'a> Load<'a> {
+    fn new(in_flight: &'a AtomicUsize) -> Self {
+        in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(in_flight)
+    }
+}
+
+impl Drop for Load<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<A> Ai for AiPool<A>
+where
+    A: Ai + Clone + Send + Sync + 'static,
+{
+    async fn ask(&self, ask: Question) -> Result<Answer, AiError> {
+        self.call(
+            |ai| {
+                let ask = ask.clone();
+                async move { ai.ask(ask).await }
+            },
+            || true,
+        )
+        .await
+    }
+
+    /// Fails over only until the first chunk is sent.
+    async fn ask_stream(&self, ask: Question, chunks: ChunkSender) -> Result<Answer, AiError> {
+        let sent = AtomicBool::new(false);
+        self.call(
+            |ai| {
+                let (tx, rx) = mpsc::unbounded();
+                let forward = rx.for_each(|chunk| {
+                    sent.store(true, Ordering::Relaxed);
+                    // the receiver is allowed to stop listening
+                    let _ = chunks.unbounded_send(chunk);
+                    ready(())
+                });
+                let ask = ask.clone();
+                async move { join(ai.ask_stream(ask, tx), forward).await.0 }
+            },
+            || !sent.load(Ordering::Relaxed),
+        )
+        .await
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AiError> {
+        self.call(
+            |ai| {
+                let texts = texts.clone();
+                async move { ai.embed(texts).await }
+            },
+            || true,
+        )
+        .await
+    }
+}
@@ -0,0 +1,73 @@
+//! Separation of the reasoning of the reasoning models, like deepseek-r1, from their answers.
+
+const THINK_START: &str = "<think>";
+const THINK_END: &str = "</think>";
+
+/// Splits the `<think>` blocks from the answer.
+/// Returns the reasoning, if any, and the answer without it.
+pub fn split_reasoning(text: &str) -> (Option<String>, String) {
+    let mut reasoning = vec![];
+    let mut rest = text;
+    // the opening tag may be a part of the chat template
+    if let Some(end) = rest.find(THINK_END) {
+        if !rest[..end].contains(THINK_START) {
+            reasoning.push(&rest[..end]);
+            rest = &rest[end + THINK_END.len()..];
+        }
+    }
+
+    let mut answer = String::new();
+    while let Some(start) = rest.find(THINK_START) {
+        answer.push_str(&rest[..start]);
+        let block = &rest[start + THINK_START.len()..];
+        rest = match block.find(THINK_END) {
+            Some(end) => {
+                reasoning.push(&block[..end]);
+                &block[end + THINK_END.len()..]
+            }
+            // the reasoning is cut off
+            None => {
+                reasoning.push(block);
+                ""
+            }
+        };
+    }
+    answer.push_str(rest);
+
+    let reasoning = reasoning
+        .iter()
+        .map(|block| block.trim())
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    (
+        (!reasoning.is_empty()).then_some(reasoning),
+        answer.trim().to_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_reasoning;
+
+    #[test]
+    fn test_split_reasoning() {
+        assert_eq!(
+            split_reasoning("<think>\na {b}\n</think>\n\n{}"),
+            (Some("a {b}".to_owned()), "{}".to_owned())
+        );
+        assert_eq!(
+            split_reasoning("a {b}</think>{}"),
+            (Some("a {b}".to_owned()), "{}".to_owned())
+        );
+        assert_eq!(
+            split_reasoning("{}<think>a {b}"),
+            (Some("a {b}".to_owned()), "{}".to_owned())
+        );
+        assert_eq!(
+            split_reasoning("<think></think>{} "),
+            (None, "{}".to_owned())
+        );
+        assert_eq!(split_reasoning("{}"), (None, "{}".to_owned()));
+    }
+}
@@ -0,0 +1,28 @@
+//! Decoding of the text generated by the backends as bytes.
+
+/// Takes the decoded text from the bytes of the tokens. A token may end in the middle
+/// of a UTF-8 character, its bytes are kept until the next token.
+pub fn take_text(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Err(err) if err.error_len().is_none() => err.valid_up_to(),
+        _ => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+    pending.drain(..valid);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_text() {
+        let mut pending = "é".as_bytes()[..1].to_vec();
+        assert_eq!(take_text(&mut pending), "");
+        pending.extend(&"é".as_bytes()[1..]);
+        pending.extend(b"!");
+        assert_eq!(take_text(&mut pending), "é!");
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use ai::{context::ContextWindow, error::AiError, Ai, Answer, Question, QuestionOptions};
+use node_config::llm::{ContextConfig, ContextPolicy};
+use std::sync::{Arc, Mutex};
+use types::ai::request::{History, Role};
+
+/// Remembers the questions, answers with `summary` to all of them.
+#[derive(Clone, Default)]
+struct Recorder {
+    questions: Arc<Mutex<Vec<Question>>>,
+}
+
+impl Ai for Recorder {
+    async fn ask(&self, ask: Question) -> Result<Answer, AiError> {
+        self.questions.lock().unwrap().push(ask);
+        Ok(Answer {
+            message: "summary".to_string(),
+            tokens: 10,
+            truncation: None,
+        })
+    }
+}
+
+/// 100 tokens with the default config.
+fn turn(role: Role, index: usize) -> History {
+    History {
+        content: format!("{index:0>384}"),
+        role,
+    }
+}
+
+/// A system turn and 8 user and assistant turns, 906 tokens with the message.
+fn question() -> Question {
+    let history = std::iter::once(turn(Role::System, 0))
+        .chain((1..=8).map(|i| {
+            turn(
+                if i % 2 == 1 {
+                    Role::User
+                } else {
+                    Role::Assistant
+                },
+                i,
+            )
+        }))
+        .collect();
+    Question {
+        message: "hello".to_string(),
+        history,
+        options: QuestionOptions {
+            max_tokens: Some(100),
+            ..Default::default()
+        },
+    }
+}
+
+fn window(policy: ContextPolicy, context_length: u64) -> (ContextWindow<Recorder>, Recorder) {
+    let recorder = Recorder::default();
+    let config = ContextConfig {
+        policy,
+        summary_tokens: 100,
+        ..Default::default()
+    };
+    (
+        ContextWindow::new(recorder.clone(), &config, context_length),
+        recorder,
+    )
+}
+
+#[tokio::test]
+async fn test_context_fits() {
+    let (window, recorder) = window(ContextPolicy::Reject, 4096);
+
+    let answer = window.ask(question()).await.unwrap();
+    assert!(answer.truncation.is_none());
+    assert_eq!(recorder.questions.lock().unwrap()[0].history.len(), 9);
+}
+
+#[tokio::test]
+async fn test_context_drop_oldest() {
+    let (window, recorder) = window(ContextPolicy::DropOldest, 600);
+
+    let answer = window.ask(question()).await.unwrap();
+    let truncation = answer.truncation.unwrap();
+    assert_eq!(truncation.removed_turns, 5);
+    assert!(!truncation.summarized);
+    assert_eq!(truncation.tokens_before, 906);
+    assert_eq!(truncation.tokens_after, 406);
+
+    let questions = recorder.questions.lock().unwrap();
+    let history = &questions[0].history;
+    assert_eq!(history.len(), 4);
+    assert_eq!(history[0], turn(Role::System, 0));
+    assert_eq!(history[1], turn(Role::Assistant, 6));
+    assert_eq!(history[3], turn(Role::Assistant, 8));
+}
+
+#[tokio::test]
+async fn test_context_summarize() {
+    let (window, recorder) = window(ContextPolicy::Summarize, 600);
+
+    let answer = window.ask(question()).await.unwrap();
+    let truncation = answer.truncation.unwrap();
+    assert_eq!(truncation.removed_turns, 6);
+    assert!(truncation.summarized);
+    assert!(truncation.tokens_after <= 500);
+    // the summary and the answer
+    assert_eq!(answer.tokens, 20);
+
+    let questions = recorder.questions.lock().unwrap();
+    assert_eq!(questions.len(), 2);
+    // the newest removed turns fitting the summary request
+    let transcript = &questions[0].message;
+    assert!(transcript.contains(&turn(Role::User, 3).content));
+    assert!(!transcript.contains(&turn(Role::Assistant, 2).content));
+    let history = &questions[1].history;
+    assert_eq!(history.len(), 4);
+    assert_eq!(history[0], turn(Role::System, 0));
+    assert_eq!(history[1].role, Role::System);
+    assert!(history[1].content.ends_with("summary"));
+    assert_eq!(history[2], turn(Role::User, 7));
+}
+
+#[tokio::test]
+async fn test_context_reject() {
+    let (window, recorder) = window(ContextPolicy::Reject, 600);
+    let err = window.ask(question()).await.unwrap_err();
+    assert!(matches!(
+        err,
+        AiError::ContextOverflow {
+            tokens: 906,
+            limit: 500
+        }
+    ));
+    assert!(recorder.questions.lock().unwrap().is_empty());
+
+    // the message doesn't fit even without the history
+    let (window, recorder) = self::window(ContextPolicy::Summarize, 600);
+    let long = Question {
+        message: "a".repeat(4000),
+        ..question()
+    };
+    assert!(matches!(
+        window.ask(long).await,
+        Err(AiError::ContextOverflow { .. })
+    ));
+    assert!(recorder.questions.lock().unwrap().is_empty());
+}
@@ -0,0 +1,125 @@
+#![cfg(feature = "openai")]
+use ai::{openai::OpenAi, Ai, Question, QuestionOptions};
+use futures::{channel::mpsc, StreamExt as _};
+use node_config::llm::OpenAiConfig;
+use poem::{
+    handler, listener::TcpAcceptor, post, web::Json, EndpointExt as _, IntoResponse as _, Request,
+    Response, Route, Server,
+};
+use serde_json::{json, Value};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use types::ai::request::{History, Role};
+
+const API_KEY: &str = "secret";
+
+/// Imitates `/v1/chat/completions`, the first request fails.
+#[handler]
+async fn completions(req: &Request, Json(body): Json<Value>) -> Response {
+    let calls = req.data::<Arc<AtomicUsize>>().unwrap();
+    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+        return Response::builder().status(500.try_into().unwrap()).finish();
+    }
+    assert_eq!(
+        req.header("authorization"),
+        Some(format!("Bearer {API_KEY}").as_str())
+    );
+    assert_eq!(body["model"], "test-model");
+    assert_eq!(body["seed"], 7);
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][1]["role"], "user");
+    assert_eq!(body["messages"][1]["content"], "hello");
+
+    if body["stream"] == true {
+        assert_eq!(body["stream_options"]["include_usage"], true);
+        let events = ["Hel", "lo", "!"]
+            .into_iter()
+            .map(|content| json!({"choices": [{"delta": {"content": content}}]}))
+            .chain([json!({"choices": [], "usage": {"total_tokens": 12}})])
+            .map(|chunk| format!("data: {chunk}\n\n"))
+            .chain(["data: [DONE]\n\n".to_string()])
+            .collect::<String>();
+        Response::builder()
+            .content_type("text/event-stream")
+            .body(events)
+    } else {
+        Json(json!({
+            "choices": [{"message": {"role": "assistant", "content": "Hello!"}}],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 3, "total_tokens": 12},
+        }))
+        .into_response()
+    }
+}
+
+/// Imitates a server that doesn't report the usage.
+#[handler]
+fn completions_without_usage() -> Json<Value> {
+    Json(json!({"choices": [{"message": {"role": "assistant", "content": "Hello!"}}]}))
+}
+
+async fn start_server() -> OpenAiConfig {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let app = Route::new()
+        .at("/v1/chat/completions", post(completions))
+        .at("/bare/v1/chat/completions", post(completions_without_usage))
+        .data(Arc::new(AtomicUsize::new(0)));
+    tokio::spawn(Server::new_with_acceptor(TcpAcceptor::from_std(listener).unwrap()).run(app));
+
+    OpenAiConfig {
+        url: format!("http://{addr}/v1").parse().unwrap(),
+        model: "test-model".to_string(),
+        api_key: Some(API_KEY.to_string()),
+        retry_limit: 1,
+        ..Default::default()
+    }
+}
+
+fn question() -> Question {
+    Question {
+        message: "hello".to_string(),
+        history: vec![History {
+            content: "be polite".to_string(),
+            role: Role::System,
+        }],
+        options: QuestionOptions {
+            seed: 7,
+            ..Default::default()
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_openai_ask() {
+    let llm = OpenAi::new(&start_server().await).unwrap();
+
+    let answer = llm.ask(question()).await.unwrap();
+    assert_eq!(answer.message, "Hello!");
+    assert_eq!(answer.tokens, 12);
+}
+
+#[tokio::test]
+async fn test_openai_stream() {
+    let llm = OpenAi::new(&start_server().await).unwrap();
+
+    let (chunks, rx) = mpsc::unbounded();
+    let answer = llm.ask_stream(question(), chunks).await.unwrap();
+    assert_eq!(answer.message, "Hello!");
+    assert_eq!(answer.tokens, 12);
+    assert_eq!(rx.collect::<Vec<_>>().await, ["Hel", "lo", "!"]);
+}
+
+#[tokio::test]
+async fn test_openai_without_usage() {
+    let mut config = start_server().await;
+    config.url = config.url.join("/bare/v1").unwrap();
+    let llm = OpenAi::new(&config).unwrap();
+
+    let answer = llm.ask(question()).await.unwrap();
+    assert_eq!(answer.message, "Hello!");
+    // "hello", "be polite" and "Hello!" with 4 characters per token and 4 around each message
+    assert_eq!(answer.tokens, 6 + 7 + 6);
+}
@@ -0,0 +1,151 @@
+#![cfg(all(feature = "pool", feature = "mock"))]
+use ai::{mock::Mock, pool::AiPool, Ai, Question, QuestionOptions};
+use futures::{channel::mpsc, StreamExt as _};
+use node_config::llm::{LlmConfig, MockConfig, PoolConfig, PoolMemberConfig, PoolStrategy};
+use std::time::{Duration, Instant};
+
+fn pool(members: &[(MockConfig, u64)], config: PoolConfig) -> AiPool<Mock> {
+    let config = PoolConfig {
+        members: members
+            .iter()
+            .map(|(mock, req_per_time)| PoolMemberConfig {
+                llm: LlmConfig::Mock(mock.clone()),
+                req_per_time: *req_per_time,
+                time_millis: 200,
+            })
+            .collect(),
+        health_check_secs: 0,
+        ..config
+    };
+    let backends = members.iter().map(|(mock, _)| Mock::new(mock)).collect();
+    AiPool::new(&config, backends).unwrap()
+}
+
+fn answering(answer: &str) -> MockConfig {
+    MockConfig {
+        answers: vec![answer.to_string()],
+        ..Default::default()
+    }
+}
+
+fn failing() -> MockConfig {
+    MockConfig {
+        failure_percent: 100,
+        ..Default::default()
+    }
+}
+
+fn question() -> Question {
+    Question {
+        message: "hello".to_string(),
+        history: vec![],
+        options: QuestionOptions::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_pool_round_robin() {
+    let pool = pool(
+        &[(answering("first"), 0), (answering("second"), 0)],
+        PoolConfig {
+            strategy: PoolStrategy::RoundRobin,
+            ..Default::default()
+        },
+    );
+
+    let mut answers = vec![];
+    for _ in 0..4 {
+        answers.push(pool.ask(question()).await.unwrap().message);
+    }
+    assert_eq!(answers, ["first", "second", "first", "second"]);
+}
+
+#[tokio::test]
+async fn test_pool_failover() {
+    let pool = pool(
+        &[(failing(), 0), (answering("ok"), 0)],
+        PoolConfig {
+            failure_threshold: 2,
+            ..Default::default()
+        },
+    );
+
+    for _ in 0..4 {
+        assert_eq!(pool.ask(question()).await.unwrap().message, "ok");
+    }
+    assert_eq!(pool.available(), 1);
+
+    let (chunks, rx) = mpsc::unbounded();
+    let answer = pool.ask_stream(question(), chunks).await.unwrap();
+    assert_eq!(answer.message, "ok");
+    assert_eq!(rx.collect::<Vec<_>>().await, ["ok"]);
+
+    let broken = self::pool(&[(failing(), 0), (failing(), 0)], Default::default());
+    assert!(broken.ask(question()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_pool_rate_limit() {
+    let pool = pool(
+        &[(answering("limited"), 1), (answering("ok"), 0)],
+        Default::default(),
+    );
+
+    let mut answers = vec![];
+    for _ in 0..3 {
+        answers.push(pool.ask(question()).await.unwrap().message);
+    }
+    assert_eq!(answers.iter().filter(|a| *a == "limited").count(), 1);
+
+    let single = self::pool(&[(answering("limited"), 1)], Default::default());
+    let start = Instant::now();
+    for _ in 0..2 {
+        single.ask(question()).await.unwrap();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn test_pool_invalid() {
+    let member = PoolMemberConfig {
+        llm: LlmConfig::Mock(answering("ok")),
+        req_per_time: 1,
+        time_millis: 0,
+    };
+    let config = PoolConfig {
+        members: vec![member.clone()],
+        health_check_secs: 0,
+        ..Default::default()
+    };
+    let backend = || Mock::new(&answering("ok"));
+
+    assert!(AiPool::new(&config, vec![backend()]).is_err());
+    assert!(AiPool::new(&config, vec![backend(), backend()]).is_err());
+
+    let config = PoolConfig {
+        members: vec![PoolMemberConfig {
+            time_millis: 1000,
+            ..member
+        }],
+        ..config
+    };
+    assert!(AiPool::<Mock>::new(&config, vec![]).is_err());
+    assert!(AiPool::new(&config, vec![backend()]).is_ok());
+
+    let empty = PoolConfig {
+        members: vec![],
+        ..config
+    };
+    assert!(AiPool::<Mock>::new(&empty, vec![]).is_err());
+
+    let other = PoolMemberConfig {
+        llm: LlmConfig::Mock(MockConfig {
+            model: "other:latest".to_string(),
+            ..answering("ok")
+        }),
+        ..config.members[0].clone()
+    };
+    let mut mixed = config;
+    mixed.members.push(other);
+    assert!(AiPool::new(&mixed, vec![backend(), backend()]).is_err());
+}
@@ -1,19 +1,39 @@
-use crate::AppState;
+use crate::{
+    signature_auth::{authorize, Requester, SignatureAuth},
+    AppState,
+};
 use crypto::ed25519::public::PublicKey;
+use node_config::api::ReadAuthConfig;
 use poem::{
     get, handler,
     http::StatusCode,
     post,
-    web::{Data, Json, Path, RemoteAddr},
-    IntoResponse, Route,
+    web::{Data, Json, Path, Query, RemoteAddr},
+    EndpointExt, IntoResponse, Route,
 };
 use serde::{Deserialize, Serialize};
 use std::{str::FromStr, sync::Arc};
 use tracing::debug;
+use types::{account::Transaction, ai::query::QuerySummary};
+
+/// Maximum number of transactions returned at once.
+const MAX_TRANSACTIONS_LIMIT: usize = 1_000;
+/// Maximum number of queries returned at once.
+const MAX_QUERIES_LIMIT: usize = 100;
+
+pub fn route(read_auth: &ReadAuthConfig) -> Route {
+    let auth = SignatureAuth {
+        max_age_secs: read_auth.max_age_secs,
+    };
 
-pub fn route() -> Route {
     Route::new()
         .at("/:pubkey", get(handler_account))
+        .at(
+            "/:pubkey/transactions",
+            get(handler_transactions).with_if(read_auth.enabled, auth),
+        )
+        // the query ids give the access to the queries, they are listed to the owner only
+        .at("/:pubkey/queries", get(handler_queries).with(auth))
         .at("/airdrop/:pubkey", post(handler_airdrop))
 }
 
@@ -38,6 +58,64 @@ pub fn handler_account(
     })
 }
 
+#[derive(Debug, Deserialize)]
+pub struct Pagination {
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default)]
+    offset: usize,
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+#[handler]
+pub fn handler_transactions(
+    state: Data<&Arc<AppState>>,
+    Path(pubkey): Path<String>,
+    Query(page): Query<Pagination>,
+    requester: Option<Data<&Requester>>,
+) -> poem::Result<Json<Vec<Transaction>>> {
+    debug!("transactions: {pubkey}, {page:?}");
+
+    let pubkey = PublicKey::from_str(&pubkey)?;
+    authorize(requester, &pubkey)?;
+
+    let transactions = state.storage.ledger.transactions(
+        &pubkey,
+        page.limit.min(MAX_TRANSACTIONS_LIMIT),
+        page.offset,
+    )?;
+    Ok(Json(transactions))
+}
+
+/// Summaries of the queries of the account, the oldest first.
+/// The read must be signed by the owner even without the access control.
+#[handler]
+pub fn handler_queries(
+    state: Data<&Arc<AppState>>,
+    Path(pubkey): Path<String>,
+    Query(page): Query<Pagination>,
+    requester: Data<&Requester>,
+) -> poem::Result<Json<Vec<QuerySummary>>> {
+    debug!("queries: {pubkey}, {page:?}");
+
+    let pubkey = PublicKey::from_str(&pubkey)?;
+    authorize(Some(requester), &pubkey)?;
+
+    let query_table = &state.storage.query_table;
+    let ids =
+        query_table.users_query_ids(&pubkey, page.limit.min(MAX_QUERIES_LIMIT), page.offset)?;
+    let mut summaries = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some(query) = query_table.get_query(&id)? {
+            summaries.push(QuerySummary::from(&query));
+        }
+    }
+    Ok(Json(summaries))
+}
+
 #[handler]
 pub async fn handler_airdrop(
     remote_addr: &RemoteAddr,
@@ -1,22 +1,270 @@
-use crate::AppState;
+use crate::{
+    signature_auth::{authorize, Requester},
+    AppState,
+};
+use crypto::ed25519::public::PublicKey;
+use eyre::eyre;
+use futures::{
+    future::ready,
+    stream::{self, BoxStream},
+    StreamExt as _,
+};
+use orchestrator::{AnswerStream, OrchRequest};
 use poem::{
     handler,
     http::StatusCode,
-    web::{Data, Json, Path},
+    web::{
+        sse::{Event, SSE},
+        Data, Json, Path, Query as QueryParams,
+    },
+};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+use types::ai::{
+    query::{Query, QueryId},
+    stream::StreamEvent,
+    webhook::WebhookDelivery,
 };
-use std::sync::Arc;
-use types::ai::query::{Query, QueryId};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AnswerParams {
+    /// Whether to include the reasoning of the models, included unless `?reasoning=false`.
+    /// See [`Query::hide_reasoning`].
+    #[serde(default = "default_reasoning")]
+    reasoning: bool,
+}
+
+fn default_reasoning() -> bool {
+    true
+}
 
 #[handler]
 pub(crate) async fn handler_answer(
     Path(query_id): Path<QueryId>,
+    QueryParams(params): QueryParams<AnswerParams>,
     state: Data<&Arc<AppState>>,
+    requester: Option<Data<&Requester>>,
 ) -> poem::Result<Json<Query>> {
-    let respose = state
+    let mut respose = state
         .storage
         .query_table
         .get_query(&query_id)?
         .ok_or(StatusCode::PROCESSING)?;
+    authorize(requester, &respose.request.query.pubkey)?;
+    if !params.reasoning {
+        respose.hide_reasoning();
+    }
 
     Ok(Json(respose))
 }
+
+/// Delivery log of the callback of the query.
+#[handler]
+pub(crate) async fn handler_answer_webhook(
+    Path(query_id): Path<QueryId>,
+    state: Data<&Arc<AppState>>,
+    requester: Option<Data<&Requester>>,
+) -> poem::Result<Json<Vec<WebhookDelivery>>> {
+    authorize_query(&state, requester, &query_id)?;
+    Ok(Json(state.storage.webhook_table.get(&query_id)?))
+}
+
+#[handler]
+pub(crate) async fn handler_answer_stream(
+    Path(query_id): Path<QueryId>,
+    QueryParams(params): QueryParams<AnswerParams>,
+    state: Data<&Arc<AppState>>,
+    requester: Option<Data<&Requester>>,
+) -> poem::Result<SSE> {
+    authorize_query(&state, requester, &query_id)?;
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    state
+        .sender
+        .send(OrchRequest::Subscribe { id: query_id, tx })
+        .await
+        .map_err(|err| eyre!("{err}"))?;
+
+    let events: BoxStream<'static, StreamEvent> = match rx.await.ok().flatten() {
+        Some(AnswerStream { snapshot, events }) => {
+            let events = stream::unfold(events, |mut events| async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => return Some((event, events)),
+                        Err(RecvError::Lagged(count)) => {
+                            warn!("Answer stream lagged by {count} events");
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            });
+            stream::iter(snapshot).chain(events).boxed()
+        }
+        // the query is not in progress, so only its last state is sent
+        None => {
+            let query = state
+                .storage
+                .query_table
+                .get_query(&query_id)?
+                .ok_or(StatusCode::NOT_FOUND)?;
+            stream::once(async move { StreamEvent::Query(query) }).boxed()
+        }
+    };
+
+    let mut filter = (!params.reasoning).then(ReasoningFilter::default);
+    let events = events.filter_map(move |event| {
+        ready(match &mut filter {
+            Some(filter) => filter.apply(event),
+            None => Some(event),
+        })
+    });
+
+    Ok(SSE::new(events.filter_map(|event| async move {
+        match serde_json::to_string(&event) {
+            Ok(data) => Some(Event::message(data).event_type(event.name())),
+            Err(err) => {
+                warn!("Failed to serialize stream event: {err}");
+                None
+            }
+        }
+    }))
+    .keep_alive(Duration::from_secs(15)))
+}
+
+/// Checks the access to the query if it exists.
+fn authorize_query(
+    state: &AppState,
+    requester: Option<Data<&Requester>>,
+    query_id: &QueryId,
+) -> poem::Result<()> {
+    if requester.is_none() {
+        return Ok(());
+    }
+    match state.storage.query_table.get_query(query_id)? {
+        Some(query) => authorize(requester, &query.request.query.pubkey),
+        None => Ok(()),
+    }
+}
+
+const THINK_START: &str = "<think>";
+const THINK_END: &str = "</think>";
+
+/// Hides the reasoning from the answer stream. The `<think>` blocks are cut from the chunks
+/// and the offsets count the text left. The reasoning not opened in the answer, when the chat
+/// template opens it, can't be told from the answer while it's streamed: it's hidden only from
+/// the state of the query.
+#[derive(Default)]
+struct ReasoningFilter {
+    nodes: HashMap<PublicKey, StreamedAnswer>,
+}
+
+#[derive(Default)]
+struct StreamedAnswer {
+    /// Text held back, it may be the beginning of a tag.
+    pending: String,
+    thinking: bool,
+    /// Length of the text sent.
+    offset: u64,
+}
+
+impl ReasoningFilter {
+    /// The chunks of a node are expected in order, like they are sent by the orchestrator.
+    fn apply(&mut self, event: StreamEvent) -> Option<StreamEvent> {
+        match event {
+            StreamEvent::Chunk { node, text, .. } => {
+                let answer = self.nodes.entry(node).or_default();
+                let offset = answer.offset;
+                let text = answer.push(&text);
+                (!text.is_empty()).then_some(StreamEvent::Chunk { node, offset, text })
+            }
+            StreamEvent::Query(mut query) => {
+                query.hide_reasoning();
+                Some(StreamEvent::Query(query))
+            }
+        }
+    }
+}
+
+impl StreamedAnswer {
+    /// Returns the text of the answer outside of the reasoning.
+    fn push(&mut self, chunk: &str) -> String {
+        self.pending.push_str(chunk);
+        let mut text = String::new();
+        loop {
+            let tag = if self.thinking {
+                THINK_END
+            } else {
+                THINK_START
+            };
+            if let Some(start) = self.pending.find(tag) {
+                if !self.thinking {
+                    text.push_str(&self.pending[..start]);
+                }
+                self.pending.drain(..start + tag.len());
+                self.thinking = !self.thinking;
+                continue;
+            }
+            // the tags are ASCII, so the held back part starts at a char boundary
+            let held = (1..tag.len())
+                .rev()
+                .find(|len| self.pending.ends_with(&tag[..*len]))
+                .unwrap_or(0);
+            let released = self.pending.len() - held;
+            if !self.thinking {
+                text.push_str(&self.pending[..released]);
+            }
+            self.pending.drain(..released);
+            break;
+        }
+        // the answer is trimmed like the stored one
+        if self.offset == 0 {
+            text = text.trim_start().to_owned();
+        }
+        self.offset += text.len() as u64;
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamedAnswer;
+
+    fn stream(chunks: &[&str]) -> Vec<(u64, String)> {
+        let mut answer = StreamedAnswer::default();
+        chunks
+            .iter()
+            .filter_map(|chunk| {
+                let offset = answer.offset;
+                let text = answer.push(chunk);
+                (!text.is_empty()).then_some((offset, text))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hide_streamed_reasoning() {
+        assert_eq!(
+            stream(&[
+                "<th",
+                "ink>I should",
+                " greet</thi",
+                "nk>\n\nHel",
+                "lo <",
+                "b>!"
+            ]),
+            vec![
+                (0, "Hel".to_owned()),
+                (3, "lo ".to_owned()),
+                (6, "<b>!".to_owned())
+            ]
+        );
+        assert_eq!(
+            stream(&["Hi<think>", "hmm</think>", " there"]),
+            vec![(0, "Hi".to_owned()), (2, " there".to_owned())]
+        );
+        assert_eq!(stream(&["<think>cut off"]), vec![]);
+        assert_eq!(stream(&["plain ", "answer"]).len(), 2);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{
+    signature_auth::{authorize, Requester},
+    AppState,
+};
+use crypto::ed25519::public::PublicKey;
+use eyre::eyre;
+use futures::{stream, StreamExt as _};
+use orchestrator::OrchRequest;
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{
+        sse::{Event, SSE},
+        Data, Query as QueryParams,
+    },
+};
+use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+use types::ai::{query::QueryId, stream::QueryEvent};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct EventsParams {
+    /// Only the events of the query.
+    query: Option<QueryId>,
+    /// Only the events of the queries of the requester.
+    pubkey: Option<PublicKey>,
+}
+
+impl EventsParams {
+    fn matches(&self, event: &QueryEvent) -> bool {
+        self.query.map_or(true, |id| id == *event.id())
+            && self
+                .pubkey
+                .map_or(true, |pubkey| pubkey == *event.requester())
+    }
+}
+
+/// Feed of the state transitions of the queries in progress. Without the access control
+/// the feed is limited to a single query.
+#[handler]
+pub(crate) async fn handler_events(
+    QueryParams(mut params): QueryParams<EventsParams>,
+    state: Data<&Arc<AppState>>,
+    requester: Option<Data<&Requester>>,
+) -> poem::Result<SSE> {
+    match requester {
+        // only the own queries are visible with the access control
+        Some(Data(requester)) => {
+            let pubkey = *params.pubkey.get_or_insert(requester.0);
+            authorize(Some(Data(requester)), &pubkey)?;
+        }
+        // without it, only the holder of the query id can read the query
+        None if params.query.is_none() => {
+            return Err(poem::Error::from_string(
+                "The `query` filter is required",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        None => {}
+    }
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    state
+        .sender
+        .send(OrchRequest::Events { tx })
+        .await
+        .map_err(|err| eyre!("{err}"))?;
+    let events = rx.await.map_err(|err| eyre!("{err}"))?;
+
+    let events = stream::unfold(events, |mut events| async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => return Some((event, events)),
+                Err(RecvError::Lagged(count)) => {
+                    warn!("Event feed lagged by {count} events");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(SSE::new(events.filter_map(move |event| {
+        let event = params.matches(&event).then(|| {
+            serde_json::to_string(&event)
+                .inspect_err(|err| warn!("Failed to serialize query event: {err}"))
+                .ok()
+                .map(|data| Event::message(data).event_type(event.name()))
+        });
+        async move { event.flatten() }
+    }))
+    .keep_alive(Duration::from_secs(15)))
+}
@@ -1,4 +1,7 @@
-use crate::AppState;
+use crate::{
+    signature_auth::{authorize, Requester},
+    AppState,
+};
 use poem::{
     handler,
     web::{Data, Json, Path},
@@ -11,12 +14,14 @@ use types::ai::{query::QueryId, request::History};
 pub(crate) async fn handler_history(
     Path(query_id): Path<QueryId>,
     state: Data<&Arc<AppState>>,
+    requester: Option<Data<&Requester>>,
 ) -> poem::Result<Json<Vec<History>>> {
     debug!("query_id: {query_id}");
 
     let Some(query) = state.storage.query_table.get_query(&query_id)? else {
         return Ok(Json(Default::default()));
     };
+    authorize(requester, &query.request.query.pubkey)?;
 
     Ok(Json(query.as_history()))
 }
@@ -2,11 +2,13 @@ pub mod account;
 pub mod ai_models;
 pub mod answer;
 pub mod cluster;
+pub mod events;
 pub mod history;
 pub mod jwt_auth;
 pub mod middleware;
 pub mod nodes;
 pub mod query;
+pub mod signature_auth;
 pub mod status;
 
 use crate::middleware::limits::LimitsMap;
@@ -15,13 +17,14 @@ use metrics::Metrics;
 use node_config::api::ApiConfig;
 use orchestrator::ApiSender;
 use poem::{
-    get, handler,
+    delete, get, handler,
     listener::TcpListener,
     middleware::{AddData, AddDataEndpoint, Cors, Tracing, TracingEndpoint},
     post,
     web::{Data, Json},
     EndpointExt, Route, Server,
 };
+use signature_auth::SignatureAuth;
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 use storage::EveStorage;
 use tokio::task::JoinHandle;
@@ -39,13 +42,37 @@ struct AppState {
 }
 
 fn route(state: AppState) -> AddDataEndpoint<TracingEndpoint<Route>, Arc<AppState>> {
+    let read_auth = &state.cfg.read_auth;
+    let auth = SignatureAuth {
+        max_age_secs: read_auth.max_age_secs,
+    };
+
     Route::new()
         .at("/", get(status::handler_status))
         .at("/ai", get(ai_models::handler_ai_model))
         .at("/query", post(query::handler_query))
-        .at("/answer/:query_id", get(answer::handler_answer))
-        .at("/history/:query_id", get(history::handler_history))
-        .nest("/account", account::route())
+        .at("/query/:query_id", delete(query::handler_cancel))
+        .at(
+            "/answer/:query_id",
+            get(answer::handler_answer).with_if(read_auth.enabled, auth),
+        )
+        .at(
+            "/answer/:query_id/stream",
+            get(answer::handler_answer_stream).with_if(read_auth.enabled, auth),
+        )
+        .at(
+            "/answer/:query_id/webhook",
+            get(answer::handler_answer_webhook).with_if(read_auth.enabled, auth),
+        )
+        .at(
+            "/events",
+            get(events::handler_events).with_if(read_auth.enabled, auth),
+        )
+        .at(
+            "/history/:query_id",
+            get(history::handler_history).with_if(read_auth.enabled, auth),
+        )
+        .nest("/account", account::route(read_auth))
         .at("/info", get(status::handler_info))
         .nest("/nodes", nodes::route(state.cfg.jwt))
         .nest("/metrics", get(handler_metrics))
@@ -89,20 +116,25 @@ pub async fn run(
 mod tests {
     use crate::{cluster::Cluster, route, LimitsMap};
     use crypto::ed25519::private::PrivateKey;
-    use node_config::api::ApiConfig;
+    use node_config::api::{ApiConfig, ReadAuthConfig};
     use orchestrator::mock::OrchestratorMock;
+    use orchestrator_client::{Client, ClientWithKey};
     use poem::{
         http::{header::RETRY_AFTER, StatusCode},
         test::TestClient,
     };
     use std::{sync::Arc, time::Duration};
-    use storage::EveStorage;
+    use storage::{ledger::Transfer, EveStorage, WriteSet};
     use tempfile::tempdir;
     use tracing::info;
     use tracing_test::traced_test;
-    use types::ai::{
-        query::{Query, QueryId},
-        request::AiRequest,
+    use types::{
+        account::{Transaction, TransactionKind},
+        ai::{
+            query::{Query, QueryId},
+            request::AiRequest,
+            stream::StreamEvent,
+        },
     };
 
     #[tokio::test]
@@ -177,6 +209,254 @@ mod tests {
         info!("{query:#?}",);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_answer_stream() {
+        let tmp = tempdir().unwrap();
+        let db_path = tmp.path().join("test.db");
+        let db_config = Default::default();
+        let eve = Arc::new(EveStorage::new(&db_path, &db_config).unwrap());
+
+        let user_private_key = PrivateKey::generate();
+        let user_pubkey = user_private_key.public_key();
+
+        let (sender, _handle) = OrchestratorMock::create(eve.clone());
+        let cfg: Arc<ApiConfig> = Default::default();
+
+        let client = TestClient::new(route(crate::AppState {
+            storage: eve.clone(),
+            sender: sender.clone(),
+            ai_limits: LimitsMap::new(cfg.req_per_hour),
+            airdrop_limits: LimitsMap::new(cfg.airdrop_per_hour),
+            cfg: Arc::clone(&cfg),
+            cluster: Cluster::new(sender, Duration::from_secs(cfg.cluster_info_ttl_secs)),
+            metrics: Default::default(),
+        }));
+
+        let response = client
+            .get(format!("/answer/{}/stream", QueryId::default()))
+            .send()
+            .await;
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        let response = client
+            .post("/query")
+            .body_json(
+                &AiRequest::new("test".into(), vec![], user_pubkey)
+                    .sign(&user_private_key)
+                    .unwrap(),
+            )
+            .send()
+            .await;
+        response.assert_status_is_ok();
+        let query_id: QueryId = response.0.into_body().into_json().await.unwrap();
+
+        let response = client
+            .get(format!("/answer/{query_id}/stream"))
+            .send()
+            .await;
+        response.assert_status_is_ok();
+
+        let body = response.0.into_body().into_string().await.unwrap();
+        info!("{body}");
+        let mut lines = body.lines();
+        assert_eq!(lines.next(), Some("event: query"));
+        let data = lines.next().unwrap().strip_prefix("data: ").unwrap();
+        let StreamEvent::Query(query) = serde_json::from_str(data).unwrap() else {
+            panic!("unexpected event: {data}");
+        };
+        assert_eq!(query.id, query_id);
+
+        // the feed of all the queries is open with the access control only
+        let response = client.get("/events").send().await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response = client
+            .get(format!("/events?pubkey={user_pubkey}"))
+            .send()
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response = client.get(format!("/events?query={query_id}")).send().await;
+        response.assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_transactions() {
+        let tmp = tempdir().unwrap();
+        let db_path = tmp.path().join("test.db");
+        let db_config = Default::default();
+        let eve = Arc::new(EveStorage::new(&db_path, &db_config).unwrap());
+        let user_pubkey = PrivateKey::generate().public_key();
+
+        for amount in 1..=3 {
+            eve.ledger
+                .apply(
+                    &[Transfer {
+                        account: user_pubkey,
+                        kind: TransactionKind::Airdrop,
+                        amount,
+                        query: None,
+                    }],
+                    WriteSet::default(),
+                )
+                .unwrap();
+        }
+
+        let (sender, _handle) = OrchestratorMock::create(eve.clone());
+        let cfg: Arc<ApiConfig> = Default::default();
+
+        let client = TestClient::new(route(crate::AppState {
+            storage: eve.clone(),
+            sender: sender.clone(),
+            ai_limits: LimitsMap::new(cfg.req_per_hour),
+            airdrop_limits: LimitsMap::new(cfg.airdrop_per_hour),
+            cfg: Arc::clone(&cfg),
+            cluster: Cluster::new(sender, Duration::from_secs(cfg.cluster_info_ttl_secs)),
+            metrics: Default::default(),
+        }));
+
+        let response = client
+            .get(format!("/account/{user_pubkey}/transactions"))
+            .send()
+            .await;
+        response.assert_status_is_ok();
+        let transactions: Vec<Transaction> = response.0.into_body().into_json().await.unwrap();
+        assert_eq!(
+            transactions.iter().map(|tx| tx.balance).collect::<Vec<_>>(),
+            vec![1, 3, 6]
+        );
+
+        let response = client
+            .get(format!(
+                "/account/{user_pubkey}/transactions?limit=1&offset=1"
+            ))
+            .send()
+            .await;
+        response.assert_status_is_ok();
+        let transactions: Vec<Transaction> = response.0.into_body().into_json().await.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].amount, 2);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_queries() {
+        let tmp = tempdir().unwrap();
+        let db_path = tmp.path().join("test.db");
+        let db_config = Default::default();
+        let eve = Arc::new(EveStorage::new(&db_path, &db_config).unwrap());
+
+        let (sender, _handle) = OrchestratorMock::create(eve.clone());
+        let listen = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = crate::run(listen, sender, eve, Default::default()).await;
+        let url = format!("http://{listen}");
+
+        let user = ClientWithKey::new(PrivateKey::generate(), url.as_str()).unwrap();
+        let anonymous = Client::new(url.as_str()).unwrap();
+
+        let mut query_ids = vec![];
+        for message in ["first", "second", "third"] {
+            // the server may still be starting
+            let query_id = loop {
+                match user.query(message).await {
+                    Ok(query_id) => break query_id,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+                }
+            };
+            query_ids.push(query_id);
+        }
+
+        let queries = user.queries(10, 0).await.unwrap();
+        assert_eq!(
+            queries.iter().map(|query| query.id).collect::<Vec<_>>(),
+            query_ids
+        );
+        assert_eq!(queries[0].message, "first");
+        // the mock does not send the requests to the nodes
+        assert!(queries[0].complete);
+        assert_eq!(queries[0].relevance, None);
+
+        let queries = user.queries(1, 1).await.unwrap();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].id, query_ids[1]);
+        assert_eq!(queries[0].message, "second");
+
+        // the queries are listed to the owner only, even without the access control
+        let err = anonymous
+            .queries(&user.public_key(), 10, 0)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("401"), "{err:?}");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_read_auth() {
+        let tmp = tempdir().unwrap();
+        let db_path = tmp.path().join("test.db");
+        let db_config = Default::default();
+        let eve = Arc::new(EveStorage::new(&db_path, &db_config).unwrap());
+
+        let (sender, _handle) = OrchestratorMock::create(eve.clone());
+        let cfg = ApiConfig {
+            read_auth: ReadAuthConfig {
+                enabled: true,
+                max_age_secs: 60,
+            },
+            ..Default::default()
+        };
+        let listen = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = crate::run(listen, sender, eve, cfg).await;
+        let url = format!("http://{listen}");
+
+        let mut user = ClientWithKey::new(PrivateKey::generate(), url.as_str()).unwrap();
+        let other = ClientWithKey::new(PrivateKey::generate(), url.as_str()).unwrap();
+        let anonymous = Client::new(url.as_str()).unwrap();
+
+        // the server may still be starting
+        let query_id = loop {
+            match user.query("test").await {
+                Ok(query_id) => break query_id,
+                Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+            }
+        };
+
+        user.answer(&query_id).await.unwrap();
+        user.history(&query_id).await.unwrap();
+        assert_eq!(user.queries(10, 0).await.unwrap().len(), 1);
+        user.transactions(10, 0).await.unwrap();
+
+        let err = anonymous.answer(&query_id).await.unwrap_err();
+        assert!(format!("{err:?}").contains("401"), "{err:?}");
+        let err = other.answer(&query_id).await.unwrap_err();
+        assert!(format!("{err:?}").contains("403"), "{err:?}");
+        let err = (*other)
+            .queries(&user.public_key(), 10, 0)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("403"), "{err:?}");
+        let err = (*other)
+            .transactions(&user.public_key(), 10, 0)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("403"), "{err:?}");
+        let err = anonymous
+            .transactions(&user.public_key(), 10, 0)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("401"), "{err:?}");
+
+        server.abort();
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_restrictions() {
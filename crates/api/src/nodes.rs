@@ -47,6 +47,8 @@ async fn handler_get(
             address: node.address.clone(),
             peer_id: node.peer_id,
             is_connected: node.is_connected(),
+            reputation: node.reputation.clone(),
+            capabilities: node.capabilities.clone(),
         });
     Ok(Json(node))
 }
@@ -214,6 +216,16 @@ mod tests {
             assert_eq!(n + 1, get_nodes(&client).await);
         }
 
+        let response = client
+            .get(format!("/nodes/{}", nodes_pub_keys[0]))
+            .send()
+            .await;
+        response.assert_status_is_ok();
+        let json = response.json().await;
+        let reputation = json.value().object().get("reputation").object();
+        reputation.get("tasks").assert_i64(0);
+        reputation.get("relevance").assert_f64(50.0);
+
         for (n, public_key) in nodes_pub_keys.iter().enumerate().rev() {
             client
                 .delete("/nodes/action")
@@ -252,6 +264,9 @@ mod tests {
                     OrchRequest::Ask { request, tx: _ } => {
                         panic!("Unexpected request: {:?}", request)
                     }
+                    OrchRequest::Cancel { request, tx: _ } => {
+                        panic!("Unexpected request: {:?}", request)
+                    }
                     OrchRequest::AddNode {
                         address,
                         public_key,
@@ -298,6 +313,12 @@ mod tests {
                     OrchRequest::Airdrop { tx, .. } => {
                         tx.send(Ok(())).unwrap();
                     }
+                    OrchRequest::Subscribe { tx, .. } => {
+                        tx.send(None).unwrap();
+                    }
+                    OrchRequest::Events { tx } => {
+                        tx.send(tokio::sync::broadcast::channel(1).1).unwrap();
+                    }
                 }
             }
         })
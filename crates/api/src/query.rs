@@ -5,10 +5,13 @@ use orchestrator::OrchRequest;
 use poem::{
     handler,
     http::StatusCode,
-    web::{Data, Json, RemoteAddr},
+    web::{Data, Json, Path, RemoteAddr},
 };
 use std::sync::Arc;
-use types::ai::request::SignedAiRequest;
+use types::ai::{
+    query::QueryId,
+    request::{SignedAiRequest, SignedCancelRequest},
+};
 
 #[handler]
 pub async fn handler_query(
@@ -16,7 +19,13 @@ pub async fn handler_query(
     Json(request): Json<SignedAiRequest>,
     state: Data<&Arc<AppState>>,
 ) -> poem::Result<Json<Hash>> {
-    if request.query.message.len() > state.cfg.max_req_length {
+    // the sealed prompt of a confidential request includes the history and the keys
+    // of its recipients, and the blacklist can't be applied to it
+    let length = match &request.query.sealed {
+        Some(sealed) => sealed.len(),
+        None => request.query.message.len(),
+    };
+    if length > state.cfg.max_req_length {
         return Err(poem::Error::from_status(StatusCode::PAYLOAD_TOO_LARGE));
     }
     if state
@@ -48,3 +57,26 @@ pub async fn handler_query(
     let query_id = receiver_response.await.map_err(|err| eyre!("{err}"))??;
     Ok(Json(query_id))
 }
+
+/// Stops the query in progress. The request must be signed by the author of the query.
+#[handler]
+pub async fn handler_cancel(
+    Path(query_id): Path<QueryId>,
+    Json(request): Json<SignedCancelRequest>,
+    state: Data<&Arc<AppState>>,
+) -> poem::Result<()> {
+    if request.request.id != query_id {
+        return Err(poem::Error::from_status(StatusCode::BAD_REQUEST));
+    }
+    let request = request.verify()?;
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    state
+        .sender
+        .send(OrchRequest::Cancel { request, tx })
+        .await
+        .map_err(|err| eyre!("{err}"))?;
+
+    rx.await.map_err(|err| eyre!("{err}"))??;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use crypto::ed25519::{public::PublicKey, signature::Signature};
+use poem::{http::StatusCode, web::Data, Endpoint, Error, Middleware, Request, Result};
+use std::str::FromStr;
+use tracing::debug;
+use types::ai::request::{
+    ReadChallenge, SignedReadChallenge, AUTH_PUBKEY_HEADER, AUTH_SIGNATURE_HEADER,
+    AUTH_TIMESTAMP_HEADER,
+};
+
+/// Requires a read challenge signed by the requester.
+/// The key of the requester is added to the request as [`Requester`].
+#[derive(Clone, Copy)]
+pub struct SignatureAuth {
+    /// Maximum age of the challenge in seconds.
+    pub max_age_secs: u64,
+}
+
+impl<E: Endpoint> Middleware<E> for SignatureAuth {
+    type Output = SignatureAuthEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        SignatureAuthEndpoint {
+            ep,
+            max_age_secs: self.max_age_secs,
+        }
+    }
+}
+
+pub struct SignatureAuthEndpoint<E> {
+    ep: E,
+    max_age_secs: u64,
+}
+
+impl<E: Endpoint> Endpoint for SignatureAuthEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let challenge = signed_challenge(&req)?.verify().map_err(|err| {
+            debug!("Error: {err}");
+            Error::from_string("invalid challenge signature", StatusCode::UNAUTHORIZED)
+        })?;
+        // the path is a part of the signed challenge, so it can't be reused for another resource
+        let challenge = &challenge.as_ref().challenge;
+        if challenge.is_expired(self.max_age_secs) {
+            return Err(Error::from_string(
+                "The challenge is expired",
+                StatusCode::UNAUTHORIZED,
+            ));
+        }
+
+        req.extensions_mut().insert(Requester(challenge.pubkey));
+        self.ep.call(req).await
+    }
+}
+
+/// Key of the requester who signed the read challenge.
+#[derive(Debug, Clone, Copy)]
+pub struct Requester(pub PublicKey);
+
+/// Fails if the read challenge is signed by another key than the owner's.
+/// Passes if the access control is disabled.
+pub(crate) fn authorize(requester: Option<Data<&Requester>>, owner: &PublicKey) -> Result<()> {
+    match requester {
+        Some(Data(requester)) if requester.0 != *owner => Err(Error::from_string(
+            "The resource belongs to another account",
+            StatusCode::FORBIDDEN,
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn signed_challenge(req: &Request) -> Result<SignedReadChallenge> {
+    let header = |name: &str| {
+        req.header(name).ok_or_else(|| {
+            Error::from_string(
+                format!("A signed challenge is required, the `{name}` header is missing"),
+                StatusCode::UNAUTHORIZED,
+            )
+        })
+    };
+    let invalid = |name: &str| {
+        Error::from_string(format!("Invalid `{name}` header"), StatusCode::BAD_REQUEST)
+    };
+
+    let pubkey = PublicKey::from_str(header(AUTH_PUBKEY_HEADER)?)
+        .map_err(|_| invalid(AUTH_PUBKEY_HEADER))?;
+    let timestamp = header(AUTH_TIMESTAMP_HEADER)?
+        .parse()
+        .map_err(|_| invalid(AUTH_TIMESTAMP_HEADER))?;
+    let signature = Signature::try_from(header(AUTH_SIGNATURE_HEADER)?)
+        .map_err(|_| invalid(AUTH_SIGNATURE_HEADER))?;
+
+    Ok(SignedReadChallenge {
+        challenge: ReadChallenge {
+            path: req.original_uri().path().to_string(),
+            timestamp,
+            pubkey,
+        },
+        signature,
+    })
+}
@@ -1,20 +1,27 @@
 use crypto::ed25519::{private::PrivateKey, public::PublicKey};
 use eyre::{bail, eyre, Context, ContextCompat, Result};
+use futures::{stream, Stream};
 use jwt::JwtSecret;
 use reqwest::{
-    header::{AUTHORIZATION, CACHE_CONTROL},
-    Url,
+    header::{ACCEPT, AUTHORIZATION, CACHE_CONTROL},
+    RequestBuilder, Url,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 #[cfg(feature = "time")]
 use std::time::Duration;
 use std::{fmt::Display, ops::Deref, sync::Arc};
 use tracing::{debug, instrument};
 use types::{
+    account::Transaction,
     ai::{
         models::AiDownloadModel,
-        query::{Query, QueryId},
-        request::{AiRequest, History},
+        query::{Query, QueryId, QuerySummary},
+        request::{
+            AiRequest, AiRequestOptions, CancelRequest, History, ReadChallenge, AUTH_PUBKEY_HEADER,
+            AUTH_SIGNATURE_HEADER, AUTH_TIMESTAMP_HEADER,
+        },
+        stream::{QueryEvent, StreamEvent},
+        webhook::WebhookDelivery,
     },
     cluster::{ClusterInfo, Node, NodeInfo},
     p2p::Peer,
@@ -24,6 +31,8 @@ use types::{
 pub struct Client {
     rpc: Arc<Url>,
     client: reqwest::Client,
+    /// Key the read challenges are signed with.
+    read_key: Option<PrivateKey>,
 }
 
 impl Client {
@@ -43,9 +52,17 @@ impl Client {
         Ok(Self {
             rpc: Arc::new(rpc),
             client,
+            read_key: None,
         })
     }
 
+    /// Signs the reads of the queries, answers and transactions with the key.
+    /// Required if the access control is enabled on the orchestrator.
+    pub fn with_read_key(mut self, key: PrivateKey) -> Self {
+        self.read_key = Some(key);
+        self
+    }
+
     pub async fn send<S, T, R>(&self, suff: S, request: &T) -> Result<R>
     where
         S: AsRef<str>,
@@ -70,8 +87,8 @@ impl Client {
         S: AsRef<str>,
         R: for<'de> serde::Deserialize<'de>,
     {
-        self.client
-            .get(self.rpc.join(suff.as_ref())?)
+        let url = self.rpc.join(suff.as_ref())?;
+        self.sign_read(self.client.get(url.clone()), &url)?
             .send()
             .await
             .context("Request error")?
@@ -120,6 +137,35 @@ impl Client {
             .context("Error when receiving the balance")
     }
 
+    #[instrument(level = "debug", skip_all)]
+    pub async fn transactions(
+        &self,
+        account: &PublicKey,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Transaction>> {
+        self.get(format!(
+            "/account/{account}/transactions?limit={limit}&offset={offset}"
+        ))
+        .await
+        .context("Error when receiving the transactions")
+    }
+
+    /// The read must be signed by the owner of the account, see [`ClientWithKey::queries`].
+    #[instrument(level = "debug", skip_all)]
+    pub async fn queries(
+        &self,
+        account: &PublicKey,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<QuerySummary>> {
+        self.get(format!(
+            "/account/{account}/queries?limit={limit}&offset={offset}"
+        ))
+        .await
+        .context("Error when receiving the queries")
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub async fn airdrop(&self, account: &PublicKey) -> Result<u64> {
         let info: AccountInfo = self
@@ -146,6 +192,111 @@ impl Client {
         self.get(format!("/answer/{query_id}")).await
     }
 
+    /// Attempts to post the completed query to its callback URL.
+    pub async fn webhook_deliveries(&self, query_id: &QueryId) -> Result<Vec<WebhookDelivery>> {
+        self.get(format!("/answer/{query_id}/webhook")).await
+    }
+
+    /// Stops the query. `key` must be the key the query is signed with.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn cancel(&self, query_id: &QueryId, key: &PrivateKey) -> Result<()> {
+        let response = self
+            .client
+            .delete(self.rpc.join(&format!("/query/{query_id}"))?)
+            .json(&CancelRequest::new(*query_id, key.public_key()).sign(key)?)
+            .send()
+            .await
+            .context("Request error")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            bail!("{status}: {}", response.text().await.unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    /// Subscribes to the answer of the query.
+    /// The stream ends after the query is complete.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn answer_stream(
+        &self,
+        query_id: &QueryId,
+    ) -> Result<impl Stream<Item = Result<StreamEvent>>> {
+        self.subscribe(self.rpc.join(&format!("/answer/{query_id}/stream"))?)
+            .await
+    }
+
+    /// Subscribes to the state transitions of the queries in progress,
+    /// optionally only of the query or of the queries of the requester.
+    /// The query is required if the orchestrator doesn't control the read access.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn events(
+        &self,
+        query_id: Option<&QueryId>,
+        requester: Option<&PublicKey>,
+    ) -> Result<impl Stream<Item = Result<QueryEvent>>> {
+        let mut url = self.rpc.join("/events")?;
+        if let Some(query_id) = query_id {
+            url.query_pairs_mut()
+                .append_pair("query", &query_id.to_string());
+        }
+        if let Some(requester) = requester {
+            url.query_pairs_mut()
+                .append_pair("pubkey", &requester.to_string());
+        }
+        self.subscribe(url).await
+    }
+
+    async fn subscribe<T: DeserializeOwned>(
+        &self,
+        url: Url,
+    ) -> Result<impl Stream<Item = Result<T>>> {
+        let response = self
+            .sign_read(self.client.get(url.clone()), &url)?
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .context("Request error")?
+            .error_for_status()?;
+
+        Ok(stream::try_unfold(
+            (response, Vec::new()),
+            |(mut response, mut buf)| async move {
+                loop {
+                    if let Some(end) = buf.windows(2).position(|w| w == b"\n\n") {
+                        let event = buf.drain(..end + 2).collect::<Vec<_>>();
+                        let Some(data) = sse_data(&String::from_utf8_lossy(&event)) else {
+                            continue;
+                        };
+                        let event = serde_json::from_str(&data)
+                            .context("Couldn't parse the stream event")?;
+                        return Ok(Some((event, (response, buf))));
+                    }
+
+                    match response.chunk().await.context("Couldn't get a response")? {
+                        Some(chunk) => buf.extend_from_slice(&chunk),
+                        None => return Ok(None),
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Adds the read challenge signed by the read key, if any.
+    fn sign_read(&self, request: RequestBuilder, url: &Url) -> Result<RequestBuilder> {
+        let Some(key) = &self.read_key else {
+            return Ok(request);
+        };
+        let signed = ReadChallenge::new(url.path().to_string(), key.public_key()).sign(key)?;
+        Ok(request
+            .header(AUTH_PUBKEY_HEADER, signed.challenge.pubkey.to_string())
+            .header(
+                AUTH_TIMESTAMP_HEADER,
+                signed.challenge.timestamp.to_string(),
+            )
+            .header(AUTH_SIGNATURE_HEADER, signed.signature.to_string()))
+    }
+
     #[cfg(feature = "time")]
     #[instrument(level = "debug", skip_all)]
     pub async fn answer_wait(
@@ -276,17 +427,24 @@ impl ClientWithKey {
 
     pub fn with_key_and_client(key: PrivateKey, client: Client) -> ClientWithKey {
         Self {
+            client: client.with_read_key(key.clone()),
             key,
-            client,
             history: Default::default(),
         }
     }
 
+    /// The history is built from the opened query, so it includes the confidential conversations.
     #[instrument(level = "debug", skip_all)]
     pub async fn history<S: Display>(&mut self, query: S) -> Result<()> {
         debug!("last query: {query}");
 
-        self.history = self.client.history(query).await?;
+        let mut query: Query = self
+            .client
+            .get(format!("/answer/{query}"))
+            .await
+            .context("Error when receiving the history")?;
+        query.open(&self.key)?;
+        self.history = query.as_history();
 
         Ok(())
     }
@@ -315,12 +473,35 @@ impl ClientWithKey {
             .context("Error when receiving the balance")
     }
 
+    #[instrument(level = "debug", skip_all)]
+    pub async fn transactions(&self, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.client
+            .transactions(&self.key.public_key(), limit, offset)
+            .await
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub async fn queries(&self, limit: usize, offset: usize) -> Result<Vec<QuerySummary>> {
+        self.client
+            .queries(&self.key.public_key(), limit, offset)
+            .await
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub async fn airdrop(&self) -> Result<u64> {
         self.client.airdrop(&self.key.public_key()).await
     }
 
     pub async fn query<S: ToString>(&self, query: S) -> Result<QueryId> {
+        self.query_with_options(query, AiRequestOptions::default())
+            .await
+    }
+
+    pub async fn query_with_options<S: ToString>(
+        &self,
+        query: S,
+        options: AiRequestOptions,
+    ) -> Result<QueryId> {
         self.client
             .send(
                 "/query",
@@ -329,13 +510,44 @@ impl ClientWithKey {
                     self.history.clone(),
                     self.key.public_key(),
                 )
+                .with_options(options)
                 .sign(&self.key)?,
             )
             .await
     }
 
+    /// Sends a request readable only by the `nodes`, the answer is readable only by the user.
+    pub async fn query_confidential<S: ToString>(
+        &self,
+        query: S,
+        options: AiRequestOptions,
+        nodes: &[PublicKey],
+    ) -> Result<QueryId> {
+        self.client
+            .send(
+                "/query",
+                &AiRequest::confidential(
+                    query.to_string(),
+                    self.history.clone(),
+                    self.key.public_key(),
+                    nodes,
+                )?
+                .with_options(options)
+                .sign(&self.key)?,
+            )
+            .await
+    }
+
+    /// Returns the query with the confidential content decrypted.
     pub async fn answer(&self, query_id: &QueryId) -> Result<Query> {
-        self.client.answer(query_id).await
+        let mut query = self.client.answer(query_id).await?;
+        // the queries of other accounts stay sealed
+        let _ = query.open(&self.key);
+        Ok(query)
+    }
+
+    pub async fn cancel(&self, query_id: &QueryId) -> Result<()> {
+        self.client.cancel(query_id, &self.key).await
     }
 
     #[cfg(feature = "time")]
@@ -345,7 +557,9 @@ impl ClientWithKey {
         query_id: &QueryId,
         duration: Option<Duration>,
     ) -> Result<Query> {
-        self.client.answer_wait(query_id, duration).await
+        let mut query = self.client.answer_wait(query_id, duration).await?;
+        let _ = query.open(&self.key);
+        Ok(query)
     }
 
     pub async fn get_ai(&self) -> Result<AiDownloadModel> {
@@ -365,6 +579,20 @@ impl Deref for ClientWithKey {
     }
 }
 
+/// Returns the data of a server-sent event. Comments and other fields are skipped.
+fn sse_data(event: &str) -> Option<String> {
+    let data = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect::<Vec<_>>();
+    if data.is_empty() {
+        None
+    } else {
+        Some(data.join("\n"))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AccountInfo {
     balance: u64,
@@ -13,6 +13,9 @@ pub struct ApiConfig {
     pub max_req_length: usize,
     pub jwt: JwtSecret,
     pub cluster_info_ttl_secs: u64,
+    /// Access control of the queries and answers.
+    #[serde(default)]
+    pub read_auth: ReadAuthConfig,
 }
 
 impl Default for ApiConfig {
@@ -24,6 +27,26 @@ impl Default for ApiConfig {
             jwt: JwtSecret::from_str(JWT_DEFAULT_DEV).unwrap(),
             cluster_info_ttl_secs: 10,
             airdrop_per_hour: 10,
+            read_auth: ReadAuthConfig::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReadAuthConfig {
+    /// The queries are readable only with a challenge signed by the requester if enabled.
+    pub enabled: bool,
+    /// Maximum age of a signed challenge. Also applies to the list of the queries
+    /// of an account, which always requires a signed challenge.
+    pub max_age_secs: u64,
+}
+
+impl Default for ReadAuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_age_secs: 300,
         }
     }
 }
@@ -13,6 +13,7 @@ pub mod p2p;
 pub mod rpc;
 pub mod tasks;
 pub mod url;
+pub mod verifier;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "tp")]
@@ -22,7 +23,7 @@ pub enum Config {
 }
 
 impl Config {
-    pub fn llm(&self) -> &llm::OllamaConfig {
+    pub fn llm(&self) -> &llm::LlmConfig {
         match self {
             Config::Node(node) => &node.llm,
             Config::Orch(orch) => &orch.llm,
@@ -1,6 +1,78 @@
 use crate::url::{deserialize_url, serialize_url};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use types::{
+    ai::models::AiWebModel,
+    cluster::{Capabilities, HardwareClass},
+};
 use url::Url;
+
+/// LLM backend of the node. The configurations without `backend` are read as Ollama ones.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+#[serde(from = "LlmConfigRepr")]
+pub enum LlmConfig {
+    Ollama(OllamaConfig),
+    /// Server with the OpenAI-compatible API: vLLM, llama.cpp server, LM Studio, TGI.
+    #[serde(rename = "openai")]
+    OpenAi(OpenAiConfig),
+    /// GGUF model run by llama.cpp inside the node process.
+    Gguf(GgufConfig),
+    /// Several backends serving as one.
+    Pool(PoolConfig),
+    /// Answers without a model, for the tests and the offline clusters.
+    Mock(MockConfig),
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self::Ollama(OllamaConfig::default())
+    }
+}
+
+impl LlmConfig {
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            LlmConfig::Ollama(config) => config.capabilities(),
+            LlmConfig::OpenAi(config) => config.capabilities(),
+            LlmConfig::Gguf(config) => config.capabilities(),
+            LlmConfig::Pool(config) => config.capabilities(),
+            LlmConfig::Mock(config) => config.capabilities(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LlmConfigRepr {
+    Tagged(TaggedLlmConfig),
+    Legacy(OllamaConfig),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+enum TaggedLlmConfig {
+    Ollama(OllamaConfig),
+    #[serde(rename = "openai")]
+    OpenAi(OpenAiConfig),
+    Gguf(GgufConfig),
+    Pool(PoolConfig),
+    Mock(MockConfig),
+}
+
+impl From<LlmConfigRepr> for LlmConfig {
+    fn from(repr: LlmConfigRepr) -> Self {
+        match repr {
+            LlmConfigRepr::Tagged(TaggedLlmConfig::Ollama(config))
+            | LlmConfigRepr::Legacy(config) => LlmConfig::Ollama(config),
+            LlmConfigRepr::Tagged(TaggedLlmConfig::OpenAi(config)) => LlmConfig::OpenAi(config),
+            LlmConfigRepr::Tagged(TaggedLlmConfig::Gguf(config)) => LlmConfig::Gguf(config),
+            LlmConfigRepr::Tagged(TaggedLlmConfig::Pool(config)) => LlmConfig::Pool(config),
+            LlmConfigRepr::Tagged(TaggedLlmConfig::Mock(config)) => LlmConfig::Mock(config),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct OllamaConfig {
@@ -22,6 +94,12 @@ pub struct OllamaConfig {
     /// timeout for Ollama request in seconds, default 300
     pub timeout: u64,
     pub pull_model: bool,
+    /// context window of the model in tokens, default 4096
+    pub context_length: u64,
+    /// number of requests the server handles in parallel, default 1
+    pub max_jobs: u32,
+    /// hardware the model runs on, reported to the orchestrator
+    pub hardware: HardwareClass,
 }
 
 impl Default for OllamaConfig {
@@ -35,6 +113,291 @@ impl Default for OllamaConfig {
             retry_limit: 13,
             timeout: 300,
             pull_model: false,
+            context_length: 4096,
+            max_jobs: 1,
+            hardware: HardwareClass::default(),
+        }
+    }
+}
+
+impl OllamaConfig {
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            model: self.model.clone(),
+            context_length: self.context_length,
+            max_jobs: self.max_jobs,
+            hardware: self.hardware,
         }
     }
 }
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenAiConfig {
+    /// base URL of the API, default http://localhost:8000/v1
+    #[serde(serialize_with = "serialize_url", deserialize_with = "deserialize_url")]
+    pub url: Url,
+    pub model: String,
+    /// sent as the bearer token, if set
+    pub api_key: Option<String>,
+    /// failed request retry limit, default 13
+    pub retry_limit: usize,
+    /// timeout for the request in seconds, default 300
+    pub timeout: u64,
+    /// context window of the model in tokens, default 4096
+    pub context_length: u64,
+    /// number of requests the server handles in parallel, default 1
+    pub max_jobs: u32,
+    /// hardware the model runs on, reported to the orchestrator
+    pub hardware: HardwareClass,
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:8000/v1".parse().expect("Never"),
+            model: "deepseek-r1:latest".to_string(),
+            api_key: None,
+            retry_limit: 13,
+            timeout: 300,
+            context_length: 4096,
+            max_jobs: 1,
+            hardware: HardwareClass::default(),
+        }
+    }
+}
+
+impl OpenAiConfig {
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            model: self.model.clone(),
+            context_length: self.context_length,
+            max_jobs: self.max_jobs,
+            hardware: self.hardware,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GgufConfig {
+    /// GGUF file of the model, downloaded from `url` if missing
+    pub path: PathBuf,
+    /// source of the model file, default the DeepSeek-R1 1.5B web model
+    #[serde(serialize_with = "serialize_url", deserialize_with = "deserialize_url")]
+    pub url: Url,
+    /// SHA-256 of the file in hex, the download and the existing file are rejected
+    /// on mismatch, default the checksum of the web model
+    pub sha256: Option<String>,
+    /// download the model without `sha256`, default false
+    #[serde(default)]
+    pub allow_unverified: bool,
+    /// model reported to the orchestrator, default deepseek-r1:1.5b
+    pub model: String,
+    /// context window of a request in tokens, default 4096
+    pub context_length: u64,
+    /// CPU threads of a request, all the cores if 0, default 0
+    pub threads: u32,
+    /// number of requests run in parallel, each one holds its own context, default 1
+    pub max_jobs: u32,
+}
+
+impl Default for GgufConfig {
+    fn default() -> Self {
+        let web_model = AiWebModel::DeepseekR1_1_5b;
+        Self {
+            path: web_model.file_name().into(),
+            url: web_model.url(),
+            sha256: web_model.sha256().map(str::to_string),
+            allow_unverified: false,
+            model: "deepseek-r1:1.5b".to_string(),
+            context_length: 4096,
+            threads: 0,
+            max_jobs: 1,
+        }
+    }
+}
+
+impl GgufConfig {
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            model: self.model.clone(),
+            context_length: self.context_length,
+            max_jobs: self.max_jobs,
+            hardware: HardwareClass::Cpu,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PoolConfig {
+    pub members: Vec<PoolMemberConfig>,
+    pub strategy: PoolStrategy,
+    /// consecutive failures opening the circuit of a backend, default 3
+    pub failure_threshold: u32,
+    /// time in seconds the backend is skipped after its circuit opens, default 30
+    pub open_secs: u64,
+    /// interval of the health checks in seconds, disabled if 0, default 60
+    ///
+    /// a check is a question answered with one token
+    pub health_check_secs: u64,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            members: vec![],
+            strategy: PoolStrategy::default(),
+            failure_threshold: 3,
+            open_secs: 30,
+            health_check_secs: 60,
+        }
+    }
+}
+
+impl PoolConfig {
+    /// The model and the hardware of the first member, the smallest context
+    /// and the sum of the parallel requests.
+    pub fn capabilities(&self) -> Capabilities {
+        let members = self
+            .members
+            .iter()
+            .map(|member| member.llm.capabilities())
+            .collect::<Vec<_>>();
+        let Some(first) = members.first() else {
+            return Capabilities {
+                model: String::new(),
+                context_length: 0,
+                max_jobs: 0,
+                hardware: HardwareClass::Unknown,
+            };
+        };
+        Capabilities {
+            model: first.model.clone(),
+            context_length: members.iter().map(|c| c.context_length).min().unwrap_or(0),
+            max_jobs: members
+                .iter()
+                .fold(0u32, |jobs, c| jobs.saturating_add(c.max_jobs)),
+            hardware: first.hardware,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PoolMemberConfig {
+    pub llm: LlmConfig,
+    /// requests to the backend per time (time_millis), unlimited if 0, default 0
+    pub req_per_time: u64,
+    /// time in milliseconds for rate limiting, default 1000
+    pub time_millis: u64,
+}
+
+impl Default for PoolMemberConfig {
+    fn default() -> Self {
+        Self {
+            llm: LlmConfig::default(),
+            req_per_time: 0,
+            time_millis: 1000,
+        }
+    }
+}
+
+/// How the pool chooses the backend of a request.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolStrategy {
+    /// the backend with the fewest requests in progress
+    #[default]
+    LeastLoaded,
+    RoundRobin,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MockConfig {
+    /// answers chosen by the seed of the request, the message is echoed if empty
+    pub answers: Vec<String>,
+    /// answers to the requests of a JSON verdict in turn, the last one is repeated
+    pub verdicts: Vec<String>,
+    /// minimum delay of the answer in milliseconds, default 0
+    pub min_latency_millis: u64,
+    /// maximum delay of the answer in milliseconds, default 0
+    pub max_latency_millis: u64,
+    /// percent of the requests failing, default 0
+    pub failure_percent: u8,
+    /// percent of the requests never answered, default 0
+    pub timeout_percent: u8,
+    /// model reported to the orchestrator, default mock:latest
+    pub model: String,
+    /// context window reported to the orchestrator, default 4096
+    pub context_length: u64,
+    /// number of parallel requests reported to the orchestrator, default 4
+    pub max_jobs: u32,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            answers: vec![],
+            verdicts: vec![r#"{"relevance": 90, "description": "ok"}"#.to_string()],
+            min_latency_millis: 0,
+            max_latency_millis: 0,
+            failure_percent: 0,
+            timeout_percent: 0,
+            model: "mock:latest".to_string(),
+            context_length: 4096,
+            max_jobs: 4,
+        }
+    }
+}
+
+impl MockConfig {
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            model: self.model.clone(),
+            context_length: self.context_length,
+            max_jobs: self.max_jobs,
+            hardware: HardwareClass::Cpu,
+        }
+    }
+}
+
+/// Fitting of the questions into the context of the model.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContextConfig {
+    pub policy: ContextPolicy,
+    /// tokens left for the answer if the request doesn't limit it, default 512
+    pub reserve_tokens: u64,
+    /// maximum length of the summary of the removed turns in tokens, default 256
+    pub summary_tokens: u32,
+    /// characters per token of the approximate count, default 4
+    pub chars_per_token: u64,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            policy: ContextPolicy::default(),
+            reserve_tokens: 512,
+            summary_tokens: 256,
+            chars_per_token: 4,
+        }
+    }
+}
+
+/// What is done with the history of a question longer than the context.
+/// The system turns and the question itself are always kept.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextPolicy {
+    /// the oldest turns are removed
+    #[default]
+    DropOldest,
+    /// the oldest turns are replaced with their summary made by the same model
+    Summarize,
+    /// the question is rejected
+    Reject,
+}
@@ -7,7 +7,9 @@ pub struct NodeConfig {
     #[serde(default)]
     pub base: base::BaseConfig,
     #[serde(default)]
-    pub llm: llm::OllamaConfig,
+    pub llm: llm::LlmConfig,
+    #[serde(default)]
+    pub context: llm::ContextConfig,
     #[serde(default)]
     pub logger: logging::LoggerConfig,
     pub p2p: p2p::NodeP2PConfig,
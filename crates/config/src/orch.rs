@@ -1,4 +1,7 @@
-use crate::{api::ApiConfig, base, db, llm, logging, p2p, rpc, tasks::AiTasksConfig};
+use crate::{
+    api::ApiConfig, base, db, llm, logging, p2p, rpc, tasks::AiTasksConfig,
+    verifier::VerifierConfig,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -7,7 +10,7 @@ pub struct OrchConfig {
     #[serde(default)]
     pub base: base::BaseConfig,
     #[serde(default)]
-    pub llm: llm::OllamaConfig,
+    pub llm: llm::LlmConfig,
     #[serde(default)]
     pub logger: logging::LoggerConfig,
     #[serde(default)]
@@ -18,5 +21,7 @@ pub struct OrchConfig {
     pub ai_tasks: AiTasksConfig,
     #[serde(default)]
     pub api: ApiConfig,
+    #[serde(default)]
+    pub verifier: VerifierConfig,
     pub p2p: p2p::OrchP2PConfig,
 }
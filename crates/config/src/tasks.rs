@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use types::p2p::Peer;
+use types::{
+    ai::{aggregation::AggregationStrategy, request::AiRequestOptions},
+    p2p::Peer,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -7,6 +10,26 @@ pub struct AiTasksConfig {
     pub whitelist: Vec<Peer>,
     pub replication_factor: u64,
     pub task_timeout_secs: u64,
+    /// How the final answer is chosen from the node responses.
+    #[serde(default)]
+    pub aggregation: AggregationStrategy,
+    /// What to do with the queries left in progress by the previous run.
+    #[serde(default)]
+    pub recovery: RecoveryConfig,
+    #[serde(default)]
+    pub billing: BillingConfig,
+    /// Bounds of the options requested by the users.
+    #[serde(default)]
+    pub request_limits: RequestLimits,
+    /// Dispatching of the query to additional nodes when the selected ones fail.
+    #[serde(default)]
+    pub hedging: HedgeConfig,
+    /// Verification of the responses by other nodes instead of the orchestrator.
+    #[serde(default)]
+    pub cross_verification: CrossVerificationConfig,
+    /// Delivery of the completed queries to the callback URLs of the requesters.
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
 }
 
 impl Default for AiTasksConfig {
@@ -15,6 +38,230 @@ impl Default for AiTasksConfig {
             whitelist: vec![],
             replication_factor: 3,
             task_timeout_secs: 60,
+            aggregation: AggregationStrategy::default(),
+            recovery: RecoveryConfig::default(),
+            billing: BillingConfig::default(),
+            request_limits: RequestLimits::default(),
+            hedging: HedgeConfig::default(),
+            cross_verification: CrossVerificationConfig::default(),
+            webhooks: WebhookConfig::default(),
         }
     }
 }
+
+impl AiTasksConfig {
+    /// Number of nodes the request is sent to.
+    pub fn replication(&self, options: &AiRequestOptions) -> u64 {
+        options.replication.unwrap_or(self.replication_factor)
+    }
+
+    /// Maximum number of tokens paid for a node response to the request.
+    pub fn max_response_tokens(&self, options: &AiRequestOptions) -> u64 {
+        options
+            .max_tokens
+            .map_or(self.billing.max_tokens, |max_tokens| {
+                u64::from(max_tokens).min(self.billing.max_tokens)
+            })
+    }
+
+    /// The amount reserved from the user's balance for a query.
+    /// The hedged requests may be answered too, so they are reserved as well,
+    /// with the verdicts of the inspectors on each response.
+    pub fn max_query_cost(&self, options: &AiRequestOptions) -> u64 {
+        let responses = self
+            .replication(options)
+            .saturating_add(self.hedging.max_hedges);
+        let response_cost = self
+            .max_response_tokens(options)
+            .saturating_mul(self.billing.price);
+        let inspection_cost = self
+            .inspection_cost(self.max_response_tokens(options))
+            .saturating_mul(self.cross_verification.inspectors);
+        responses.saturating_mul(response_cost.saturating_add(inspection_cost))
+    }
+
+    /// Returns the payment of an inspector for its verdict on a response of `tokens` tokens.
+    pub fn inspection_cost(&self, tokens: u64) -> u64 {
+        tokens
+            .min(self.billing.max_tokens)
+            .saturating_mul(self.billing.price)
+            .saturating_mul(self.cross_verification.payout.min(100) as u64)
+            / 100
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestLimits {
+    pub min_replication: u64,
+    pub max_replication: u64,
+    /// Maximum number of tokens in the answer a user can request.
+    pub max_tokens: u32,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            min_replication: 1,
+            max_replication: 5,
+            max_tokens: 4_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HedgeConfig {
+    /// Maximum number of additional nodes a query is sent to. Hedging is disabled if 0.
+    pub max_hedges: u64,
+    /// Time after which a silent node is replaced by another one.
+    /// A node returning an error is replaced immediately.
+    pub soft_deadline_secs: u64,
+}
+
+impl Default for HedgeConfig {
+    fn default() -> Self {
+        Self {
+            max_hedges: 0,
+            soft_deadline_secs: 20,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CrossVerificationConfig {
+    /// Number of nodes judging each response. Disabled if 0.
+    pub inspectors: u64,
+    /// Time to wait for the verdicts, the delivery of the requests included.
+    /// The orchestrator verifies the response itself if no node has responded.
+    pub timeout_secs: u64,
+    /// Percent of the cost of the judged response paid to each inspector for a valid verdict.
+    pub payout: u8,
+}
+
+impl Default for CrossVerificationConfig {
+    fn default() -> Self {
+        Self {
+            inspectors: 0,
+            timeout_secs: 30,
+            payout: 10,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// The requests with a callback URL are rejected if disabled.
+    pub enabled: bool,
+    /// Number of retries of a failed delivery.
+    pub retries: usize,
+    /// Delay before the first retry, doubled for each next one.
+    pub min_delay_ms: u64,
+    pub timeout_secs: u64,
+    /// Hosts the callbacks may be posted to even if they are not public,
+    /// e.g. `127.0.0.1` or `hooks.internal`. The other hosts must resolve to public addresses.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retries: 5,
+            min_delay_ms: 1_000,
+            timeout_secs: 10,
+            allowed_hosts: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BillingConfig {
+    /// Price of a token.
+    pub price: u64,
+    /// Maximum number of tokens paid for a node response.
+    pub max_tokens: u64,
+    /// Responses with a lower relevance are paid partially.
+    pub min_relevance: Option<u8>,
+    /// Percent of the cost paid for a response below `min_relevance`,
+    /// or for a response the orchestrator failed to verify.
+    pub low_relevance_payout: u8,
+}
+
+/// Rough number of bytes of text per token.
+const BYTES_PER_TOKEN: u64 = 4;
+
+impl BillingConfig {
+    /// Returns the payment for the verified response.
+    pub fn response_cost(&self, tokens: u64, relevance: u8) -> u64 {
+        let cost = tokens.min(self.max_tokens).saturating_mul(self.price);
+        match self.min_relevance {
+            Some(min_relevance) if relevance < min_relevance => {
+                cost.saturating_mul(self.low_relevance_payout.min(100) as u64) / 100
+            }
+            _ => cost,
+        }
+    }
+
+    /// Returns the payment for the response that failed verification. It is paid like
+    /// a low relevance one: an answer breaking the evaluator must not earn the full cost.
+    pub fn unverified_cost(&self, tokens: u64) -> u64 {
+        tokens
+            .min(self.max_tokens)
+            .saturating_mul(self.price)
+            .saturating_mul(self.low_relevance_payout.min(100) as u64)
+            / 100
+    }
+
+    /// Returns the payment for the text generated before the query was cancelled.
+    /// The node doesn't report the tokens of an unfinished response, they are estimated.
+    /// `max_tokens` is the limit of the request.
+    pub fn partial_cost(&self, text_len: usize, max_tokens: u64) -> u64 {
+        (text_len as u64)
+            .div_ceil(BYTES_PER_TOKEN)
+            .min(max_tokens)
+            .min(self.max_tokens)
+            .saturating_mul(self.price)
+    }
+}
+
+impl Default for BillingConfig {
+    fn default() -> Self {
+        Self {
+            price: 1,
+            max_tokens: 4_000,
+            min_relevance: None,
+            low_relevance_payout: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecoveryConfig {
+    pub policy: RecoveryPolicy,
+    /// Time to wait for the nodes to reconnect before resuming the queries.
+    pub delay_secs: u64,
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        Self {
+            policy: RecoveryPolicy::Resume,
+            delay_secs: 10,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryPolicy {
+    /// Send the lost requests to the nodes again and verify the received responses.
+    Resume,
+    /// Mark the unfinished requests as timed out.
+    Timeout,
+}
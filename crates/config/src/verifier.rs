@@ -0,0 +1,163 @@
+use eyre::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Evaluators applied to each node response. The relevance of the response
+/// is the weighted average of the evaluator scores.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerifierConfig {
+    pub evaluators: Vec<EvaluatorConfig>,
+}
+
+impl VerifierConfig {
+    /// The weighted average needs at least one evaluator with a nonzero weight.
+    pub fn validate(&self) -> Result<()> {
+        if self
+            .evaluators
+            .iter()
+            .all(|evaluator| evaluator.weight == 0)
+        {
+            bail!("No evaluators with a nonzero weight configured");
+        }
+        Ok(())
+    }
+}
+
+impl Default for VerifierConfig {
+    fn default() -> Self {
+        Self {
+            evaluators: vec![EvaluatorConfig {
+                weight: 1,
+                kind: EvaluatorKind::LlmJudge {
+                    prompt: None,
+                    rubric: vec![],
+                },
+            }],
+        }
+    }
+}
+
+// serde doesn't support `deny_unknown_fields` together with `flatten`,
+// the unknown fields are rejected by the variants of the kind instead.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct EvaluatorConfig {
+    pub weight: u32,
+    #[serde(flatten)]
+    pub kind: EvaluatorKind,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum EvaluatorKind {
+    /// The orchestrator's model grades the response.
+    LlmJudge {
+        /// File with the system prompt of the judge. The built-in prompt is used if not set.
+        #[serde(default)]
+        prompt: Option<PathBuf>,
+        /// Criteria scored separately. The overall relevance is requested if empty.
+        #[serde(default)]
+        rubric: Vec<Criterion>,
+    },
+    /// Checks of the response text that don't need a model.
+    Heuristic {
+        /// Responses shorter than this number of characters are scored proportionally.
+        #[serde(default)]
+        min_length: usize,
+        /// The response must be written in the script of the user request.
+        #[serde(default)]
+        check_language: bool,
+    },
+    /// Comparison with the known answers to the requests.
+    Reference {
+        /// JSON file mapping the requests to their reference answers.
+        answers: PathBuf,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Criterion {
+    pub name: String,
+    pub description: String,
+    #[serde(default = "default_criterion_weight")]
+    pub weight: u32,
+}
+
+fn default_criterion_weight() -> u32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Criterion, EvaluatorConfig, EvaluatorKind, VerifierConfig};
+
+    #[test]
+    fn test_verifier_config_yaml() {
+        let yaml = r#"
+evaluators:
+  - weight: 2
+    type: llm_judge
+    rubric:
+      - name: accuracy
+        description: The facts are correct
+  - weight: 1
+    type: heuristic
+    min_length: 20
+  - weight: 1
+    type: reference
+    answers: answers.json
+"#;
+        let config: VerifierConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            config.evaluators[0],
+            EvaluatorConfig {
+                weight: 2,
+                kind: EvaluatorKind::LlmJudge {
+                    prompt: None,
+                    rubric: vec![Criterion {
+                        name: "accuracy".to_string(),
+                        description: "The facts are correct".to_string(),
+                        weight: 1,
+                    }],
+                },
+            }
+        );
+        assert_eq!(
+            config.evaluators[1].kind,
+            EvaluatorKind::Heuristic {
+                min_length: 20,
+                check_language: false,
+            }
+        );
+
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<VerifierConfig>(&yaml).unwrap(),
+            config
+        );
+
+        let typo = "evaluators:\n  - weight: 1\n    type: heuristic\n    min_lenght: 20\n";
+        assert!(serde_yaml::from_str::<VerifierConfig>(typo).is_err());
+    }
+
+    #[test]
+    fn test_verifier_config_validate() {
+        let mut config = VerifierConfig::default();
+        config.validate().unwrap();
+
+        config.evaluators[0].weight = 0;
+        assert!(config.validate().is_err());
+        config.evaluators.push(EvaluatorConfig {
+            weight: 1,
+            kind: EvaluatorKind::Heuristic {
+                min_length: 0,
+                check_language: false,
+            },
+        });
+        config.validate().unwrap();
+
+        config.evaluators.clear();
+        assert!(config.validate().is_err());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod private;
 pub mod public;
+pub mod sealed;
 pub mod signature;
 
 #[cfg(test)]
@@ -42,6 +42,15 @@ impl PrivateKey {
     pub fn sign(&self, message: &[u8]) -> Signature {
         Signature(self.0.sign(message))
     }
+
+    /// X25519 key agreement, both keys are converted to their Montgomery form.
+    pub(super) fn diffie_hellman(&self, public_key: &PublicKey) -> [u8; 32] {
+        public_key
+            .0
+            .to_montgomery()
+            .mul_clamped(self.0.to_scalar_bytes())
+            .to_bytes()
+    }
 }
 
 impl Debug for PrivateKey {
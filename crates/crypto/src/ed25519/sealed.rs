@@ -0,0 +1,174 @@
+use super::{private::PrivateKey, public::PublicKey};
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use eyre::{bail, eyre, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+const NONCE_LEN: usize = 12;
+
+/// Message encrypted to one or more ed25519 keys.
+///
+/// The content is encrypted once with a random key. The key is wrapped for each recipient
+/// with the X25519 agreement of an ephemeral key and the key of the recipient.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SealedMessage {
+    ephemeral: PublicKey,
+    keys: Vec<WrappedKey>,
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+/// Content key encrypted to one recipient.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct WrappedKey {
+    recipient: PublicKey,
+    key: Vec<u8>,
+}
+
+impl SealedMessage {
+    pub fn seal(plaintext: &[u8], recipients: &[PublicKey]) -> Result<Self> {
+        if recipients.is_empty() {
+            bail!("No recipients");
+        }
+
+        let mut content_key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut content_key);
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&content_key))
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| eyre!("Failed to encrypt the message"))?;
+
+        let ephemeral = PrivateKey::generate();
+        let keys = recipients
+            .iter()
+            .map(|recipient| {
+                let key = wrapping_cipher(&ephemeral, &ephemeral.public_key(), recipient)?
+                    .encrypt(&Nonce::default(), content_key.as_slice())
+                    .map_err(|_| eyre!("Failed to wrap the key"))?;
+                Ok(WrappedKey {
+                    recipient: *recipient,
+                    key,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            ephemeral: ephemeral.public_key(),
+            keys,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Decrypts the message with the key of a recipient.
+    pub fn open(&self, key: &PrivateKey) -> Result<Vec<u8>> {
+        let recipient = key.public_key();
+        let wrapped = self
+            .keys
+            .iter()
+            .find(|wrapped| wrapped.recipient == recipient)
+            .ok_or_else(|| eyre!("The key is not a recipient of the message"))?;
+        let content_key = wrapping_cipher(key, &self.ephemeral, &recipient)?
+            .decrypt(&Nonce::default(), wrapped.key.as_slice())
+            .map_err(|_| eyre!("Failed to unwrap the key"))?;
+        if content_key.len() != 32 {
+            bail!("Invalid key length");
+        }
+
+        ChaCha20Poly1305::new(Key::from_slice(&content_key))
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map_err(|_| eyre!("Failed to decrypt the message"))
+    }
+
+    pub fn recipients(&self) -> impl Iterator<Item = &PublicKey> {
+        self.keys.iter().map(|wrapped| &wrapped.recipient)
+    }
+
+    pub fn is_recipient(&self, key: &PublicKey) -> bool {
+        self.recipients().any(|recipient| recipient == key)
+    }
+
+    /// Size of the message in bytes, the keys of the recipients included.
+    pub fn len(&self) -> usize {
+        let keys = self
+            .keys
+            .iter()
+            .map(|wrapped| wrapped.recipient.bytes().len() + wrapped.key.len())
+            .sum::<usize>();
+        self.ephemeral.bytes().len() + keys + NONCE_LEN + self.ciphertext.len()
+    }
+
+    /// Whether the encrypted content is empty.
+    pub fn is_empty(&self) -> bool {
+        self.ciphertext.is_empty()
+    }
+}
+
+/// Cipher of the content key for the recipient. `key` is either the ephemeral key
+/// or the key of the recipient, the agreement is made with the other one.
+/// Each wrapping key is used once, so the nonce is constant.
+fn wrapping_cipher(
+    key: &PrivateKey,
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<ChaCha20Poly1305> {
+    let other = if key.public_key() == *recipient {
+        ephemeral
+    } else {
+        recipient
+    };
+    let shared = key.diffie_hellman(other);
+    if shared == [0u8; 32] {
+        bail!("Invalid public key");
+    }
+
+    let mut hasher = Sha3_256::new();
+    hasher.update(shared);
+    hasher.update(ephemeral.bytes());
+    hasher.update(recipient.bytes());
+    Ok(ChaCha20Poly1305::new(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let alice = PrivateKey::generate();
+        let bob = PrivateKey::generate();
+        let eve = PrivateKey::generate();
+        let message = b"secret message";
+
+        let sealed = SealedMessage::seal(message, &[alice.public_key(), bob.public_key()]).unwrap();
+        assert_eq!(sealed.open(&alice).unwrap(), message);
+        assert_eq!(sealed.open(&bob).unwrap(), message);
+        assert!(sealed.open(&eve).is_err());
+        assert!(sealed.is_recipient(&bob.public_key()));
+        assert!(!sealed.is_recipient(&eve.public_key()));
+
+        // the keys of the recipients are counted
+        let single = SealedMessage::seal(message, &[alice.public_key()]).unwrap();
+        assert!(sealed.len() > single.len());
+        assert!(single.len() > message.len());
+    }
+
+    #[test]
+    fn test_open_tampered() {
+        let alice = PrivateKey::generate();
+        let mut sealed = SealedMessage::seal(b"secret message", &[alice.public_key()]).unwrap();
+        sealed.ciphertext[0] ^= 1;
+        assert!(sealed.open(&alice).is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let alice = PrivateKey::generate();
+        let sealed = SealedMessage::seal(b"secret message", &[alice.public_key()]).unwrap();
+        let bytes = bincode::serialize(&sealed).unwrap();
+        let sealed: SealedMessage = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(sealed.open(&alice).unwrap(), b"secret message");
+    }
+}
@@ -16,5 +16,11 @@ pub static PROCESSING: LazyLock<UpDownCounter<i64>> =
     LazyLock::new(|| METER.i64_up_down_counter("processing").build());
 pub static TIMEOUTS: LazyLock<Counter<u64>> =
     LazyLock::new(|| METER.u64_counter("timeouts").build());
+/// Requests sent to additional nodes in place of the failed or silent ones.
+pub static HEDGES: LazyLock<Counter<u64>> = LazyLock::new(|| METER.u64_counter("hedges").build());
 pub static LATENCY: LazyLock<Histogram<u64>> =
     LazyLock::new(|| METER.u64_histogram("latency").build());
+/// Requests sent to a node and not answered yet, with the `node` attribute.
+pub static NODE_IN_FLIGHT: LazyLock<UpDownCounter<i64>> =
+    LazyLock::new(|| METER.i64_up_down_counter("node_in_flight").build());
+pub const NODE_ATTRIBUTE: &str = "node";
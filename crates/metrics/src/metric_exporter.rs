@@ -1,3 +1,4 @@
+use crate::NODE_ATTRIBUTE;
 use async_trait::async_trait;
 use opentelemetry_sdk::{
     error::OTelSdkResult,
@@ -7,9 +8,12 @@ use opentelemetry_sdk::{
         Temporality,
     },
 };
-use std::sync::{
-    atomic::{AtomicI64, AtomicU64, Ordering},
-    Arc,
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
 };
 use tracing::error;
 use types::cluster::MetricsInfo;
@@ -19,9 +23,11 @@ struct Inner {
     requests: AtomicU64,
     processing: AtomicI64,
     timeouts: AtomicU64,
+    hedges: AtomicU64,
     errors: AtomicU64,
     latency_sum: AtomicU64,
     latency_count: AtomicU64,
+    node_in_flight: Mutex<HashMap<String, i64>>,
 }
 
 #[derive(Default, Clone)]
@@ -63,6 +69,14 @@ impl PushMetricExporter for Metrics {
                         error!("Invalid data type for metric 'timeouts'");
                     }
                 }
+                "hedges" => {
+                    if let Some(data) = item.data.as_any().downcast_ref::<Sum<u64>>() {
+                        let sum: u64 = data.data_points.iter().map(|p| p.value).sum();
+                        self.inner.hedges.fetch_add(sum, Ordering::Relaxed);
+                    } else {
+                        error!("Invalid data type for metric 'hedges'");
+                    }
+                }
                 "latency" => {
                     if let Some(data) = item.data.as_any().downcast_ref::<Histogram<u64>>() {
                         let (sum, count) = data
@@ -83,6 +97,22 @@ impl PushMetricExporter for Metrics {
                         error!("Invalid data type for metric 'errors'");
                     }
                 }
+                "node_in_flight" => {
+                    if let Some(data) = item.data.as_any().downcast_ref::<Sum<i64>>() {
+                        let mut node_in_flight = self.inner.node_in_flight();
+                        for point in &data.data_points {
+                            let node = point
+                                .attributes
+                                .iter()
+                                .find(|attr| attr.key.as_str() == NODE_ATTRIBUTE);
+                            if let Some(node) = node {
+                                node_in_flight.insert(node.value.to_string(), point.value);
+                            }
+                        }
+                    } else {
+                        error!("Invalid data type for metric 'node_in_flight'");
+                    }
+                }
                 _ => {
                     error!("unknown metric: {}", item.name);
                 }
@@ -101,6 +131,9 @@ impl PushMetricExporter for Metrics {
         self.inner
             .timeouts
             .store(Default::default(), Ordering::Relaxed);
+        self.inner
+            .hedges
+            .store(Default::default(), Ordering::Relaxed);
         self.inner
             .errors
             .store(Default::default(), Ordering::Relaxed);
@@ -110,6 +143,7 @@ impl PushMetricExporter for Metrics {
         self.inner
             .latency_sum
             .store(Default::default(), Ordering::Relaxed);
+        self.inner.node_in_flight().clear();
 
         Ok(())
     }
@@ -132,8 +166,18 @@ impl Metrics {
             requests: self.inner.requests.load(Ordering::Relaxed),
             processing: self.inner.processing.load(Ordering::Relaxed),
             timeouts: self.inner.timeouts.load(Ordering::Relaxed),
+            hedges: self.inner.hedges.load(Ordering::Relaxed),
             errors: self.inner.errors.load(Ordering::Relaxed),
             latency,
+            node_in_flight: self.inner.node_in_flight().clone(),
         }
     }
 }
+
+impl Inner {
+    fn node_in_flight(&self) -> MutexGuard<'_, HashMap<String, i64>> {
+        self.node_in_flight
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+}
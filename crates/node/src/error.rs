@@ -9,8 +9,14 @@ pub enum NodeError {
     InvalidSignature,
     #[error("Ai error: {0}")]
     AiError(#[from] ai::error::AiError),
+    #[error("Node can't verify its own response")]
+    OwnResponse,
+    #[error("Invalid verdict: {0}")]
+    InvalidVerdict(#[from] ai::judge::VerdictError),
     #[error("Failed to sign response")]
     FailedToSignResponse(#[from] eyre::Error),
+    #[error("Failed to open the sealed message: {0}")]
+    InvalidSealedMessage(eyre::Error),
     #[error("Failed to send message")]
     P2PError,
     #[error("Invalid multiaddr: {0}")]
@@ -9,7 +9,7 @@ use multiaddr::Multiaddr;
 use p2p::etp::{FromETP, ToETP};
 use std::sync::Arc;
 use tracing::error;
-use types::p2p::EveMessage;
+use types::{cluster::Capabilities, p2p::EveMessage};
 
 pub type ToP2P = futures::channel::mpsc::Sender<ToETP<EveMessage>>;
 pub type FromP2P = futures::channel::mpsc::Receiver<FromETP<EveMessage>>;
@@ -21,6 +21,7 @@ pub async fn spawn_node<A: Ai + Send + Sync + 'static>(
     orch_public_key: PublicKey,
     private_key: PrivateKey,
     orch_address: Multiaddr,
+    capabilities: Capabilities,
 ) -> Result<NodeHandler, Error> {
     let mut task = task::NodeTask::new(
         (to_p2p, from_p2p),
@@ -28,6 +29,7 @@ pub async fn spawn_node<A: Ai + Send + Sync + 'static>(
         orch_public_key,
         private_key,
         orch_address,
+        capabilities,
     );
 
     let task = async move {
@@ -23,6 +23,11 @@ impl Network {
         self.orch.peer_id == peer_id
     }
 
+    /// Returns the orchestrator peer id if it is connected.
+    pub fn connected_orch(&self) -> Option<PeerId> {
+        self.orch.is_connected().then_some(self.orch.peer_id)
+    }
+
     pub async fn disconnect_peer(&mut self, peer_id: PeerId) -> Result<(), NodeError> {
         if self.orch.peer_id == peer_id {
             if !self.orch.is_connected() {
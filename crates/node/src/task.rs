@@ -1,26 +1,55 @@
 use crate::{error::NodeError, net::Network, FromP2P, ToP2P};
-use ai::{Ai, QuestionOptions};
+use ai::{
+    judge::{ask_verdict, VerdictError, SYSTEM_PROMPT},
+    reasoning::split_reasoning,
+    Ai, ChunkSender, QuestionOptions,
+};
 use crypto::ed25519::{private::PrivateKey, public::PublicKey};
-use futures::{SinkExt as _, StreamExt};
+use futures::{
+    channel::mpsc,
+    future::{AbortHandle, Abortable},
+    SinkExt as _, StreamExt,
+};
 use multiaddr::Multiaddr;
 use p2p::{etp::FromETP, sys::now_secs, task::PeerId};
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
 use tracing::{error, info, warn};
 use types::{
     ai::{
         query::QueryId,
-        request::SignedAiRequest,
+        request::{History, Role, SignedAiRequest},
         response::{AiResponse, SignedAiResponse},
+        verification::{SignedVerificationResult, VerificationResult},
     },
-    p2p::{EveMessage, NodeMessage},
+    cluster::{Capabilities, LoadReport},
+    p2p::{EveMessage, NodeMessage, OrchMessage},
+    percent::Percent,
 };
 
+/// Maximum number of generated chunks packed into one message.
+const CHUNKS_PER_MESSAGE: usize = 32;
+/// How often the node reports changes of its load to the orchestrator.
+const LOAD_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
 pub struct NodeTask<A> {
     to_p2p: ToP2P,
     from_p2p: FromP2P,
     ai: Arc<A>,
     node_key: PrivateKey,
     network: Network,
+    capabilities: Capabilities,
+    /// Requests received and not answered yet.
+    in_flight: Arc<AtomicU32>,
+    /// Handles to stop the generation of the responses in progress.
+    running: Arc<Mutex<HashMap<QueryId, AbortHandle>>>,
+    /// The last load reported to the orchestrator.
+    reported_load: Option<LoadReport>,
 }
 
 impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
@@ -30,6 +59,7 @@ impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
         orch_public_key: PublicKey,
         node_key: PrivateKey,
         orch_address: Multiaddr,
+        capabilities: Capabilities,
     ) -> Self {
         let network = Network::new(orch_address, orch_public_key, p2p.0.clone());
 
@@ -39,9 +69,26 @@ impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
             ai,
             node_key,
             network,
+            capabilities,
+            in_flight: Default::default(),
+            running: Default::default(),
+            reported_load: None,
         }
     }
 
+    /// Tells the orchestrator what requests the node is able to serve.
+    async fn send_capabilities(&self, to: PeerId) -> Result<(), NodeError> {
+        self.to_p2p
+            .clone()
+            .send(p2p::etp::ToETP::Send {
+                to,
+                message: EveMessage::Node(NodeMessage::Capabilities(self.capabilities.clone())),
+                on_received: None,
+            })
+            .await
+            .map_err(|_| NodeError::P2PError)
+    }
+
     async fn handle_ai_request(
         &self,
         sender: PeerId,
@@ -56,12 +103,25 @@ impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
         let ai = self.ai.clone();
         let node_key = self.node_key.clone();
         let mut p2p = self.to_p2p.clone();
+        let in_flight = self.in_flight.clone();
+        let running = self.running.clone();
 
-        let task = async move {
+        in_flight.fetch_add(1, Ordering::AcqRel);
+        let (abort, registration) = AbortHandle::new_pair();
+        running.lock().expect("Poisoned").insert(id, abort);
+
+        let confidential = request.query.is_confidential();
+        let work = async move {
             info!("Received AI request {id} from orchestrator");
-            let response = Self::request_task(request, ai, node_key)
-                .await
-                .map_err(|err| err.to_string());
+            let (chunks, chunks_rx) = mpsc::unbounded();
+            let (response, _) =
+                futures::join!(Self::request_task(request, ai, node_key, chunks), async {
+                    // the text of the confidential requests is not revealed to the orchestrator
+                    if !confidential {
+                        Self::send_chunks(p2p.clone(), sender, id, chunks_rx).await;
+                    }
+                },);
+            let response = response.map_err(|err| err.to_string());
 
             let result = p2p
                 .send(p2p::etp::ToETP::Send {
@@ -78,6 +138,13 @@ impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
                 error!("Failed to send response: {err}");
             }
         };
+        let task = async move {
+            if Abortable::new(work, registration).await.is_err() {
+                info!("AI request {id} is cancelled");
+            }
+            running.lock().expect("Poisoned").remove(&id);
+            in_flight.fetch_sub(1, Ordering::AcqRel);
+        };
 
         #[cfg(not(target_arch = "wasm32"))]
         tokio::spawn(task);
@@ -86,51 +153,262 @@ impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
         Ok(())
     }
 
+    /// Stops the generation of the response, nothing is sent to the orchestrator.
+    fn handle_cancel(&self, sender: PeerId, id: QueryId) -> Result<(), NodeError> {
+        if !self.network.is_orch(sender) {
+            warn!("Received cancel from non-orchestrator peer {sender}");
+            return Err(NodeError::InvalidSender);
+        }
+
+        if let Some(abort) = self.running.lock().expect("Poisoned").remove(&id) {
+            info!("Cancel AI request {id}");
+            abort.abort();
+        }
+        Ok(())
+    }
+
+    /// Judges the response of another node to the request.
+    async fn handle_verify_request(
+        &self,
+        sender: PeerId,
+        id: QueryId,
+        request: SignedAiRequest,
+        response: SignedAiResponse,
+    ) -> Result<(), NodeError> {
+        if !self.network.is_orch(sender) {
+            warn!("Received verification request from non-orchestrator peer {sender}");
+            return Err(NodeError::InvalidSender);
+        }
+
+        let ai = self.ai.clone();
+        let node_key = self.node_key.clone();
+        let mut p2p = self.to_p2p.clone();
+        let in_flight = self.in_flight.clone();
+
+        in_flight.fetch_add(1, Ordering::AcqRel);
+
+        let task = async move {
+            info!("Received verification request {id} from orchestrator");
+            let node = response.node_key();
+            let result = Self::verify_task(id, request, response, ai, node_key)
+                .await
+                .map_err(|err| err.to_string());
+
+            let result = p2p
+                .send(p2p::etp::ToETP::Send {
+                    to: sender,
+                    message: EveMessage::Node(NodeMessage::VerifyResponse { id, node, result }),
+                    on_received: None,
+                })
+                .await;
+            if let Err(err) = result {
+                error!("Failed to send verdict: {err}");
+            }
+
+            in_flight.fetch_sub(1, Ordering::AcqRel);
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        tokio::spawn(task);
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(task);
+        Ok(())
+    }
+
+    async fn verify_task(
+        id: QueryId,
+        request: SignedAiRequest,
+        response: SignedAiResponse,
+        ai: Arc<A>,
+        key: PrivateKey,
+    ) -> Result<SignedVerificationResult, NodeError> {
+        if response.node_key() == key.public_key() {
+            return Err(NodeError::OwnResponse);
+        }
+        response
+            .clone()
+            .verify()
+            .map_err(|_| NodeError::InvalidSignature)?;
+        let mut request = request
+            .verify()
+            .map_err(|_| NodeError::InvalidSignature)?
+            .into_inner();
+        // the inspectors of the confidential requests are among their recipients
+        request
+            .query
+            .open(&key)
+            .map_err(NodeError::InvalidSealedMessage)?;
+        let mut answer = response.node_response.clone();
+        answer.open(&key).map_err(NodeError::InvalidSealedMessage)?;
+
+        let question = ai::Question {
+            message: ai::judge::question(&id, &request.query, &answer.response),
+            history: vec![History {
+                content: SYSTEM_PROMPT.to_owned(),
+                role: Role::System,
+            }],
+            options: QuestionOptions {
+                seed: request.query.seed,
+                ..Default::default()
+            },
+        };
+        let verdict = ask_verdict(ai.as_ref(), question, &[]).await?;
+        let relevance = verdict
+            .relevance
+            .ok_or(VerdictError::InvalidJson("No relevance found"))?;
+        let relevance = Percent::try_from(relevance)
+            .map_err(|_| VerdictError::InvalidJson("Relevance is out of range"))?;
+
+        VerificationResult {
+            material: response,
+            inspector: key.public_key(),
+            relevance,
+            description: verdict.description,
+            scores: vec![],
+            verdicts: vec![],
+        }
+        .sign(&key)
+        .map_err(|(_, err)| NodeError::FailedToSignResponse(err))
+    }
+
+    /// Reports the number of requests in progress to the orchestrator
+    /// if it has changed since the last report.
+    /// The requests above `max_jobs` are waiting for the model.
+    async fn report_load(&mut self) -> Result<(), NodeError> {
+        let Some(orch) = self.network.connected_orch() else {
+            return Ok(());
+        };
+        let in_flight = self.in_flight.load(Ordering::Acquire);
+        let report = LoadReport {
+            in_flight,
+            queued: in_flight.saturating_sub(self.capabilities.max_jobs),
+        };
+        if self.reported_load == Some(report) {
+            return Ok(());
+        }
+        self.to_p2p
+            .send(p2p::etp::ToETP::Send {
+                to: orch,
+                message: EveMessage::Node(NodeMessage::Load(report)),
+                on_received: None,
+            })
+            .await
+            .map_err(|_| NodeError::P2PError)?;
+        self.reported_load = Some(report);
+        Ok(())
+    }
+
+    async fn send_chunks(
+        mut p2p: ToP2P,
+        to: PeerId,
+        id: QueryId,
+        chunks: mpsc::UnboundedReceiver<String>,
+    ) {
+        let mut offset = 0;
+        let mut chunks = chunks.ready_chunks(CHUNKS_PER_MESSAGE);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.concat();
+            let len = chunk.len() as u64;
+            let result = p2p
+                .send(p2p::etp::ToETP::Send {
+                    to,
+                    message: EveMessage::Node(NodeMessage::AiResponseChunk { id, offset, chunk }),
+                    on_received: None,
+                })
+                .await;
+            if let Err(err) = result {
+                warn!("Failed to send response chunk: {err}");
+                return;
+            }
+            offset += len;
+        }
+    }
+
     async fn request_task(
         request: SignedAiRequest,
         ai: Arc<A>,
         key: PrivateKey,
+        chunks: ChunkSender,
     ) -> Result<SignedAiResponse, NodeError> {
-        let request = request
+        let mut request = request
             .verify()
             .map_err(|_| NodeError::InvalidSignature)?
             .into_inner();
+        request
+            .query
+            .open(&key)
+            .map_err(NodeError::InvalidSealedMessage)?;
+        // the response is readable by the requester and the other nodes able to judge it
+        let recipients = request.query.sealed.as_ref().map(|sealed| {
+            std::iter::once(request.query.pubkey)
+                .chain(sealed.recipients().copied().filter(|recipient| {
+                    *recipient != key.public_key() && *recipient != request.query.pubkey
+                }))
+                .collect::<Vec<_>>()
+        });
 
         let request_signature = request.signature().to_owned();
+        let options = request.query.options;
         let question = ai::Question {
             message: request.query.message,
             history: request.query.history,
             options: QuestionOptions {
                 seed: request.query.seed,
-                ..Default::default()
+                temperature: options.temperature.unwrap_or_default(),
+                top_p: options.top_p,
+                max_tokens: options.max_tokens,
+                json: false,
             },
         };
-        let answer: ai::Answer = ai.ask(question).await?;
+        let answer: ai::Answer = ai.ask_stream(question, chunks).await?;
+        let (reasoning, response) = split_reasoning(&answer.message);
 
-        AiResponse {
-            response: answer.message,
+        let mut response = AiResponse {
+            response,
+            reasoning,
             pubkey: key.public_key(),
             request_signature,
             timestamp: now_secs(),
             cost: answer.tokens,
+            sealed: None,
+            truncation: answer.truncation,
+        };
+        if let Some(recipients) = recipients {
+            response
+                .seal(&recipients)
+                .map_err(NodeError::FailedToSignResponse)?;
         }
-        .sign(&key)
-        .map_err(NodeError::FailedToSignResponse)
+        response.sign(&key).map_err(NodeError::FailedToSignResponse)
     }
 
     async fn handle_message(&mut self, msg: FromETP<EveMessage>) -> Result<(), NodeError> {
         match msg {
             FromETP::Receive(peer_id, msg) => match msg {
                 EveMessage::Orch(orch_message) => match orch_message {
-                    types::p2p::OrchMessage::AiRequest { id, request } => {
+                    OrchMessage::AiRequest { id, request } => {
                         self.handle_ai_request(peer_id, id, request).await?;
                     }
+                    OrchMessage::VerifyRequest {
+                        id,
+                        request,
+                        response,
+                    } => {
+                        self.handle_verify_request(peer_id, id, request, response)
+                            .await?;
+                    }
+                    OrchMessage::Cancel { id } => self.handle_cancel(peer_id, id)?,
                 },
                 EveMessage::Node(_) => {
                     warn!("Received node message from node {peer_id}");
                 }
             },
-            FromETP::Connect(peer_id) => self.network.connect_peer(peer_id).await?,
+            FromETP::Connect(peer_id) => {
+                self.network.connect_peer(peer_id).await?;
+                if self.network.is_orch(peer_id) {
+                    self.send_capabilities(peer_id).await?;
+                    self.reported_load = None;
+                }
+            }
             FromETP::Disconnect(peer_id) => self.network.disconnect_peer(peer_id).await?,
         }
         Ok(())
@@ -161,6 +439,7 @@ impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
         self.network.reconnect_nodes().await?;
 
         info!("Dialing complete");
+        let mut load_interval = p2p::sys::interval_generator(LOAD_REPORT_INTERVAL);
         #[cfg(not(target_arch = "wasm32"))]
         {
             use futures::FutureExt;
@@ -173,6 +452,9 @@ impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
                     _ = reconnect_interval.tick().fuse() => {
                        self.network.reconnect_nodes().await?
                     }
+                    _ = load_interval.next() => {
+                        self.report_load().await?
+                    }
                 }
             }
         }
@@ -183,6 +465,9 @@ impl<A: Ai + Send + Sync + 'static> NodeTask<A> {
                     request = self.from_p2p.next() => {
                         self.handle_request(request).await?;
                     }
+                    _ = load_interval.next() => {
+                        self.report_load().await?;
+                    }
                 }
             }
         }
@@ -1,7 +1,15 @@
-use crypto::hash::sha3;
-use futures::StreamExt;
-use p2p::{etp::ToETP, key::ToP2P as _};
-use types::{ai::request::AiRequest, p2p::NodeMessage};
+use crypto::{ed25519::private::PrivateKey, hash::sha3};
+use futures::{SinkExt as _, StreamExt};
+use p2p::{
+    etp::{FromETP, ToETP},
+    key::ToP2P as _,
+};
+use std::time::Duration;
+use types::{
+    ai::{request::AiRequest, response::AiResponse},
+    cluster::LoadReport,
+    p2p::{EveMessage, NodeMessage},
+};
 
 mod rt;
 
@@ -38,6 +46,9 @@ pub async fn test_node_response() {
                             assert_eq!(response.response, format!("ai:test:{}", i));
                             assert_eq!(response.pubkey, node.node_key.public_key());
                         }
+                        NodeMessage::AiResponseChunk { .. } | NodeMessage::Load(_) => continue,
+                        NodeMessage::Capabilities(_) => panic!("unexpected capabilities"),
+                        NodeMessage::VerifyResponse { .. } => panic!("unexpected verdict"),
                     },
                 }
                 i += 1;
@@ -47,3 +58,261 @@ pub async fn test_node_response() {
         }
     }
 }
+
+#[tokio::test]
+pub async fn test_node_response_chunks() {
+    let mut node = rt::start_node().await;
+
+    let id = sha3(&0);
+    let req = AiRequest::new("test".to_string(), vec![], node.orch.public_key());
+    node.send(id, req.sign(&node.orch).unwrap()).await;
+
+    let mut text = String::new();
+    loop {
+        let resp = node.from_node.next().await.unwrap();
+        let ToETP::Send { message, .. } = resp else {
+            continue;
+        };
+        match message {
+            types::p2p::EveMessage::Node(NodeMessage::AiResponseChunk {
+                id: chunk_id,
+                offset,
+                chunk,
+            }) => {
+                assert_eq!(chunk_id, id);
+                assert_eq!(offset, text.len() as u64);
+                text.push_str(&chunk);
+            }
+            types::p2p::EveMessage::Node(NodeMessage::AiResponse {
+                id: response_id,
+                response,
+            }) => {
+                assert_eq!(response_id, id);
+                let response = response.unwrap().node_response.response;
+                assert_eq!(response, "ai:test");
+                assert_eq!(text, response);
+                break;
+            }
+            types::p2p::EveMessage::Node(NodeMessage::Load(_)) => {}
+            message => panic!("unexpected message: {:?}", message),
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn test_node_reasoning() {
+    let mut node = rt::start_node().await;
+
+    let id = sha3(&0);
+    let req = AiRequest::new(
+        "<think>thinking</think>answer".to_string(),
+        vec![],
+        node.orch.public_key(),
+    );
+    node.send(id, req.sign(&node.orch).unwrap()).await;
+
+    loop {
+        let ToETP::Send { message, .. } = node.from_node.next().await.unwrap() else {
+            continue;
+        };
+        if let EveMessage::Node(NodeMessage::AiResponse { response, .. }) = message {
+            let response = response.unwrap().verify().unwrap().into_inner();
+            assert_eq!(response.node_response.response, "ai:answer");
+            assert_eq!(
+                response.node_response.reasoning,
+                Some("thinking".to_string())
+            );
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn test_node_confidential() {
+    let mut node = rt::start_node().await;
+    let user = PrivateKey::generate();
+
+    let id = sha3(&0);
+    let req = AiRequest::confidential(
+        "test".to_string(),
+        vec![],
+        user.public_key(),
+        &[node.node_key.public_key()],
+    )
+    .unwrap();
+    node.send(id, req.sign(&user).unwrap()).await;
+
+    loop {
+        let ToETP::Send { message, .. } = node.from_node.next().await.unwrap() else {
+            continue;
+        };
+        match message {
+            EveMessage::Node(NodeMessage::AiResponse { response, .. }) => {
+                // the orchestrator sees the sealed response only
+                let mut response = response.unwrap().verify().unwrap().into_inner();
+                assert!(response.node_response.response.is_empty());
+                assert!(response.node_response.open(&node.orch).is_err());
+                response.node_response.open(&user).unwrap();
+                assert_eq!(response.node_response.response, "ai:test");
+                break;
+            }
+            EveMessage::Node(NodeMessage::AiResponseChunk { .. }) => {
+                panic!("the confidential response is streamed")
+            }
+            _ => {}
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn test_node_capabilities() {
+    let mut node = rt::start_node().await;
+    let orch = node.orch.public_key().to_p2p().to_peer_id();
+    node.to_node.send(FromETP::Connect(orch)).await.unwrap();
+
+    loop {
+        match node.from_node.next().await.unwrap() {
+            ToETP::Send { to, message, .. } => {
+                assert_eq!(to, orch);
+                let EveMessage::Node(NodeMessage::Capabilities(capabilities)) = message else {
+                    panic!("unexpected message: {:?}", message);
+                };
+                assert_eq!(capabilities, rt::capabilities());
+                break;
+            }
+            ToETP::Dial(_, _) => {}
+            resp => panic!("unexpected message: {:?}", resp),
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn test_node_load() {
+    let mut node = rt::start_node_with_delay(Duration::from_secs(2)).await;
+
+    let id = sha3(&0);
+    let req = AiRequest::new("test".to_string(), vec![], node.orch.public_key());
+    node.send(id, req.sign(&node.orch).unwrap()).await;
+    let orch = node.orch.public_key().to_p2p().to_peer_id();
+    node.to_node.send(FromETP::Connect(orch)).await.unwrap();
+
+    let mut reports = vec![];
+    loop {
+        let ToETP::Send { message, .. } = node.from_node.next().await.unwrap() else {
+            continue;
+        };
+        match message {
+            EveMessage::Node(NodeMessage::Load(report)) => {
+                reports.push(report);
+                if report.in_flight == 0 {
+                    break;
+                }
+            }
+            EveMessage::Node(NodeMessage::AiResponse { response, .. }) => {
+                assert!(response.is_ok());
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(
+        reports,
+        vec![
+            LoadReport {
+                in_flight: 1,
+                queued: 0
+            },
+            LoadReport {
+                in_flight: 0,
+                queued: 0
+            }
+        ]
+    );
+}
+
+#[tokio::test]
+pub async fn test_node_cancel() {
+    let mut node = rt::start_node_with_delay(Duration::from_secs(3600)).await;
+
+    let id = sha3(&0);
+    let req = AiRequest::new("test".to_string(), vec![], node.orch.public_key());
+    node.send(id, req.sign(&node.orch).unwrap()).await;
+    let orch = node.orch.public_key().to_p2p().to_peer_id();
+    node.to_node.send(FromETP::Connect(orch)).await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let ToETP::Send { message, .. } = node.from_node.next().await.unwrap() else {
+                continue;
+            };
+            match message {
+                EveMessage::Node(NodeMessage::Load(report)) if report.in_flight == 1 => {
+                    node.send_cancel(id).await;
+                }
+                EveMessage::Node(NodeMessage::Load(report)) if report.in_flight == 0 => break,
+                EveMessage::Node(NodeMessage::AiResponse { .. }) => {
+                    panic!("the cancelled request is answered")
+                }
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("the request is not cancelled");
+}
+
+#[tokio::test]
+pub async fn test_node_verify() {
+    let mut node = rt::start_node().await;
+    let other = crypto::ed25519::private::PrivateKey::generate();
+
+    let request = AiRequest::new("test".to_string(), vec![], node.orch.public_key())
+        .sign(&node.orch)
+        .unwrap();
+    let respond = |key: &crypto::ed25519::private::PrivateKey| {
+        AiResponse {
+            response: "ai:test".to_string(),
+            pubkey: key.public_key(),
+            request_signature: request.signature().to_owned(),
+            timestamp: 0,
+            cost: 0,
+            reasoning: None,
+            sealed: None,
+            truncation: None,
+        }
+        .sign(key)
+        .unwrap()
+    };
+    let response = respond(&other);
+    node.send_verify(sha3(&0), request.clone(), response.clone())
+        .await;
+    // a node never judges its own response
+    node.send_verify(sha3(&1), request.clone(), respond(&node.node_key))
+        .await;
+
+    let mut verdicts = 0;
+    while verdicts < 2 {
+        let ToETP::Send { message, .. } = node.from_node.next().await.unwrap() else {
+            continue;
+        };
+        let EveMessage::Node(NodeMessage::VerifyResponse {
+            id,
+            node: node_key,
+            result,
+        }) = message
+        else {
+            continue;
+        };
+        if id == sha3(&0) {
+            assert_eq!(node_key, other.public_key());
+            let result = result.unwrap();
+            result.verify().unwrap();
+            assert_eq!(result.result.inspector, node.node_key.public_key());
+            assert_eq!(result.result.material, response);
+            assert_eq!(result.result.relevance.inner(), 90);
+        } else {
+            assert_eq!(node_key, node.node_key.public_key());
+            assert!(result.is_err());
+        }
+        verdicts += 1;
+    }
+}
@@ -12,7 +12,12 @@ use p2p::{
 };
 use std::{sync::Arc, time::Duration};
 use types::{
-    ai::{query::QueryId, request::SignedAiRequest},
+    ai::{
+        query::QueryId,
+        request::{Role, SignedAiRequest},
+        response::SignedAiResponse,
+    },
+    cluster::{Capabilities, HardwareClass},
     p2p::{EveMessage, OrchMessage},
 };
 
@@ -35,16 +40,41 @@ impl Ai for AiMock {
         if question.message == "error" {
             return Err(ai::error::AiError::InternalError);
         }
+        if question
+            .history
+            .first()
+            .is_some_and(|history| history.role == Role::System)
+        {
+            return Ok(ai::Answer {
+                message: r#"{"relevance": 90, "description": "ok"}"#.to_string(),
+                tokens: 0,
+                truncation: None,
+            });
+        }
 
         Ok(ai::Answer {
             message: format!("ai:{}", question.message),
             tokens: 0,
+            truncation: None,
         })
     }
 }
 
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        model: "mock:latest".to_string(),
+        context_length: 1024,
+        max_jobs: 4,
+        hardware: HardwareClass::Cpu,
+    }
+}
+
 pub async fn start_node() -> Node {
-    let ai = Arc::new(AiMock::new(Duration::from_secs(0)));
+    start_node_with_delay(Duration::from_secs(0)).await
+}
+
+pub async fn start_node_with_delay(delay: Duration) -> Node {
+    let ai = Arc::new(AiMock::new(delay));
 
     let orch = PrivateKey::generate();
     let node_key = PrivateKey::generate();
@@ -61,6 +91,7 @@ pub async fn start_node() -> Node {
         orch.public_key(),
         node_key.clone(),
         orch_address.clone(),
+        capabilities(),
     )
     .await
     .unwrap();
@@ -88,4 +119,29 @@ impl Node {
         );
         self.to_node.send(msg).await.unwrap();
     }
+
+    pub async fn send_cancel(&mut self, id: QueryId) {
+        let msg = FromETP::Receive(
+            self.orch.public_key().to_p2p().to_peer_id(),
+            EveMessage::Orch(OrchMessage::Cancel { id }),
+        );
+        self.to_node.send(msg).await.unwrap();
+    }
+
+    pub async fn send_verify(
+        &mut self,
+        id: QueryId,
+        request: SignedAiRequest,
+        response: SignedAiResponse,
+    ) {
+        let msg = FromETP::Receive(
+            self.orch.public_key().to_p2p().to_peer_id(),
+            EveMessage::Orch(OrchMessage::VerifyRequest {
+                id,
+                request,
+                response,
+            }),
+        );
+        self.to_node.send(msg).await.unwrap();
+    }
 }
@@ -0,0 +1,294 @@
+use crate::error::EvaluatorError;
+use ai::{
+    judge::{ask_json, parse_json, VerdictError},
+    Ai, Question, QuestionOptions,
+};
+use crypto::ed25519::public::PublicKey;
+use std::sync::Arc;
+use tokio::{
+    sync::{mpsc::Receiver, oneshot::Sender},
+    time::{timeout_at, Instant},
+};
+use tracing::{error, warn};
+use types::{
+    ai::{
+        aggregation::{AggregationStrategy, FinalAnswer},
+        query::{NodeResult, Query},
+        request::{History, Role},
+    },
+    percent::Percent,
+};
+
+/// Minimum cosine similarity for two responses to be considered in agreement.
+const AGREEMENT_THRESHOLD: f32 = 0.85;
+
+const PAIRWISE_PROMPT: &str = "You act as a judge comparing two AI responses. You will be provided with a conversation history between a human and an AI, followed by two candidate responses labeled 1 and 2. Decide which response answers the user's request better: it must be accurate, complete and relevant. Provide a verdict in JSON format consisting of two fields:
+'better' — the number of the better response, 1 or 2.
+'description' — a short textual explanation of the choice.
+Return only a JSON object. Do not include any additional text or commentary before or after the JSON object.";
+
+pub struct AggregatorTask<A> {
+    ai: Arc<A>,
+    receiver: Receiver<AggregationRequest>,
+}
+
+impl<A: Ai + Send + Sync + 'static> AggregatorTask<A> {
+    pub fn new(ai: Arc<A>, receiver: Receiver<AggregationRequest>) -> Self {
+        Self { ai, receiver }
+    }
+
+    pub async fn run(&mut self) {
+        while let Some(request) = self.receiver.recv().await {
+            let ai = self.ai.clone();
+            tokio::spawn(async move {
+                let answer = aggregate(
+                    ai.as_ref(),
+                    request.strategy,
+                    &request.query,
+                    request.deadline,
+                )
+                .await;
+                if request.on_result.send(answer).is_err() {
+                    warn!("Failed to send final answer: task is closed");
+                }
+            });
+        }
+        error!("Aggregation channel is closed");
+    }
+}
+
+pub struct AggregationRequest {
+    pub strategy: AggregationStrategy,
+    pub query: Query,
+    /// The strategy is abandoned for [`AggregationStrategy::BestOf`] after the deadline.
+    pub deadline: Instant,
+    pub on_result: Sender<Option<FinalAnswer>>,
+}
+
+struct Candidate {
+    node: PublicKey,
+    content: String,
+    relevance: Percent,
+}
+
+/// Chooses the final answer among the verified responses of the query.
+/// Falls back to [`AggregationStrategy::BestOf`] if the strategy fails or misses the deadline.
+async fn aggregate<A: Ai + Sync>(
+    ai: &A,
+    strategy: AggregationStrategy,
+    query: &Query,
+    deadline: Instant,
+) -> Option<FinalAnswer> {
+    let mut candidates = query
+        .response
+        .iter()
+        .filter_map(NodeResult::verified)
+        .map(|verified| Candidate {
+            node: verified.result.material.node_key(),
+            content: verified.result.material.node_response.response.clone(),
+            relevance: verified.result.relevance.clone(),
+        })
+        .collect::<Vec<_>>();
+    // the most relevant first, the order is stable for equal relevance
+    candidates.sort_by(|a, b| b.relevance.cmp(&a.relevance));
+    // the orchestrator can't read the confidential responses to compare them
+    if candidates.len() < 2 || query.request.query.is_confidential() {
+        return best_of(&candidates);
+    }
+
+    let result = match strategy {
+        AggregationStrategy::BestOf => return best_of(&candidates),
+        AggregationStrategy::Majority => timeout_at(deadline, majority(ai, &candidates)).await,
+        AggregationStrategy::Pairwise => {
+            timeout_at(deadline, pairwise(ai, query, &candidates)).await
+        }
+    };
+    match result {
+        Ok(Ok(answer)) => Some(answer),
+        Err(_) => {
+            warn!(
+                "Aggregation of query {} with {strategy} strategy missed the deadline",
+                query.id
+            );
+            best_of(&candidates)
+        }
+        Ok(Err(err)) => {
+            warn!(
+                "Failed to aggregate answers of query {} with {strategy} strategy: {err}",
+                query.id
+            );
+            best_of(&candidates)
+        }
+    }
+}
+
+fn best_of(candidates: &[Candidate]) -> Option<FinalAnswer> {
+    let best = candidates.first()?;
+    Some(FinalAnswer {
+        strategy: AggregationStrategy::BestOf,
+        node: best.node,
+        content: best.content.clone(),
+        supporters: vec![best.node],
+    })
+}
+
+async fn majority<A: Ai + Sync>(
+    ai: &A,
+    candidates: &[Candidate],
+) -> Result<FinalAnswer, EvaluatorError> {
+    let embeddings = ai
+        .embed(candidates.iter().map(|c| c.content.clone()).collect())
+        .await?;
+    if embeddings.len() != candidates.len() {
+        return Err(EvaluatorError::InvalidRequest(format!(
+            "expected {} embeddings, got {}",
+            candidates.len(),
+            embeddings.len()
+        )));
+    }
+
+    let (index, agreeing) = most_agreed(&embeddings);
+    Ok(FinalAnswer {
+        strategy: AggregationStrategy::Majority,
+        node: candidates[index].node,
+        content: candidates[index].content.clone(),
+        supporters: agreeing.into_iter().map(|i| candidates[i].node).collect(),
+    })
+}
+
+/// Returns the index of the embedding the most of the others agree with, and the indexes of
+/// the agreeing embeddings. Ties are resolved by the total similarity, then by the order.
+fn most_agreed(embeddings: &[Vec<f32>]) -> (usize, Vec<usize>) {
+    let mut best: Option<(usize, Vec<usize>, f32)> = None;
+    for (i, a) in embeddings.iter().enumerate() {
+        let mut agreeing = vec![];
+        let mut total = 0.0;
+        for (j, b) in embeddings.iter().enumerate() {
+            let similarity = if i == j { 1.0 } else { cosine_similarity(a, b) };
+            if similarity >= AGREEMENT_THRESHOLD {
+                agreeing.push(j);
+            }
+            total += similarity;
+        }
+
+        let is_better = match &best {
+            Some((_, best_agreeing, best_total)) => {
+                agreeing.len() > best_agreeing.len()
+                    || (agreeing.len() == best_agreeing.len() && total > *best_total)
+            }
+            None => true,
+        };
+        if is_better {
+            best = Some((i, agreeing, total));
+        }
+    }
+
+    best.map(|(i, agreeing, _)| (i, agreeing))
+        .unwrap_or_default()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+    let norm_a = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Runs a single elimination round starting from the most relevant response:
+/// the current winner is compared against each of the remaining responses.
+async fn pairwise<A: Ai + Sync>(
+    ai: &A,
+    query: &Query,
+    candidates: &[Candidate],
+) -> Result<FinalAnswer, EvaluatorError> {
+    let mut winner = &candidates[0];
+    for challenger in &candidates[1..] {
+        let question = Question {
+            message: pairwise_question(query, &winner.content, &challenger.content),
+            history: vec![History {
+                content: PAIRWISE_PROMPT.to_owned(),
+                role: Role::System,
+            }],
+            options: QuestionOptions {
+                seed: query.request.query.seed,
+                ..Default::default()
+            },
+        };
+        if ask_json(ai, question, parse_pairwise_answer).await? == 2 {
+            winner = challenger;
+        }
+    }
+
+    Ok(FinalAnswer {
+        strategy: AggregationStrategy::Pairwise,
+        node: winner.node,
+        content: winner.content.clone(),
+        supporters: vec![winner.node],
+    })
+}
+
+fn pairwise_question(query: &Query, first: &str, second: &str) -> String {
+    let mut request = String::new();
+
+    let id = query.id.to_hex();
+    request.push_str(&format!("id: {}\n", id));
+    request.push_str(&format!("history section start {}\n", id));
+    query.request.query.history.iter().for_each(|message| {
+        request.push_str(&format!("{}:\n{}\n", message.role, message.content));
+    });
+    request.push_str(&format!("history section end {}\n", id));
+
+    request.push_str(&format!(
+        "user request with id {}:\n{}\n",
+        id, query.request.query.message
+    ));
+    request.push_str(&format!("ai response 1 with id {}:\n{}\n", id, first));
+    request.push_str(&format!("ai response 2 with id {}:\n{}\n", id, second));
+    request
+}
+
+#[derive(serde::Deserialize)]
+struct PairwiseJson {
+    better: u8,
+}
+
+/// Returns the number of the better response.
+fn parse_pairwise_answer(answer: &str) -> Result<u8, VerdictError> {
+    parse_json(answer, |answer: &PairwiseJson| match answer.better {
+        1 | 2 => Ok(()),
+        _ => Err(VerdictError::InvalidScore("better".to_owned())),
+    })
+    .map(|answer| answer.better)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cosine_similarity, most_agreed, parse_pairwise_answer};
+
+    #[test]
+    fn test_most_agreed() {
+        let embeddings = vec![
+            vec![0.0, 1.0, 0.0],
+            vec![1.0, 0.1, 0.0],
+            vec![0.0, -1.0, 0.0],
+            vec![1.0, 0.0, 0.1],
+            vec![1.0, 0.0, 0.0],
+        ];
+        assert_eq!(most_agreed(&embeddings), (4, vec![1, 3, 4]));
+        assert_eq!(most_agreed(&embeddings[..1]), (0, vec![0]));
+        assert!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_parse_pairwise_answer() {
+        let answer = "Verdict: {\"better\": 2, \"description\": \"more complete\"}";
+        assert_eq!(parse_pairwise_answer(answer).unwrap(), 2);
+        let answer = "<think>Is {1} better? No.</think>{\"better\": 2, \"description\": \"ok\"}";
+        assert_eq!(parse_pairwise_answer(answer).unwrap(), 2);
+        assert!(parse_pairwise_answer("{\"better\": 3}").is_err());
+        assert!(parse_pairwise_answer("the first one").is_err());
+    }
+}
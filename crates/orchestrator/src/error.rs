@@ -16,6 +16,8 @@ pub enum OrchestratorError {
     P2PError,
     #[error("Verifier error")]
     VerifierError,
+    #[error("Aggregator error")]
+    AggregatorError,
     #[error("Node {0} is not in whitelist")]
     NodeIsNotInWhitelist(PeerId),
     #[error("System role is not allowed")]
@@ -30,6 +32,14 @@ pub enum OrchestratorError {
     EyreError(#[from] eyre::Error),
     #[error("Task error: {0}")]
     TaskError(#[from] JoinError),
+    #[error("Insufficient balance: {balance}, required: {required}")]
+    InsufficientBalance { balance: u64, required: u64 },
+    #[error("Invalid request options: {0}")]
+    InvalidOptions(&'static str),
+    #[error("Query {0} is not in progress")]
+    QueryIsNotInProgress(QueryId),
+    #[error("Query {0} belongs to another user")]
+    NotQueryOwner(QueryId),
 }
 
 #[cfg(feature = "err_poem")]
@@ -49,6 +59,11 @@ impl ResponseError for OrchestratorError {
             OrchestratorError::EyreError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             OrchestratorError::TaskError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             OrchestratorError::VerifierError => StatusCode::INTERNAL_SERVER_ERROR,
+            OrchestratorError::AggregatorError => StatusCode::INTERNAL_SERVER_ERROR,
+            OrchestratorError::InsufficientBalance { .. } => StatusCode::PAYMENT_REQUIRED,
+            OrchestratorError::InvalidOptions(_) => StatusCode::BAD_REQUEST,
+            OrchestratorError::QueryIsNotInProgress(_) => StatusCode::NOT_FOUND,
+            OrchestratorError::NotQueryOwner(_) => StatusCode::FORBIDDEN,
         }
     }
 }
@@ -67,6 +82,23 @@ pub enum EvaluatorError {
     InvalidRequest(String),
     #[error("Invalid JSON: {0}")]
     InvalidJson(&'static str),
+    #[error("Criterion {0} is not scored")]
+    MissingCriterion(String),
+    #[error("No evaluator scored the response")]
+    NoEvaluation,
+    #[error("The confidential response is not judged by any inspector")]
+    Confidential,
+    #[error("Invalid verdict: {0}")]
+    InvalidVerdict(ai::judge::VerdictError),
+}
+
+impl From<ai::judge::VerdictError> for EvaluatorError {
+    fn from(err: ai::judge::VerdictError) -> Self {
+        match err {
+            ai::judge::VerdictError::Ai(err) => EvaluatorError::AiError(err),
+            err => EvaluatorError::InvalidVerdict(err),
+        }
+    }
 }
 
 #[cfg(feature = "err_poem")]
@@ -78,7 +110,11 @@ impl ResponseError for EvaluatorError {
             | EvaluatorError::InvalidAiResponse(_)
             | EvaluatorError::InvalidRelevance(_) => StatusCode::INTERNAL_SERVER_ERROR,
             EvaluatorError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
-            EvaluatorError::InvalidJson(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            EvaluatorError::InvalidJson(_)
+            | EvaluatorError::MissingCriterion(_)
+            | EvaluatorError::NoEvaluation
+            | EvaluatorError::Confidential
+            | EvaluatorError::InvalidVerdict(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -4,9 +4,14 @@ use crypto::ed25519::public::PublicKey;
 use futures::channel::mpsc::{Receiver, Sender};
 use multiaddr::Multiaddr;
 use p2p::etp::{FromETP, ToETP};
-use tokio::sync::oneshot;
+use tokio::sync::{broadcast, oneshot};
 use types::{
-    ai::{query::QueryId, request::SignedAiRequest, verification::Verified},
+    ai::{
+        query::QueryId,
+        request::{SignedAiRequest, SignedCancelRequest},
+        stream::{QueryEvent, StreamEvent},
+        verification::Verified,
+    },
     cluster::ClusterInfoWithNodes,
     p2p::EveMessage,
 };
@@ -19,11 +24,17 @@ pub type FromP2P = Receiver<FromETP<EveMessage>>;
 
 pub type AiSender = Sender<Question>;
 
+#[allow(clippy::large_enum_variant)]
 pub enum OrchRequest {
     Ask {
         request: Verified<SignedAiRequest>,
         tx: oneshot::Sender<Result<QueryId, OrchestratorError>>,
     },
+    /// Stops the query in progress. Only the author of the query can cancel it.
+    Cancel {
+        request: Verified<SignedCancelRequest>,
+        tx: oneshot::Sender<Result<(), OrchestratorError>>,
+    },
     AddNode {
         address: Option<Multiaddr>,
         public_key: PublicKey,
@@ -41,4 +52,22 @@ pub enum OrchRequest {
         amount: u64,
         tx: oneshot::Sender<Result<(), OrchestratorError>>,
     },
+    /// Subscribe to the answer of a query in progress.
+    /// `None` is returned if the query is not processed right now.
+    Subscribe {
+        id: QueryId,
+        tx: oneshot::Sender<Option<AnswerStream>>,
+    },
+    /// Subscribe to the state transitions of all queries.
+    Events {
+        tx: oneshot::Sender<broadcast::Receiver<QueryEvent>>,
+    },
+}
+
+#[derive(Debug)]
+pub struct AnswerStream {
+    /// The current state of the query and the text received from the nodes so far.
+    pub snapshot: Vec<StreamEvent>,
+    /// Events that follow the snapshot. Closed when the query is complete.
+    pub events: broadcast::Receiver<StreamEvent>,
 }
@@ -1,3 +1,4 @@
+mod aggregator;
 mod error;
 mod interface;
 mod network;
@@ -5,20 +6,22 @@ mod orch;
 mod store;
 mod tasks;
 mod verifier;
+mod webhook;
 
+use aggregator::AggregatorTask;
 use ai::Ai;
 use crypto::ed25519::private::PrivateKey;
 pub use error::*;
 use eyre::{Context, Error, Result};
 pub use interface::*;
-use node_config::tasks::AiTasksConfig;
+use node_config::{tasks::AiTasksConfig, verifier::VerifierConfig};
 use orch::OrchestratorTask;
 use std::sync::Arc;
 use storage::EveStorage;
 use tokio::task::JoinHandle;
 use tracing::debug;
 use types::p2p::Peer;
-use verifier::VerifierTask;
+use verifier::{Pipeline, VerifierTask};
 
 pub async fn spawn_orchestrator<A: Ai + Send + Sync + 'static>(
     storage: Arc<EveStorage>,
@@ -27,21 +30,30 @@ pub async fn spawn_orchestrator<A: Ai + Send + Sync + 'static>(
     ai: Arc<A>,
     key: PrivateKey,
     cfg: &AiTasksConfig,
+    verifier: &VerifierConfig,
 ) -> Result<OrchestratorHandles, Error> {
     init_cluster(&storage, cfg)?;
 
     let (evaluator_rec_tx, evaluator_rec_rx) = tokio::sync::mpsc::channel(100);
 
-    let mut evaluator = VerifierTask::new(key.clone(), ai, evaluator_rec_rx);
+    let pipeline = Pipeline::new(verifier, ai.clone()).context("Failed to create verifier")?;
+    let mut evaluator = VerifierTask::new(key.clone(), pipeline, evaluator_rec_rx);
     let eva = tokio::spawn(async move {
         evaluator.run().await;
     });
 
+    let (aggregator_tx, aggregator_rx) = tokio::sync::mpsc::channel(100);
+    let mut aggregator = AggregatorTask::new(ai, aggregator_rx);
+    let agg = tokio::spawn(async move {
+        aggregator.run().await;
+    });
+
     let mut orchestrator = OrchestratorTask::new(
-        key.public_key(),
+        key,
         api_rec,
         p2p,
         evaluator_rec_tx,
+        aggregator_tx,
         storage,
         cfg,
     )?;
@@ -52,7 +64,7 @@ pub async fn spawn_orchestrator<A: Ai + Send + Sync + 'static>(
         }
     });
 
-    Ok(OrchestratorHandles { ev: eva, orch })
+    Ok(OrchestratorHandles { ev: eva, agg, orch })
 }
 
 fn init_cluster(storage: &EveStorage, resolver: &AiTasksConfig) -> Result<(), Error> {
@@ -78,6 +90,7 @@ fn init_cluster(storage: &EveStorage, resolver: &AiTasksConfig) -> Result<(), Er
 
 pub struct OrchestratorHandles {
     pub ev: JoinHandle<()>,
+    pub agg: JoinHandle<()>,
     pub orch: JoinHandle<()>,
 }
 
@@ -85,6 +98,7 @@ impl OrchestratorHandles {
     pub async fn wait(&mut self) -> Result<()> {
         let result = tokio::select! {
             result = &mut self.ev => result.context("running evaluator"),
+            result = &mut self.agg => result.context("running aggregator"),
             result = &mut self.orch => result.context("running orchestrator")
         };
 
@@ -95,6 +109,7 @@ impl OrchestratorHandles {
 
     pub fn abort(&self) {
         self.ev.abort();
+        self.agg.abort();
         self.orch.abort();
     }
 }
@@ -180,6 +195,19 @@ pub mod mock {
                         } => {
                             tx.send(Ok(())).unwrap();
                         }
+                        crate::OrchRequest::Subscribe { id: _, tx } => {
+                            tx.send(None).unwrap();
+                        }
+                        crate::OrchRequest::Events { tx } => {
+                            // no query is processed by the mock
+                            tx.send(tokio::sync::broadcast::channel(1).1).unwrap();
+                        }
+                        crate::OrchRequest::Cancel { request, tx } => {
+                            tx.send(Err(crate::OrchestratorError::QueryIsNotInProgress(
+                                request.into_inner().request.id,
+                            )))
+                            .unwrap();
+                        }
                     }
                 }
             });
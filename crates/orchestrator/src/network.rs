@@ -1,14 +1,23 @@
 use crate::{error::OrchestratorError, ToP2P};
 use crypto::ed25519::public::PublicKey;
 use futures::{channel::oneshot, SinkExt as _};
+use metrics::{NODE_ATTRIBUTE, NODE_IN_FLIGHT};
 use multiaddr::{Multiaddr, Protocol};
+use opentelemetry::KeyValue;
 use p2p::{etp::ToETP, key::ToP2P as _, task::PeerId};
 use rand::seq::IteratorRandom;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
 use storage::{EveStorage, WriteSet};
 use tracing::{info, warn};
 use types::{
-    cluster::{ClusterInfo, ClusterInfoWithNodes, Node},
+    ai::request::{AiRequest, AiRequestOptions},
+    cluster::{Capabilities, ClusterInfo, ClusterInfoWithNodes, LoadReport, Node},
     p2p::Peer,
 };
 
@@ -129,6 +138,7 @@ impl Network {
         tokio::task::spawn_blocking(move || {
             let mut ws = WriteSet::default();
             storage.cluster_table.remove_node(&public_key, &mut ws)?;
+            storage.reputation_table.remove(&public_key, &mut ws)?;
             storage.commit(ws)?;
             Ok::<_, OrchestratorError>(())
         })
@@ -144,13 +154,18 @@ impl Network {
         Ok(())
     }
 
-    pub fn connected_peers(&self, amount: usize) -> Vec<ConnectedNode> {
-        if self.connected.len() < amount {
-            return self.connected.clone();
-        }
-
+    /// Returns up to `amount` random connected nodes that support the request options.
+    /// The confidential requests are sent to their recipients only.
+    pub fn connected_peers(&self, amount: usize, request: &AiRequest) -> Vec<ConnectedNode> {
         self.connected
             .iter()
+            .filter(|node| node.supports(&request.options))
+            .filter(|node| {
+                request
+                    .sealed
+                    .as_ref()
+                    .map_or(true, |sealed| sealed.is_recipient(&node.key))
+            })
             .choose_multiple(&mut rand::thread_rng(), amount)
             .into_iter()
             .cloned()
@@ -169,6 +184,7 @@ impl Network {
             self.connected
                 .push(ConnectedNode::new(node.peer_id, node.key));
             node.set_connected(true);
+            node.capabilities = None;
         } else {
             warn!("Node {} is not in whitelist", peer);
         }
@@ -192,6 +208,31 @@ impl Network {
         Ok(())
     }
 
+    pub fn set_capabilities(
+        &mut self,
+        peer: PeerId,
+        capabilities: Capabilities,
+    ) -> Result<(), OrchestratorError> {
+        let node = self
+            .peers
+            .get_mut(&peer)
+            .ok_or(OrchestratorError::NodeIsNotInWhitelist(peer))?;
+
+        info!("Node {} serves {:?}", peer, capabilities);
+        if let Some(connected) = self.connected.iter_mut().find(|node| node.peer_id == peer) {
+            connected.capabilities = Some(capabilities.clone());
+        }
+        node.capabilities = Some(capabilities);
+        Ok(())
+    }
+
+    pub fn report_load(&self, peer: PeerId, report: LoadReport) {
+        match self.connected.iter().find(|node| node.peer_id == peer) {
+            Some(node) => node.load.report(report),
+            None => warn!("Load report from not connected node {}", peer),
+        }
+    }
+
     pub async fn cluster_info(&mut self) -> Result<ClusterInfoWithNodes, OrchestratorError> {
         if !self.has_listen_addresses() {
             let (tx, rx) = oneshot::channel();
@@ -205,6 +246,11 @@ impl Network {
             }
         }
 
+        let mut nodes = self.peers.clone();
+        for node in nodes.values_mut() {
+            node.reputation = self.storage.reputation_table.get(&node.key)?;
+        }
+
         Ok(ClusterInfoWithNodes {
             cluster_info: ClusterInfo {
                 orch_address: self.info.addresses.clone(),
@@ -212,7 +258,7 @@ impl Network {
                 orch_pubkey: self.info.self_key,
                 nodes_count: self.peers.len(),
             },
-            nodes: self.peers.clone(),
+            nodes,
         })
     }
 
@@ -284,10 +330,89 @@ pub fn parse_webrtc_certhash(addr: &Multiaddr) -> Option<String> {
 pub struct ConnectedNode {
     pub peer_id: PeerId,
     pub key: PublicKey,
+    pub capabilities: Option<Capabilities>,
+    /// Shared by all the tasks the node is selected for.
+    pub load: Arc<NodeLoad>,
 }
 
 impl ConnectedNode {
     pub fn new(peer_id: PeerId, key: PublicKey) -> Self {
-        Self { peer_id, key }
+        Self {
+            peer_id,
+            key,
+            capabilities: None,
+            load: Default::default(),
+        }
     }
+
+    /// Reserves a slot for a request, unless the node is saturated.
+    /// The limit of the nodes that haven't reported their capabilities is unknown.
+    pub fn try_acquire(&self) -> Option<LoadGuard> {
+        let max_jobs = self
+            .capabilities
+            .as_ref()
+            .map_or(u32::MAX, |capabilities| capabilities.max_jobs.max(1));
+        self.load.try_acquire(self.peer_id, max_jobs)
+    }
+
+    /// Nodes that haven't reported their capabilities only get the requests
+    /// without model requirements.
+    pub fn supports(&self, options: &AiRequestOptions) -> bool {
+        match &self.capabilities {
+            Some(capabilities) => capabilities.supports(options),
+            None => options.model.is_none() && options.min_context.is_none(),
+        }
+    }
+}
+
+/// Requests processed by a node, as seen by the orchestrator and reported by the node.
+#[derive(Debug, Default)]
+pub struct NodeLoad {
+    /// Requests sent by the orchestrator and not answered yet.
+    outstanding: AtomicU32,
+    in_flight: AtomicU32,
+    queued: AtomicU32,
+}
+
+impl NodeLoad {
+    fn try_acquire(self: &Arc<Self>, peer_id: PeerId, max_jobs: u32) -> Option<LoadGuard> {
+        if self.queued.load(Ordering::Relaxed) > 0 {
+            return None;
+        }
+        let in_flight = self.in_flight.load(Ordering::Relaxed);
+        self.outstanding
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |outstanding| {
+                (outstanding.max(in_flight) < max_jobs).then_some(outstanding + 1)
+            })
+            .ok()?;
+
+        NODE_IN_FLIGHT.add(1, &[node_attribute(peer_id)]);
+        Some(LoadGuard {
+            load: self.clone(),
+            peer_id,
+        })
+    }
+
+    fn report(&self, report: LoadReport) {
+        self.in_flight.store(report.in_flight, Ordering::Relaxed);
+        self.queued.store(report.queued, Ordering::Relaxed);
+    }
+}
+
+/// Slot of a request sent to a node, released when the request is finished.
+#[derive(Debug)]
+pub struct LoadGuard {
+    load: Arc<NodeLoad>,
+    peer_id: PeerId,
+}
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        self.load.outstanding.fetch_sub(1, Ordering::AcqRel);
+        NODE_IN_FLIGHT.add(-1, &[node_attribute(self.peer_id)]);
+    }
+}
+
+fn node_attribute(peer_id: PeerId) -> KeyValue {
+    KeyValue::new(NODE_ATTRIBUTE, peer_id.to_base58())
 }
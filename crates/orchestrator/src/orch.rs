@@ -1,18 +1,20 @@
 use crate::{
+    aggregator::AggregationRequest,
     error::OrchestratorError,
     network::Network,
-    store::{accounts::Accounts, queries::Queries},
-    tasks::Tasks,
+    store::{accounts::Accounts, queries::Queries, reputation::Reputations},
+    tasks::{NodeVerdict, Tasks},
     verifier::VerificationRequest,
+    webhook::Webhooks,
     ApiReceiver, FromP2P, OrchRequest, ToP2P,
 };
-use crypto::ed25519::public::PublicKey;
+use crypto::ed25519::private::PrivateKey;
 use eyre::Error;
 use futures::StreamExt;
 use metrics::ERRORS;
-use node_config::tasks::AiTasksConfig;
+use node_config::tasks::{AiTasksConfig, RecoveryPolicy};
 use p2p::etp::FromETP;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use storage::EveStorage;
 use tokio::sync::mpsc::Sender;
 use tracing::warn;
@@ -24,27 +26,45 @@ pub(crate) struct OrchestratorTask {
     tasks: Tasks,
     net: Network,
     accounts: Accounts,
+    recovery_delay: Duration,
 }
 
 impl OrchestratorTask {
     pub fn new(
-        key: PublicKey,
+        key: PrivateKey,
         api_receiver: ApiReceiver,
         p2p: (ToP2P, FromP2P),
         verifier: Sender<VerificationRequest>,
+        aggregator: Sender<AggregationRequest>,
         store: Arc<EveStorage>,
         cfg: &AiTasksConfig,
     ) -> Result<Self, Error> {
         let accounts = Accounts::new(store.clone());
         let queries = Queries::new(store.clone());
-        let net = Network::new(key, p2p.0.clone(), store)?;
+        let reputations = Reputations::new(store.clone());
+        let webhooks = Webhooks::new(key.clone(), &cfg.webhooks, queries.clone())?;
+        let net = Network::new(key.public_key(), p2p.0.clone(), store)?;
+        let recovery_delay = match cfg.recovery.policy {
+            RecoveryPolicy::Resume => Duration::from_secs(cfg.recovery.delay_secs),
+            RecoveryPolicy::Timeout => Duration::ZERO,
+        };
 
         Ok(Self {
             api_receiver,
             p2p_receiver: p2p.1,
             net,
-            tasks: Tasks::new(queries, accounts.clone(), cfg, verifier, p2p.0),
+            tasks: Tasks::new(
+                queries,
+                accounts.clone(),
+                reputations,
+                cfg,
+                verifier,
+                aggregator,
+                p2p.0,
+            )
+            .with_webhooks(webhooks),
             accounts,
+            recovery_delay,
         })
     }
 
@@ -62,6 +82,26 @@ impl OrchestratorTask {
                     self.tasks.on_node_response(id, sender, response).await;
                     Ok(())
                 }
+                EveMessage::Node(NodeMessage::AiResponseChunk { id, offset, chunk }) => {
+                    self.tasks.on_node_chunk(id, sender, offset, chunk);
+                    Ok(())
+                }
+                EveMessage::Node(NodeMessage::Capabilities(capabilities)) => {
+                    self.net.set_capabilities(sender, capabilities)
+                }
+                EveMessage::Node(NodeMessage::Load(report)) => {
+                    self.net.report_load(sender, report);
+                    Ok(())
+                }
+                EveMessage::Node(NodeMessage::VerifyResponse { id, node, result }) => {
+                    let verdict = NodeVerdict {
+                        sender,
+                        node,
+                        result,
+                    };
+                    self.tasks.on_verify_response(id, verdict).await;
+                    Ok(())
+                }
             },
             FromETP::Connect(peer_id) => self.net.connect_peer(peer_id),
             FromETP::Disconnect(peer_id) => self.net.disconnect_peer(peer_id),
@@ -128,6 +168,17 @@ impl OrchestratorTask {
                     }
                 });
             }
+            OrchRequest::Subscribe { id, tx } => {
+                self.tasks.subscribe(id, tx).await;
+            }
+            OrchRequest::Events { tx } => {
+                if tx.send(self.tasks.subscribe_events()).is_err() {
+                    warn!("Failed to send event feed to api");
+                }
+            }
+            OrchRequest::Cancel { request, tx } => {
+                self.tasks.cancel(request, tx).await;
+            }
         }
 
         Ok(())
@@ -136,6 +187,10 @@ impl OrchestratorTask {
     pub async fn run(&mut self) -> Result<(), Error> {
         self.net.init_whitelist().await?;
         let mut expire_task_interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+        // the nodes need some time to reconnect before the queries are resumed
+        let recovery = tokio::time::sleep(self.recovery_delay);
+        tokio::pin!(recovery);
+        let mut recovered = false;
 
         loop {
             tokio::select! {
@@ -163,6 +218,13 @@ impl OrchestratorTask {
                         warn!("Failed to handle p2p request: {:?}", e);
                     }
                 }
+                _ = &mut recovery, if !recovered => {
+                    recovered = true;
+                    if let Err(err) = self.tasks.recover(&self.net).await {
+                        ERRORS.add(1, &[]);
+                        warn!("Failed to recover queries in progress: {:?}", err);
+                    }
+                }
                 _ = expire_task_interval.tick() => {
                     self.tasks.gc_tasks();
                 }
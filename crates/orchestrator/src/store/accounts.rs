@@ -1,7 +1,12 @@
 use crate::OrchestratorError;
 use crypto::ed25519::public::PublicKey;
 use std::sync::Arc;
-use storage::{EveStorage, WriteSet};
+use storage::{ledger::Transfer, EveStorage, StorageError, WriteSet};
+use tracing::warn;
+use types::{
+    account::{Escrow, TransactionKind},
+    ai::query::QueryId,
+};
 
 #[derive(Clone)]
 pub struct Accounts {
@@ -14,29 +19,90 @@ impl Accounts {
     }
 
     pub fn airdrop(&self, public_key: PublicKey, sum: u64) -> Result<(), OrchestratorError> {
-        let mut ws = WriteSet::default();
+        let transfer = Transfer {
+            account: public_key,
+            kind: TransactionKind::Airdrop,
+            amount: sum,
+            query: None,
+        };
         self.storage
-            .account_table
-            .update_balance(public_key, sum as i64, &mut ws)?;
-        self.storage.commit(ws)?;
+            .ledger
+            .apply(&[transfer], WriteSet::default())?;
         Ok(())
     }
 
-    pub fn transfer(
+    /// Moves `amount` from the payer's balance to the escrow of the query.
+    pub fn reserve(
         &self,
-        from: PublicKey,
-        to: PublicKey,
+        id: QueryId,
+        payer: PublicKey,
         amount: u64,
     ) -> Result<(), OrchestratorError> {
         let mut ws = WriteSet::default();
-        let amount = amount as i64;
         self.storage
-            .account_table
-            .update_balance(from, -amount, &mut ws)?;
+            .escrow_table
+            .put(&id, &Escrow { payer, amount }, &mut ws)?;
+
+        let transfer = Transfer {
+            account: payer,
+            kind: TransactionKind::Charge,
+            amount,
+            query: Some(id),
+        };
         self.storage
-            .account_table
-            .update_balance(to, amount, &mut ws)?;
-        self.storage.commit(ws)?;
-        Ok(())
+            .ledger
+            .apply(&[transfer], ws)
+            .map_err(|err| match err {
+                StorageError::InsufficientBalance { balance, required } => {
+                    OrchestratorError::InsufficientBalance { balance, required }
+                }
+                err => err.into(),
+            })
+    }
+
+    /// Pays the node from the escrow of the query.
+    /// Returns the paid amount, which is limited by the rest of the escrow.
+    /// The escrow is only changed by the task of the query, so it is not locked.
+    pub fn settle(
+        &self,
+        id: QueryId,
+        node: PublicKey,
+        amount: u64,
+    ) -> Result<u64, OrchestratorError> {
+        let Some(mut escrow) = self.storage.escrow_table.get(&id)? else {
+            warn!("Escrow of the query {id} is not found");
+            return Ok(0);
+        };
+        let amount = amount.min(escrow.amount);
+        escrow.amount -= amount;
+
+        let mut ws = WriteSet::default();
+        self.storage.escrow_table.put(&id, &escrow, &mut ws)?;
+        let transfer = Transfer {
+            account: node,
+            kind: TransactionKind::Payout,
+            amount,
+            query: Some(id),
+        };
+        self.storage.ledger.apply(&[transfer], ws)?;
+        Ok(amount)
+    }
+
+    /// Returns the rest of the escrow to the payer and closes it.
+    pub fn refund(&self, id: QueryId) -> Result<u64, OrchestratorError> {
+        let Some(escrow) = self.storage.escrow_table.get(&id)? else {
+            return Ok(0);
+        };
+
+        let mut ws = WriteSet::default();
+        self.storage.escrow_table.remove(&id, &mut ws)?;
+        let transfer = Transfer {
+            account: escrow.payer,
+            kind: TransactionKind::Refund,
+            amount: escrow.amount,
+            query: Some(id),
+        };
+        self.storage.ledger.apply(&[transfer], ws)?;
+        Ok(escrow.amount)
     }
 }
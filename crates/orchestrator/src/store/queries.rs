@@ -1,10 +1,15 @@
 use std::sync::Arc;
 use storage::{EveStorage, WriteSet};
+use tracing::warn;
 use types::ai::{
     query::{Query, QueryId},
     request::SignedAiRequest,
+    webhook::WebhookDelivery,
 };
 
+/// Number of query ids read from the in-progress index at once.
+const IN_PROGRESS_PAGE: usize = 100;
+
 #[derive(Clone)]
 pub struct Queries {
     storage: Arc<EveStorage>,
@@ -32,6 +37,75 @@ impl Queries {
         Ok(query)
     }
 
+    /// Returns all queries that are not complete.
+    pub(crate) fn in_progress(&self) -> Result<Vec<Query>, storage::StorageError> {
+        let mut queries = vec![];
+        let mut offset = 0;
+        loop {
+            let ids = self
+                .storage
+                .query_table
+                .get_in_progress_ids(IN_PROGRESS_PAGE, offset)?;
+            if ids.is_empty() {
+                return Ok(queries);
+            }
+            offset += ids.len();
+            for id in ids {
+                match self.storage.query_table.get_query(&id)? {
+                    Some(query) => queries.push(query),
+                    None => warn!("Query {id} is in progress, but not found"),
+                }
+            }
+        }
+    }
+
+    /// Appends the attempt to the delivery log of the query.
+    pub(crate) fn log_delivery(
+        &self,
+        id: &QueryId,
+        delivery: WebhookDelivery,
+    ) -> Result<(), storage::StorageError> {
+        let mut ws = WriteSet::default();
+        self.storage.webhook_table.push(id, delivery, &mut ws)?;
+        self.storage.commit(ws)
+    }
+
+    /// Marks the query to be delivered to the callback URL until [`Self::delivered`].
+    pub(crate) fn add_pending_delivery(
+        &self,
+        id: &QueryId,
+        url: &str,
+    ) -> Result<(), storage::StorageError> {
+        let mut ws = WriteSet::default();
+        self.storage.webhook_table.put_pending(id, url, &mut ws)?;
+        self.storage.commit(ws)
+    }
+
+    /// The delivery of the query is finished, successfully or not.
+    pub(crate) fn delivered(&self, id: &QueryId) -> Result<(), storage::StorageError> {
+        let mut ws = WriteSet::default();
+        self.storage.webhook_table.remove_pending(id, &mut ws)?;
+        self.storage.commit(ws)
+    }
+
+    /// Returns the queries not delivered yet, with their callback URLs
+    /// and the number of the attempts made.
+    pub(crate) fn pending_deliveries(
+        &self,
+    ) -> Result<Vec<(Query, String, u32)>, storage::StorageError> {
+        let mut pending = vec![];
+        for (id, url) in self.storage.webhook_table.pending()? {
+            match self.storage.query_table.get_query(&id)? {
+                Some(query) => {
+                    let attempts = self.storage.webhook_table.get(&id)?.len() as u32;
+                    pending.push((query, url, attempts));
+                }
+                None => warn!("Query {id} is to be delivered, but not found"),
+            }
+        }
+        Ok(pending)
+    }
+
     pub(crate) fn update_query(&self, query: &Query) -> Result<(), storage::StorageError> {
         let mut ws = WriteSet::default();
         self.storage.query_table.put_query(query, &mut ws)?;
@@ -0,0 +1,41 @@
+use crypto::ed25519::public::PublicKey;
+use std::sync::{Arc, Mutex};
+use storage::{EveStorage, WriteSet};
+use types::cluster::{Reputation, TaskOutcome};
+
+#[derive(Clone)]
+pub struct Reputations {
+    storage: Arc<EveStorage>,
+    /// Serializes the read-modify-write updates of concurrent tasks.
+    lock: Arc<Mutex<()>>,
+}
+
+impl Reputations {
+    pub fn new(storage: Arc<EveStorage>) -> Self {
+        Self {
+            storage,
+            lock: Default::default(),
+        }
+    }
+
+    pub fn get(&self, key: &PublicKey) -> Result<Reputation, storage::StorageError> {
+        self.storage.reputation_table.get(key)
+    }
+
+    pub fn record(
+        &self,
+        outcomes: &[(PublicKey, TaskOutcome)],
+    ) -> Result<(), storage::StorageError> {
+        let _guard = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+
+        let mut ws = WriteSet::default();
+        for (key, outcome) in outcomes {
+            let mut reputation = self.storage.reputation_table.get(key)?;
+            reputation.record(*outcome);
+            self.storage
+                .reputation_table
+                .put(key, &reputation, &mut ws)?;
+        }
+        self.storage.commit(ws)
+    }
+}
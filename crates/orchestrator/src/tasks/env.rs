@@ -1,5 +1,6 @@
 use crate::{
-    store::{accounts, queries},
+    aggregator::AggregationRequest,
+    store::{accounts, queries, reputation},
     verifier::VerificationRequest,
     OrchestratorError, ToP2P,
 };
@@ -12,40 +13,68 @@ use multiaddr::PeerId;
 use node_config::tasks::AiTasksConfig;
 use p2p::etp::DeliveryResult;
 use rand::random;
-use tokio::sync::mpsc::Sender;
+use tokio::{
+    sync::{broadcast, mpsc::Sender},
+    time::Instant,
+};
 use types::{
     ai::{
+        aggregation::FinalAnswer,
         query::{query_id, Query, QueryId},
         request::SignedAiRequest,
+        response::SignedAiResponse,
+        stream::QueryEvent,
     },
+    cluster::{Reputation, TaskOutcome},
     p2p::OrchMessage,
 };
 
 pub struct Env {
     accounts: accounts::Accounts,
     queries: queries::Queries,
+    reputations: reputation::Reputations,
     verifier: Sender<VerificationRequest>,
+    aggregator: Sender<AggregationRequest>,
     pub cfg: AiTasksConfig,
     etp: ToP2P,
+    /// State transitions of the queries, for the subscribers of the api.
+    events: broadcast::Sender<QueryEvent>,
 }
 
+/// Number of events a slow subscriber of the feed can lag behind.
+const EVENTS_CAPACITY: usize = 1024;
+
 impl Env {
     pub fn new(
         accounts: accounts::Accounts,
         queries: queries::Queries,
+        reputations: reputation::Reputations,
         verifier: Sender<VerificationRequest>,
+        aggregator: Sender<AggregationRequest>,
         cfg: AiTasksConfig,
         etp: ToP2P,
     ) -> Self {
         Self {
             accounts,
             queries,
+            reputations,
             verifier,
+            aggregator,
             cfg,
             etp,
+            events: broadcast::channel(EVENTS_CAPACITY).0,
         }
     }
 
+    pub fn subscribe_events(&self) -> broadcast::Receiver<QueryEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn publish(&self, event: QueryEvent) {
+        // there may be no subscribers
+        let _ = self.events.send(event);
+    }
+
     pub fn new_id(&self, req: &SignedAiRequest) -> QueryId {
         query_id(random(), req)
     }
@@ -60,6 +89,26 @@ impl Env {
             .map_err(|_| OrchestratorError::VerifierError)
     }
 
+    /// Chooses the final answer of the completed query with the configured strategy.
+    /// The strategy falls back to the best of the responses after the deadline.
+    pub async fn aggregate(
+        &self,
+        query: Query,
+        deadline: Instant,
+    ) -> Result<Option<FinalAnswer>, OrchestratorError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.aggregator
+            .send(AggregationRequest {
+                strategy: self.cfg.aggregation,
+                query,
+                deadline,
+                on_result: tx,
+            })
+            .await
+            .map_err(|_| OrchestratorError::AggregatorError)?;
+        rx.await.map_err(|_| OrchestratorError::AggregatorError)
+    }
+
     pub fn new_query(
         &self,
         id: QueryId,
@@ -68,10 +117,25 @@ impl Env {
         self.queries.new_query(id, request)
     }
 
+    pub fn in_progress_queries(&self) -> Result<Vec<Query>, storage::StorageError> {
+        self.queries.in_progress()
+    }
+
     pub fn update_query(&self, query: &Query) -> Result<(), storage::StorageError> {
         self.queries.update_query(query)
     }
 
+    pub fn reputation(&self, key: &PublicKey) -> Result<Reputation, storage::StorageError> {
+        self.reputations.get(key)
+    }
+
+    pub fn record_outcomes(
+        &self,
+        outcomes: &[(PublicKey, TaskOutcome)],
+    ) -> Result<(), storage::StorageError> {
+        self.reputations.record(outcomes)
+    }
+
     pub async fn send_request(
         &self,
         peer: PeerId,
@@ -91,12 +155,62 @@ impl Env {
         Ok(rx)
     }
 
-    pub fn transfer(
+    /// Asks the node to judge the response of another node.
+    pub async fn send_verify_request(
         &self,
-        from: PublicKey,
-        to: PublicKey,
+        peer: PeerId,
+        id: QueryId,
+        request: SignedAiRequest,
+        response: SignedAiResponse,
+    ) -> Result<Receiver<DeliveryResult>, ()> {
+        let (tx, rx) = oneshot::channel();
+        let mut etp = self.etp.clone();
+        etp.send(p2p::etp::ToETP::Send {
+            to: peer,
+            message: types::p2p::EveMessage::Orch(OrchMessage::VerifyRequest {
+                id,
+                request,
+                response,
+            }),
+            on_received: Some(tx),
+        })
+        .await
+        .map_err(|_| ())?;
+
+        Ok(rx)
+    }
+
+    /// Stops the generation of the response. Best effort: the node is not awaited.
+    pub async fn send_cancel(&self, peer: PeerId, id: QueryId) -> Result<(), ()> {
+        let mut etp = self.etp.clone();
+        etp.send(p2p::etp::ToETP::Send {
+            to: peer,
+            message: types::p2p::EveMessage::Orch(OrchMessage::Cancel { id }),
+            on_received: None,
+        })
+        .await
+        .map_err(|_| ())
+    }
+
+    pub fn reserve(
+        &self,
+        id: QueryId,
+        payer: PublicKey,
         amount: u64,
     ) -> Result<(), OrchestratorError> {
-        self.accounts.transfer(from, to, amount)
+        self.accounts.reserve(id, payer, amount)
+    }
+
+    pub fn settle(
+        &self,
+        id: QueryId,
+        node: PublicKey,
+        amount: u64,
+    ) -> Result<u64, OrchestratorError> {
+        self.accounts.settle(id, node, amount)
+    }
+
+    pub fn refund(&self, id: QueryId) -> Result<u64, OrchestratorError> {
+        self.accounts.refund(id)
     }
 }
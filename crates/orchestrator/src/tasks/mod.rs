@@ -2,47 +2,105 @@ mod env;
 mod task;
 
 use crate::{
-    network::Network,
-    store::{accounts::Accounts, queries::Queries},
+    aggregator::AggregationRequest,
+    network::{ConnectedNode, Network},
+    store::{accounts::Accounts, queries::Queries, reputation::Reputations},
     verifier::VerificationRequest,
-    OrchestratorError, ToP2P,
+    webhook::{check_callback, Webhooks},
+    AnswerStream, OrchestratorError, ToP2P,
 };
+use crypto::ed25519::public::PublicKey;
 use env::Env;
 use metrics::{ERRORS, PROCESSING, REQUESTS};
 use multiaddr::PeerId;
-use node_config::tasks::AiTasksConfig;
-use std::{collections::HashMap, sync::Arc};
+use node_config::tasks::{AiTasksConfig, RecoveryPolicy};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 use task::Task;
-use tokio::sync::{mpsc, mpsc::Sender, oneshot};
-use tracing::{info, warn};
+use tokio::sync::{
+    broadcast,
+    mpsc::{self, error::SendError, Sender},
+    oneshot,
+};
+use tracing::{debug, info, warn};
 use types::ai::{
     query::QueryId,
-    request::{Role, SignedAiRequest},
+    request::{AiRequest, AiRequestOptions, Role, SignedAiRequest, SignedCancelRequest},
     response::SignedAiResponse,
-    verification::Verified,
+    stream::QueryEvent,
+    verification::{SignedVerificationResult, Verified},
 };
 
+/// Number of messages a task can buffer for each node.
+const MESSAGES_PER_NODE: usize = 64;
+const MAX_MODEL_NAME_LEN: usize = 128;
+const MAX_CALLBACK_LEN: usize = 2048;
+
 pub type NodeResponse = (PeerId, Result<SignedAiResponse, String>);
 
+#[allow(clippy::large_enum_variant)]
+pub enum TaskMessage {
+    Response(NodeResponse),
+    Chunk {
+        sender: PeerId,
+        offset: u64,
+        chunk: String,
+    },
+    Subscribe(oneshot::Sender<Option<AnswerStream>>),
+    Verdict(NodeVerdict),
+    /// The user `pubkey` cancels the query.
+    Cancel {
+        pubkey: PublicKey,
+        tx: oneshot::Sender<Result<(), OrchestratorError>>,
+    },
+}
+
+/// Verdict of the node `sender` on the response of the node `node`.
+pub struct NodeVerdict {
+    pub sender: PeerId,
+    pub node: PublicKey,
+    pub result: Result<SignedVerificationResult, String>,
+}
+
 pub struct Tasks {
     env: Arc<Env>,
-    tasks: HashMap<QueryId, Sender<NodeResponse>>,
+    tasks: HashMap<QueryId, Sender<TaskMessage>>,
+    webhooks: Option<Webhooks>,
 }
 
 impl Tasks {
     pub fn new(
         query: Queries,
         accounts: Accounts,
+        reputations: Reputations,
         cfg: &AiTasksConfig,
         verifier: Sender<VerificationRequest>,
+        aggregator: Sender<AggregationRequest>,
         etp: ToP2P,
     ) -> Self {
         Self {
-            env: Arc::new(Env::new(accounts, query, verifier, cfg.clone(), etp)),
+            env: Arc::new(Env::new(
+                accounts,
+                query,
+                reputations,
+                verifier,
+                aggregator,
+                cfg.clone(),
+                etp,
+            )),
             tasks: HashMap::new(),
+            webhooks: None,
         }
     }
 
+    /// The completed queries are posted to the callback URLs of the requesters.
+    pub fn with_webhooks(mut self, webhooks: Webhooks) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
     pub async fn new_task(
         &mut self,
         request: Verified<SignedAiRequest>,
@@ -55,32 +113,63 @@ impl Tasks {
         if has_system {
             return Err(OrchestratorError::SystemRoleIsNotAllowed);
         }
+        if let Err(err) = check_options(&self.env.cfg, &request.query.options)
+            .and_then(|()| check_confidential(&self.env.cfg, &request.query))
+        {
+            if tx.send(Err(err)).is_err() {
+                warn!("Failed to send response to orchestrator");
+            }
+            return Ok(());
+        }
 
         REQUESTS.add(1, &[]);
         PROCESSING.add(1, &[]);
 
         info!("Handle user request with pubkey: {}", request.query.pubkey);
-        let peer_pool = net.connected_peers(self.env.cfg.replication_factor as usize * 4);
+        let replication = self.env.cfg.replication(&request.query.options) as usize;
+        let peer_pool = net.connected_peers(replication * 4, &request.query);
 
         let env = self.env.clone();
-        let (task_tx, task_rx) = mpsc::channel(env.cfg.replication_factor as usize);
+        let webhooks = self.webhooks.clone();
+        let (task_tx, task_rx) = mpsc::channel(replication * MESSAGES_PER_NODE);
         let id = env.new_id(&request);
         self.tasks.insert(id, task_tx);
 
-        tokio::task::spawn_blocking(move || match env.new_query(id, request) {
-            Ok(query) => {
-                let mut task = Task::new(query, peer_pool, env, task_rx);
-                tokio::task::spawn(async move {
-                    if let Err(err) = task.run(tx).await {
-                        ERRORS.add(1, &[]);
-                        warn!("Task {:?} failed: {:#?}", task.id(), err);
-                    }
-                    PROCESSING.add(-1, &[]);
+        tokio::task::spawn_blocking(move || {
+            let query = env
+                .reserve(
+                    id,
+                    request.query.pubkey,
+                    env.cfg.max_query_cost(&request.query.options),
+                )
+                .and_then(|()| {
+                    env.new_query(id, request).map_err(|err| {
+                        if let Err(err) = env.refund(id) {
+                            warn!("Failed to refund query {id}: {:?}", err);
+                        }
+                        OrchestratorError::StorageError(err)
+                    })
                 });
-            }
-            Err(err) => {
-                if tx.send(Err(OrchestratorError::StorageError(err))).is_err() {
-                    warn!("Failed to send response to orchestrator");
+
+            match query {
+                Ok(query) => {
+                    let peer_pool = weigh_peers(&env, peer_pool);
+                    let mut task = Task::new(query, peer_pool, env, task_rx);
+                    tokio::task::spawn(async move {
+                        if let Err(err) = task.run(tx).await {
+                            ERRORS.add(1, &[]);
+                            warn!("Task {:?} failed: {:#?}", task.id(), err);
+                        }
+                        task.refund().await;
+                        task.notify(webhooks.as_ref());
+                        PROCESSING.add(-1, &[]);
+                    });
+                }
+                Err(err) => {
+                    PROCESSING.add(-1, &[]);
+                    if tx.send(Err(err)).is_err() {
+                        warn!("Failed to send response to orchestrator");
+                    }
                 }
             }
         });
@@ -88,6 +177,78 @@ impl Tasks {
         Ok(())
     }
 
+    /// Continues or finalizes the queries left in progress by the previous run,
+    /// according to the recovery policy.
+    /// The interrupted webhook deliveries are resumed too.
+    pub async fn recover(&mut self, net: &Network) -> Result<(), OrchestratorError> {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.resume().await;
+        }
+        let env = self.env.clone();
+        let queries = tokio::task::spawn_blocking(move || env.in_progress_queries()).await??;
+        let queries = queries
+            .into_iter()
+            .filter(|query| !self.tasks.contains_key(&query.id))
+            .collect::<Vec<_>>();
+        if queries.is_empty() {
+            return Ok(());
+        }
+
+        let policy = self.env.cfg.recovery.policy;
+        info!(
+            "Recovering {} queries in progress: {policy:?}",
+            queries.len()
+        );
+        for query in queries {
+            let replication = self.env.cfg.replication(&query.request.query.options) as usize;
+            let peer_pool = match policy {
+                RecoveryPolicy::Resume => {
+                    net.connected_peers(replication * 4, &query.request.query)
+                        .into_iter()
+                        .filter(|node| {
+                            query.response.iter().all(|result| {
+                                result.is_sent_request() || result.node_key() != node.key
+                            })
+                        })
+                        .collect()
+                }
+                RecoveryPolicy::Timeout => vec![],
+            };
+
+            PROCESSING.add(1, &[]);
+            let env = self.env.clone();
+            let webhooks = self.webhooks.clone();
+            let (task_tx, task_rx) = mpsc::channel(replication * MESSAGES_PER_NODE);
+            self.tasks.insert(query.id, task_tx);
+
+            tokio::spawn(async move {
+                let task_env = env.clone();
+                let peer_pool =
+                    tokio::task::spawn_blocking(move || weigh_peers(&task_env, peer_pool)).await;
+                let result = match peer_pool {
+                    Ok(peer_pool) => {
+                        let mut task = Task::new(query, peer_pool, env, task_rx);
+                        let result = match policy {
+                            RecoveryPolicy::Resume => task.resume().await,
+                            RecoveryPolicy::Timeout => task.expire().await,
+                        };
+                        task.refund().await;
+                        task.notify(webhooks.as_ref());
+                        result
+                    }
+                    Err(err) => Err(err.into()),
+                };
+                if let Err(err) = result {
+                    ERRORS.add(1, &[]);
+                    warn!("Failed to recover task: {:#?}", err);
+                }
+                PROCESSING.add(-1, &[]);
+            });
+        }
+
+        Ok(())
+    }
+
     pub async fn on_node_response(
         &mut self,
         id: QueryId,
@@ -95,14 +256,184 @@ impl Tasks {
         response: Result<SignedAiResponse, String>,
     ) {
         if let Some(task) = self.tasks.get_mut(&id) {
-            let result = task.send((sender, response)).await;
-            if let Err(err) = result {
-                info!("Task {:?} is closed: {:?}", id, err);
+            let result = task.send(TaskMessage::Response((sender, response))).await;
+            if result.is_err() {
+                info!("Task {:?} is closed", id);
+            }
+        }
+    }
+
+    pub async fn on_verify_response(&mut self, id: QueryId, verdict: NodeVerdict) {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            let result = task.send(TaskMessage::Verdict(verdict)).await;
+            if result.is_err() {
+                info!("Task {:?} is closed", id);
             }
         }
     }
 
+    /// Chunks are best effort: they are dropped if the task is busy,
+    /// the final response is delivered anyway.
+    pub fn on_node_chunk(&self, id: QueryId, sender: PeerId, offset: u64, chunk: String) {
+        if let Some(task) = self.tasks.get(&id) {
+            if task
+                .try_send(TaskMessage::Chunk {
+                    sender,
+                    offset,
+                    chunk,
+                })
+                .is_err()
+            {
+                debug!("Chunk of the query {:?} is dropped", id);
+            }
+        }
+    }
+
+    pub async fn subscribe(&self, id: QueryId, tx: oneshot::Sender<Option<AnswerStream>>) {
+        let Some(task) = self.tasks.get(&id) else {
+            if tx.send(None).is_err() {
+                warn!("Failed to send answer stream to api");
+            }
+            return;
+        };
+
+        if let Err(SendError(TaskMessage::Subscribe(tx))) =
+            task.send(TaskMessage::Subscribe(tx)).await
+        {
+            if tx.send(None).is_err() {
+                warn!("Failed to send answer stream to api");
+            }
+        }
+    }
+
+    pub async fn cancel(
+        &self,
+        request: Verified<SignedCancelRequest>,
+        tx: oneshot::Sender<Result<(), OrchestratorError>>,
+    ) {
+        let request = request.into_inner().request;
+        let Some(task) = self.tasks.get(&request.id) else {
+            if tx
+                .send(Err(OrchestratorError::QueryIsNotInProgress(request.id)))
+                .is_err()
+            {
+                warn!("Failed to send response to api");
+            }
+            return;
+        };
+
+        if let Err(SendError(TaskMessage::Cancel { tx, .. })) = task
+            .send(TaskMessage::Cancel {
+                pubkey: request.pubkey,
+                tx,
+            })
+            .await
+        {
+            if tx
+                .send(Err(OrchestratorError::QueryIsNotInProgress(request.id)))
+                .is_err()
+            {
+                warn!("Failed to send response to api");
+            }
+        }
+    }
+
+    pub fn subscribe_events(&self) -> broadcast::Receiver<QueryEvent> {
+        self.env.subscribe_events()
+    }
+
     pub fn gc_tasks(&mut self) {
         self.tasks.retain(|_, task| !task.is_closed());
     }
 }
+
+/// Checks the options requested by the user against the operator's limits.
+fn check_options(cfg: &AiTasksConfig, options: &AiRequestOptions) -> Result<(), OrchestratorError> {
+    let limits = &cfg.request_limits;
+    if let Some(temperature) = options.temperature {
+        if !(0.0..=2.0).contains(&temperature) {
+            return Err(OrchestratorError::InvalidOptions(
+                "temperature must be in range 0..=2",
+            ));
+        }
+    }
+    if let Some(top_p) = options.top_p {
+        if !(0.0..=1.0).contains(&top_p) {
+            return Err(OrchestratorError::InvalidOptions(
+                "top_p must be in range 0..=1",
+            ));
+        }
+    }
+    if let Some(max_tokens) = options.max_tokens {
+        if max_tokens == 0 || max_tokens > limits.max_tokens {
+            return Err(OrchestratorError::InvalidOptions(
+                "max_tokens is out of the allowed range",
+            ));
+        }
+    }
+    if let Some(model) = &options.model {
+        if model.is_empty() || model.len() > MAX_MODEL_NAME_LEN {
+            return Err(OrchestratorError::InvalidOptions("invalid model name"));
+        }
+    }
+    if let Some(callback) = &options.callback {
+        if !cfg.webhooks.enabled {
+            return Err(OrchestratorError::InvalidOptions("callbacks are disabled"));
+        }
+        if callback.len() > MAX_CALLBACK_LEN {
+            return Err(OrchestratorError::InvalidOptions("invalid callback URL"));
+        }
+        check_callback(&cfg.webhooks, callback).map_err(OrchestratorError::InvalidOptions)?;
+    }
+    if let Some(replication) = options.replication {
+        if !(limits.min_replication..=limits.max_replication).contains(&replication) {
+            return Err(OrchestratorError::InvalidOptions(
+                "replication is out of the allowed range",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The confidential requests must not carry the plaintext. The orchestrator can't read
+/// their responses, so they are sealed to another recipient at least to be inspected.
+fn check_confidential(cfg: &AiTasksConfig, request: &AiRequest) -> Result<(), OrchestratorError> {
+    let Some(sealed) = &request.sealed else {
+        return Ok(());
+    };
+    if !request.message.is_empty() || !request.history.is_empty() {
+        return Err(OrchestratorError::InvalidOptions(
+            "confidential request contains plaintext",
+        ));
+    }
+    if cfg.cross_verification.inspectors == 0 {
+        return Err(OrchestratorError::InvalidOptions(
+            "confidential requests are not supported",
+        ));
+    }
+    let nodes = sealed
+        .recipients()
+        .filter(|recipient| **recipient != request.pubkey)
+        .collect::<HashSet<_>>();
+    if nodes.len() < 2 {
+        return Err(OrchestratorError::InvalidOptions(
+            "confidential request must be readable by 2 nodes at least",
+        ));
+    }
+    Ok(())
+}
+
+/// Pairs the peers with their selection weights.
+fn weigh_peers(env: &Env, peers: Vec<ConnectedNode>) -> Vec<(ConnectedNode, f64)> {
+    peers
+        .into_iter()
+        .map(|node| {
+            let weight = env
+                .reputation(&node.key)
+                .inspect_err(|err| warn!("Failed to load reputation: {err:?}"))
+                .unwrap_or_default()
+                .weight();
+            (node, weight)
+        })
+        .collect()
+}
@@ -1,36 +1,77 @@
-use super::{env::Env, NodeResponse};
+use super::{env::Env, NodeResponse, NodeVerdict, TaskMessage};
 use crate::{
-    network::ConnectedNode,
+    error::EvaluatorError,
+    network::{ConnectedNode, LoadGuard},
     verifier::{VerificationRequest, VerificationResponse},
-    OrchestratorError,
+    webhook::Webhooks,
+    AnswerStream, OrchestratorError,
 };
 use crypto::ed25519::public::PublicKey;
-use metrics::{LATENCY, TIMEOUTS};
-use rand::Rng as _;
-use std::{sync::Arc, time::Duration};
+use futures::future::join_all;
+use metrics::{HEDGES, LATENCY, TIMEOUTS};
+use multiaddr::PeerId;
+use rand::{
+    distributions::{Distribution as _, WeightedIndex},
+    seq::SliceRandom as _,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
     select,
-    sync::{mpsc::Receiver, oneshot},
+    sync::{broadcast, mpsc::Receiver, oneshot},
     time::{sleep_until, Instant},
 };
-use tracing::{info, warn};
-use types::ai::query::{NodeResult, Query, QueryId};
+use tracing::{debug, info, warn};
+use types::{
+    ai::{
+        query::{NodeResult, Query, QueryId},
+        stream::{QueryEvent, StreamEvent},
+        verification::InspectorVerdict,
+    },
+    cluster::TaskOutcome,
+};
+
+/// Number of events a slow subscriber can lag behind.
+const STREAM_CAPACITY: usize = 256;
+/// Chunks a node can send ahead of the missing ones.
+const MAX_PENDING_CHUNKS: usize = 64;
+/// Bytes of the chunks received ahead of the missing ones, per node.
+const MAX_PENDING_BYTES: usize = 64 * 1024;
 
 pub struct Task {
     query: Option<Query>,
-    peer_pool: Vec<ConnectedNode>,
+    /// Candidates with their selection weights.
+    peer_pool: Vec<(ConnectedNode, f64)>,
     env: Arc<Env>,
-    rx: Receiver<NodeResponse>,
+    rx: Receiver<TaskMessage>,
     used_nodes: Vec<ConnectedNode>,
+    sent_at: HashMap<PublicKey, Instant>,
+    /// Slots of the nodes that haven't responded yet.
+    in_flight: HashMap<PublicKey, LoadGuard>,
+    /// Number of requests sent in place of the failed or silent nodes.
+    hedges: u64,
+    /// Failed or silent nodes already replaced by other ones.
+    hedged: HashSet<PublicKey>,
+    /// Response time of the nodes in seconds.
+    latency: HashMap<PublicKey, f64>,
     verifier_results: Vec<oneshot::Receiver<VerificationResponse>>,
+    /// Responses judged by the other nodes, by the responder.
+    inspections: HashMap<PublicKey, Inspection>,
+    partial: HashMap<PeerId, PartialAnswer>,
+    events: broadcast::Sender<StreamEvent>,
+    /// The node results last published to the event feed.
+    published: HashMap<PublicKey, NodeResult>,
 }
 
 impl Task {
     pub fn new(
         query: Query,
-        peer_pool: Vec<ConnectedNode>,
+        peer_pool: Vec<(ConnectedNode, f64)>,
         env: Arc<Env>,
-        rx: Receiver<NodeResponse>,
+        rx: Receiver<TaskMessage>,
     ) -> Self {
         Self {
             query: Some(query),
@@ -38,7 +79,16 @@ impl Task {
             env,
             rx,
             used_nodes: vec![],
+            sent_at: HashMap::new(),
+            in_flight: HashMap::new(),
+            hedges: 0,
+            hedged: HashSet::new(),
+            latency: HashMap::new(),
             verifier_results: vec![],
+            inspections: HashMap::new(),
+            partial: HashMap::new(),
+            events: broadcast::channel(STREAM_CAPACITY).0,
+            published: HashMap::new(),
         }
     }
 
@@ -59,13 +109,79 @@ impl Task {
         tx: oneshot::Sender<Result<QueryId, OrchestratorError>>,
     ) -> Result<(), OrchestratorError> {
         info!("Spawn task for query: {}", self.id());
-        let deadline = Instant::now() + Duration::from_secs(self.env.cfg.task_timeout_secs);
+        let deadline = self.deadline();
+        self.dispatch(deadline).await?;
+
+        let result = self.store_query().await;
+        tx.send(Ok(*self.id()))
+            .map_err(|_| OrchestratorError::EyreError(eyre::eyre!("Failed to send result")))?;
+        result?;
+
+        self.process(deadline).await
+    }
+
+    /// Continues the query left in progress by the previous run of the orchestrator.
+    /// The peer pool must not contain the nodes that already responded.
+    pub async fn resume(&mut self) -> Result<(), OrchestratorError> {
+        info!("Resume task for query: {}", self.id());
+        let deadline = self.deadline();
+
+        let query = self.query.as_mut().expect("Query is not set");
+        // the requests sent before the restart are lost
+        query.response.retain(|result| !result.is_sent_request());
+        let request_ts = query.request.query.timestamp;
+        let unverified = query
+            .response
+            .iter()
+            .filter_map(NodeResult::as_node_response)
+            .map(|response| {
+                let latency = response.node_response.timestamp.saturating_sub(request_ts);
+                (response.node_key(), latency as f64)
+            })
+            .collect::<Vec<_>>();
+        for (node_key, latency) in unverified {
+            self.latency.insert(node_key, latency);
+            self.verify(node_key).await?;
+        }
 
-        while self.response_nodes() < self.env.cfg.replication_factor as usize
+        self.dispatch(deadline).await?;
+        self.store_query().await?;
+
+        self.process(deadline).await
+    }
+
+    /// Finalizes the query left in progress by the previous run of the orchestrator:
+    /// the unfinished requests are marked as timed out.
+    pub async fn expire(&mut self) -> Result<(), OrchestratorError> {
+        info!("Expire query: {}", self.id());
+        let query = self.query.as_mut().expect("Query is not set");
+        for node in query.response.iter_mut() {
+            if node.is_sent_request() || node.is_node_response() {
+                *node = NodeResult::Timeout(Box::new(node.clone()));
+            }
+        }
+
+        self.store_query().await?;
+        self.aggregate(self.deadline()).await
+    }
+
+    fn deadline(&self) -> Instant {
+        Instant::now() + Duration::from_secs(self.env.cfg.task_timeout_secs)
+    }
+
+    fn replication(&self) -> usize {
+        let query = self.query.as_ref().expect("Query is not set");
+        self.env.cfg.replication(&query.request.query.options) as usize
+    }
+
+    /// Sends the request to the nodes until the replication factor is reached.
+    async fn dispatch(&mut self, deadline: Instant) -> Result<(), OrchestratorError> {
+        let replication = self.replication();
+        while self.response_nodes() < replication
             && !self.peer_pool.is_empty()
             && Instant::now() < deadline
         {
-            let nodes_count = self.env.cfg.replication_factor as usize - self.response_nodes();
+            let nodes_count = replication - self.response_nodes();
             let nodes = self.select_workers(nodes_count).await?;
             self.query
                 .as_mut()
@@ -73,12 +189,11 @@ impl Task {
                 .response
                 .extend(nodes.iter().map(|key| NodeResult::SentRequest(*key)));
         }
+        Ok(())
+    }
 
-        let result = self.store_query().await;
-        tx.send(Ok(*self.id()))
-            .map_err(|_| OrchestratorError::EyreError(eyre::eyre!("Failed to send result")))?;
-        result?;
-
+    /// Waits for the node responses and their verification.
+    async fn process(&mut self, deadline: Instant) -> Result<(), OrchestratorError> {
         let req_ts = {
             let query = self.query.as_ref().expect("Query is not set");
             query.request.query.timestamp
@@ -92,18 +207,28 @@ impl Task {
         }
 
         info!("Waiting for results for query: {}", self.id());
-        loop {
+        while !self.all_request_received() {
+            if !self.hedged.is_empty() && self.answered() >= self.replication() {
+                // the replication is reached with the help of the hedged requests
+                self.cancel_superseded().await?;
+                break;
+            }
+            let soft_deadline = self.soft_deadline();
+            let inspection_deadline = self.inspection_deadline();
             select! {
                 _ = sleep_until(deadline) => {
                     TIMEOUTS.add(1, &[]);
                     self.set_timeout_error().await?;
                     break;
                 }
-                Some(result) = self.rx.recv() => {
-                    self.set_node_result(result).await?;
-                    if self.all_request_received() {
-                        break;
-                    }
+                _ = sleep_until(soft_deadline.unwrap_or(deadline)), if soft_deadline.is_some() => {
+                    self.replace_silent().await?;
+                }
+                _ = sleep_until(inspection_deadline.unwrap_or(deadline)), if inspection_deadline.is_some() => {
+                    self.expire_inspections().await?;
+                }
+                Some(msg) = self.rx.recv() => {
+                    self.on_message(msg).await?;
                 }
                else => {
                     break;
@@ -113,13 +238,21 @@ impl Task {
         info!("Query: {}. Sending results to verifier", self.id());
 
         while !self.verifier_results.is_empty() {
+            let inspection_deadline = self.inspection_deadline();
             select! {
                 _ = sleep_until(deadline) => {
                     TIMEOUTS.add(1, &[]);
                     self.set_timeout_error().await?;
                     break;
                 }
-                result = self.verifier_results.remove(0) => {
+                _ = sleep_until(inspection_deadline.unwrap_or(deadline)), if inspection_deadline.is_some() => {
+                    self.expire_inspections().await?;
+                }
+                Some(msg) = self.rx.recv() => {
+                    self.on_message(msg).await?;
+                }
+                result = &mut self.verifier_results[0] => {
+                    self.verifier_results.remove(0);
                     match result {
                         Ok(response) => {
                            self.set_verifier_result(response).await?;
@@ -129,6 +262,8 @@ impl Task {
                 }
             }
         }
+        self.aggregate(deadline).await?;
+
         let res_ts = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -140,6 +275,95 @@ impl Task {
         Ok(())
     }
 
+    async fn on_message(&mut self, msg: TaskMessage) -> Result<(), OrchestratorError> {
+        match msg {
+            TaskMessage::Response(result) => {
+                let sender = result.0;
+                self.set_node_result(result).await?;
+                self.partial.remove(&sender);
+            }
+            TaskMessage::Chunk {
+                sender,
+                offset,
+                chunk,
+            } => self.add_chunk(sender, offset, chunk),
+            TaskMessage::Subscribe(tx) => {
+                if tx.send(Some(self.subscribe())).is_err() {
+                    warn!("Failed to send answer stream to api");
+                }
+            }
+            TaskMessage::Verdict(verdict) => self.on_verdict(verdict).await?,
+            TaskMessage::Cancel { pubkey, tx } => {
+                let result = if pubkey
+                    == self
+                        .query
+                        .as_ref()
+                        .expect("Query is not set")
+                        .request
+                        .query
+                        .pubkey
+                {
+                    self.cancel().await
+                } else {
+                    Err(OrchestratorError::NotQueryOwner(*self.id()))
+                };
+                if tx.send(result).is_err() {
+                    warn!("Failed to send response to api");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_chunk(&mut self, sender: PeerId, offset: u64, chunk: String) {
+        let Some(node) = self.used_nodes.iter().find(|node| node.peer_id == sender) else {
+            warn!("Received chunk from unknown node: {sender}");
+            return;
+        };
+        let in_progress = self
+            .query
+            .as_ref()
+            .expect("Query is not set")
+            .response
+            .iter()
+            .any(|result| result.node_key() == node.key && result.is_sent_request());
+        if !in_progress {
+            return;
+        }
+
+        let partial = self.partial.entry(sender).or_default();
+        let Some(appended) = partial.push(offset, chunk) else {
+            warn!("Dropped chunk at offset {offset} out of the reorder window of {sender}");
+            return;
+        };
+        for (offset, text) in appended {
+            // there may be no subscribers
+            let _ = self.events.send(StreamEvent::Chunk {
+                node: node.key,
+                offset,
+                text,
+            });
+        }
+    }
+
+    fn subscribe(&self) -> AnswerStream {
+        let query = self.query.as_ref().expect("Query is not set");
+        let mut snapshot = vec![StreamEvent::Query(query.clone())];
+        snapshot.extend(self.used_nodes.iter().filter_map(|node| {
+            let partial = self.partial.get(&node.peer_id)?;
+            Some(StreamEvent::Chunk {
+                node: node.key,
+                offset: 0,
+                text: partial.text.clone(),
+            })
+        }));
+
+        AnswerStream {
+            snapshot,
+            events: self.events.subscribe(),
+        }
+    }
+
     async fn set_verifier_result(
         &mut self,
         response: VerificationResponse,
@@ -151,25 +375,82 @@ impl Task {
             .find(|node| node.node_key() == response.node_key)
             .ok_or_else(|| OrchestratorError::InvalidSender)?;
 
-        *node_result = NodeResult::Verified(Box::new(response.verification_result));
+        let latency = self
+            .latency
+            .get(&response.node_key)
+            .copied()
+            .unwrap_or_default();
+        let max_tokens = self
+            .env
+            .cfg
+            .max_response_tokens(&query.request.query.options);
+        let (cost, outcome) = match response.verification_result {
+            Ok(verification_result) => {
+                let relevance = verification_result.result.relevance.inner();
+                let tokens = verification_result
+                    .result
+                    .material
+                    .node_response
+                    .cost
+                    .min(max_tokens);
+                *node_result = NodeResult::Verified(Box::new(verification_result));
+                (
+                    self.env.cfg.billing.response_cost(tokens, relevance),
+                    TaskOutcome::Verified { relevance, latency },
+                )
+            }
+            Err(err) => {
+                let NodeResult::NodeResponse(material) = node_result else {
+                    return Err(OrchestratorError::InvalidSender);
+                };
+                warn!(
+                    "Query: {}. Failed to verify response of {}: {}",
+                    query.id, response.node_key, err
+                );
+                let tokens = material.node_response.cost.min(max_tokens);
+                *node_result = NodeResult::Unverified(material.clone(), err.to_string());
+                // the reputation is kept, the node may not be to blame for the failure
+                // of the evaluators
+                (
+                    self.env.cfg.billing.unverified_cost(tokens),
+                    TaskOutcome::Unverified { latency },
+                )
+            }
+        };
+
+        self.settle(response.node_key, cost).await;
+        self.record_outcomes(vec![(response.node_key, outcome)])
+            .await;
 
         self.store_query().await?;
         Ok(())
     }
 
+    async fn aggregate(&mut self, deadline: Instant) -> Result<(), OrchestratorError> {
+        let query = self.query.as_ref().expect("Query is not set");
+        if !query.response.iter().any(NodeResult::is_verified) {
+            return Ok(());
+        }
+
+        let answer = self.env.aggregate(query.clone(), deadline).await?;
+        self.query.as_mut().expect("Query is not set").answer = answer;
+        self.store_query().await
+    }
+
     fn all_request_received(&self) -> bool {
         self.query
             .as_ref()
             .expect("Query is not set")
             .response
             .iter()
-            .all(|node| matches!(node, NodeResult::NodeResponse(_) | NodeResult::Error(_, _)))
+            .all(|node| !node.is_sent_request())
     }
 
     async fn set_node_result(&mut self, result: NodeResponse) -> Result<(), OrchestratorError> {
         let query = self.query.as_mut().expect("Query is not set");
         let node = self.used_nodes.iter().find(|node| node.peer_id == result.0);
 
+        let mut failed_node = None;
         let send_to_verifier = {
             let node_result = if let Some(node) = node {
                 let response = query.response.iter_mut().find(|n| n.node_key() == node.key);
@@ -183,18 +464,19 @@ impl Task {
             };
 
             if node_result.is_sent_request() {
+                self.in_flight.remove(&node_result.node_key());
                 match result.1 {
                     Ok(ok) => {
-                        self.env.transfer(
-                            query.request.query.pubkey,
-                            node_result.node_key(),
-                            ok.node_response.cost,
-                        )?;
                         *node_result = NodeResult::NodeResponse(ok);
-                        Some(node_result.node_key())
+                        let key = node_result.node_key();
+                        if let Some(sent_at) = self.sent_at.get(&key) {
+                            self.latency.insert(key, sent_at.elapsed().as_secs_f64());
+                        }
+                        Some(key)
                     }
                     Err(err) => {
                         *node_result = NodeResult::Error(node_result.node_key(), err);
+                        failed_node = Some(node_result.node_key());
                         None
                     }
                 }
@@ -205,26 +487,441 @@ impl Task {
         };
 
         if let Some(node_key) = send_to_verifier {
-            let (tx, rx) = tokio::sync::oneshot::channel();
-            self.env
-                .send_to_evaluator(VerificationRequest::new(query, node_key, tx)?)
-                .await?;
-            self.verifier_results.push(rx);
+            self.verify(node_key).await?;
+        }
+        if let Some(node_key) = failed_node {
+            self.record_outcomes(vec![(node_key, TaskOutcome::Error)])
+                .await;
+            if self.hedged.insert(node_key) {
+                self.hedge().await?;
+            }
         }
         self.store_query().await?;
 
         Ok(())
     }
 
+    /// Number of the nodes that responded.
+    fn answered(&self) -> usize {
+        self.query
+            .as_ref()
+            .expect("Query is not set")
+            .response
+            .iter()
+            .filter(|node| node.is_node_response() || node.is_verified() || node.is_unverified())
+            .count()
+    }
+
+    /// Time when the earliest of the silent nodes is to be replaced.
+    fn soft_deadline(&self) -> Option<Instant> {
+        let hedging = &self.env.cfg.hedging;
+        if self.hedges >= hedging.max_hedges || self.peer_pool.is_empty() {
+            return None;
+        }
+        self.query
+            .as_ref()
+            .expect("Query is not set")
+            .response
+            .iter()
+            .filter(|node| node.is_sent_request() && !self.hedged.contains(&node.node_key()))
+            .filter_map(|node| self.sent_at.get(&node.node_key()))
+            .min()
+            .map(|sent_at| *sent_at + Duration::from_secs(hedging.soft_deadline_secs))
+    }
+
+    /// Replaces the nodes that haven't responded before the soft deadline.
+    async fn replace_silent(&mut self) -> Result<(), OrchestratorError> {
+        let soft_deadline = Duration::from_secs(self.env.cfg.hedging.soft_deadline_secs);
+        let silent = self
+            .query
+            .as_ref()
+            .expect("Query is not set")
+            .response
+            .iter()
+            .filter(|node| node.is_sent_request())
+            .map(NodeResult::node_key)
+            .filter(|key| {
+                self.sent_at
+                    .get(key)
+                    .is_some_and(|sent_at| sent_at.elapsed() >= soft_deadline)
+            })
+            .collect::<Vec<_>>();
+        self.hedged.extend(silent);
+        self.hedge().await?;
+        self.store_query().await
+    }
+
+    /// Sends the request to additional nodes to replace the failed and silent ones.
+    async fn hedge(&mut self) -> Result<(), OrchestratorError> {
+        let remaining = self.env.cfg.hedging.max_hedges.saturating_sub(self.hedges);
+        let expected = self
+            .query
+            .as_ref()
+            .expect("Query is not set")
+            .response
+            .iter()
+            .filter(|node| {
+                node.is_node_response()
+                    || node.is_verified()
+                    || node.is_unverified()
+                    || (node.is_sent_request() && !self.hedged.contains(&node.node_key()))
+            })
+            .count();
+        let missing = self.replication().saturating_sub(expected);
+        let nodes_count = missing.min(remaining as usize);
+        if nodes_count == 0 {
+            return Ok(());
+        }
+
+        let nodes = self.select_workers(nodes_count).await?;
+        info!("Query: {}. Hedged to {} nodes", self.id(), nodes.len());
+        self.hedges += nodes.len() as u64;
+        HEDGES.add(nodes.len() as u64, &[]);
+        self.query
+            .as_mut()
+            .expect("Query is not set")
+            .response
+            .extend(nodes.iter().map(|key| NodeResult::SentRequest(*key)));
+        Ok(())
+    }
+
+    async fn verify(&mut self, node_key: PublicKey) -> Result<(), OrchestratorError> {
+        let (tx, rx) = oneshot::channel();
+        let cross_verification = &self.env.cfg.cross_verification;
+        let sent_at = Instant::now();
+        let deadline = sent_at + Duration::from_secs(cross_verification.timeout_secs);
+        let pending = if cross_verification.inspectors > 0 {
+            self.select_inspectors(node_key, deadline).await
+        } else {
+            HashMap::new()
+        };
+
+        if pending.is_empty() {
+            let query = self.query.as_ref().expect("Query is not set");
+            if query.request.query.is_confidential() {
+                // the orchestrator can't read the response to judge it
+                let _ = tx.send(VerificationResponse {
+                    node_key,
+                    verification_result: Err(EvaluatorError::Confidential),
+                });
+            } else {
+                self.env
+                    .send_to_evaluator(VerificationRequest::new(query, node_key, tx)?)
+                    .await?;
+            }
+        } else {
+            info!(
+                "Query: {}. Response of {} is sent to {} inspectors",
+                self.id(),
+                node_key,
+                pending.len()
+            );
+            self.inspections.insert(
+                node_key,
+                Inspection {
+                    pending,
+                    verdicts: vec![],
+                    sent_at,
+                    deadline,
+                    on_result: tx,
+                },
+            );
+        }
+        self.verifier_results.push(rx);
+        Ok(())
+    }
+
+    /// Sends the response of the node to the other nodes to judge.
+    /// The requests are sent together, the inspectors that fail to confirm theirs
+    /// before the deadline are replaced by the next candidates.
+    async fn select_inspectors(
+        &self,
+        node_key: PublicKey,
+        deadline: Instant,
+    ) -> HashMap<PeerId, (PublicKey, LoadGuard)> {
+        let query = self.query.as_ref().expect("Query is not set");
+        let Some(response) = query
+            .response
+            .iter()
+            .filter_map(NodeResult::as_node_response)
+            .find(|response| response.node_key() == node_key)
+            .cloned()
+        else {
+            return HashMap::new();
+        };
+
+        // the responder never judges its own response,
+        // the confidential responses are readable by the recipients of the request only
+        let mut candidates = self
+            .used_nodes
+            .iter()
+            .chain(self.peer_pool.iter().map(|(node, _)| node))
+            .filter(|node| node.key != node_key)
+            .filter(|node| {
+                query
+                    .request
+                    .query
+                    .sealed
+                    .as_ref()
+                    .map_or(true, |sealed| sealed.is_recipient(&node.key))
+            })
+            .collect::<Vec<_>>();
+        candidates.shuffle(&mut rand::thread_rng());
+        let mut candidates = candidates.into_iter();
+
+        let mut pending = HashMap::new();
+        let inspectors = self.env.cfg.cross_verification.inspectors as usize;
+        while pending.len() < inspectors && Instant::now() < deadline {
+            let mut sent = vec![];
+            while pending.len() + sent.len() < inspectors {
+                let Some(node) = candidates.next() else {
+                    break;
+                };
+                let Some(guard) = node.try_acquire() else {
+                    debug!("Node {} is saturated, skipping", node.key);
+                    continue;
+                };
+                if let Ok(rx) = self
+                    .env
+                    .send_verify_request(
+                        node.peer_id,
+                        query.id,
+                        query.request.clone(),
+                        response.clone(),
+                    )
+                    .await
+                {
+                    sent.push(((node, guard), rx));
+                }
+            }
+            if sent.is_empty() {
+                break;
+            }
+
+            let (nodes, confirmations): (Vec<_>, Vec<_>) = sent.into_iter().unzip();
+            let confirmations = join_all(
+                confirmations
+                    .into_iter()
+                    .map(|rx| tokio::time::timeout_at(deadline, rx)),
+            )
+            .await;
+            for ((node, guard), confirmation) in nodes.into_iter().zip(confirmations) {
+                match confirmation {
+                    Ok(Ok(result)) if result.is_success() => {
+                        pending.insert(node.peer_id, (node.key, guard));
+                    }
+                    confirmation => warn!(
+                        "Failed to send verification request to node: {:?} {:?}",
+                        node.key, confirmation
+                    ),
+                }
+            }
+        }
+        pending
+    }
+
+    /// Collects the verdict of the inspector. The valid verdicts are paid.
+    async fn on_verdict(&mut self, verdict: NodeVerdict) -> Result<(), OrchestratorError> {
+        let query = self.query.as_ref().expect("Query is not set");
+        let Some(inspection) = self.inspections.get_mut(&verdict.node) else {
+            warn!(
+                "Unexpected verdict on node {} from {}",
+                verdict.node, verdict.sender
+            );
+            return Ok(());
+        };
+        let Some((inspector, _guard)) = inspection.pending.remove(&verdict.sender) else {
+            warn!(
+                "Unexpected verdict on node {} from {}",
+                verdict.node, verdict.sender
+            );
+            return Ok(());
+        };
+
+        let material = query
+            .response
+            .iter()
+            .filter_map(NodeResult::as_node_response)
+            .find(|response| response.node_key() == verdict.node);
+        let valid = match verdict.result {
+            Ok(result) => {
+                if result.result.inspector != inspector {
+                    warn!("Verdict of {} is signed by another key", inspector);
+                    false
+                } else if let Err(err) = result.verify() {
+                    warn!("Invalid verdict signature of {}: {}", inspector, err);
+                    false
+                } else if material != Some(&result.result.material) {
+                    warn!("Verdict of {} is on another response", inspector);
+                    false
+                } else {
+                    inspection.verdicts.push(InspectorVerdict::from(result));
+                    true
+                }
+            }
+            Err(err) => {
+                warn!("Node {} failed to verify the response: {}", inspector, err);
+                false
+            }
+        };
+        let latency = inspection.sent_at.elapsed().as_secs_f64();
+        let finished = inspection.pending.is_empty();
+
+        let outcome = if valid {
+            let tokens = material.map_or(0, |material| {
+                material.node_response.cost.min(
+                    self.env
+                        .cfg
+                        .max_response_tokens(&query.request.query.options),
+                )
+            });
+            self.settle(inspector, self.env.cfg.inspection_cost(tokens))
+                .await;
+            TaskOutcome::Inspected { latency }
+        } else {
+            TaskOutcome::Error
+        };
+        self.record_outcomes(vec![(inspector, outcome)]).await;
+
+        if finished {
+            self.finish_inspection(verdict.node).await?;
+        }
+        Ok(())
+    }
+
+    /// Time when the earliest of the inspections is to be finished.
+    fn inspection_deadline(&self) -> Option<Instant> {
+        self.inspections
+            .values()
+            .map(|inspection| inspection.deadline)
+            .min()
+    }
+
+    /// Finishes the inspections with the verdicts received before the timeout.
+    async fn expire_inspections(&mut self) -> Result<(), OrchestratorError> {
+        let now = Instant::now();
+        let expired = self
+            .inspections
+            .iter()
+            .filter(|(_, inspection)| inspection.deadline <= now)
+            .map(|(node_key, _)| *node_key)
+            .collect::<Vec<_>>();
+        for node_key in expired {
+            warn!("Query: {}. Inspection of {} timed out", self.id(), node_key);
+            self.finish_inspection(node_key).await?;
+        }
+        Ok(())
+    }
+
+    /// Combines the verdicts of the inspectors. The orchestrator judges the response
+    /// itself if there are none. The inspectors still silent are timed out.
+    async fn finish_inspection(&mut self, node_key: PublicKey) -> Result<(), OrchestratorError> {
+        let Some(inspection) = self.inspections.remove(&node_key) else {
+            return Ok(());
+        };
+        let silent = inspection
+            .pending
+            .values()
+            .map(|(inspector, _)| (*inspector, TaskOutcome::Timeout))
+            .collect();
+        self.record_outcomes(silent).await;
+        let query = self.query.as_ref().expect("Query is not set");
+        if inspection.verdicts.is_empty() && query.request.query.is_confidential() {
+            let _ = inspection.on_result.send(VerificationResponse {
+                node_key,
+                verification_result: Err(EvaluatorError::Confidential),
+            });
+            return Ok(());
+        }
+        let request = VerificationRequest::new(query, node_key, inspection.on_result)?
+            .with_verdicts(inspection.verdicts);
+        self.env.send_to_evaluator(request).await?;
+        Ok(())
+    }
+
+    /// Stops the nodes still generating the response. They are paid for the text
+    /// streamed so far, the responses already received are verified as usual.
+    async fn cancel(&mut self) -> Result<(), OrchestratorError> {
+        info!("Cancel query: {}", self.id());
+        let query = self.query.as_mut().expect("Query is not set");
+        let max_tokens = self
+            .env
+            .cfg
+            .max_response_tokens(&query.request.query.options);
+        let mut cancelled = vec![];
+        for node in query.response.iter_mut() {
+            if let NodeResult::SentRequest(public_key) = node {
+                cancelled.push(*public_key);
+                *node = NodeResult::Cancelled(*public_key);
+            }
+        }
+
+        let id = *self.id();
+        for key in cancelled {
+            self.in_flight.remove(&key);
+            let Some(node) = self.used_nodes.iter().find(|node| node.key == key) else {
+                continue;
+            };
+            let peer_id = node.peer_id;
+            if self.env.send_cancel(peer_id, id).await.is_err() {
+                warn!("Failed to send cancel to node: {key}");
+            }
+            let produced = self
+                .partial
+                .remove(&peer_id)
+                .map(|partial| partial.text.len())
+                .unwrap_or_default();
+            let cost = self.env.cfg.billing.partial_cost(produced, max_tokens);
+            if cost > 0 {
+                self.settle(key, cost).await;
+            }
+        }
+
+        self.store_query().await
+    }
+
+    /// Stops the silent nodes replaced by the hedged requests once the others have
+    /// answered. They were only outpaced, so their reputation is kept as it is.
+    async fn cancel_superseded(&mut self) -> Result<(), OrchestratorError> {
+        let query = self.query.as_mut().expect("Query is not set");
+        let mut superseded = vec![];
+        for node in query.response.iter_mut() {
+            if let NodeResult::SentRequest(public_key) = node {
+                superseded.push(*public_key);
+                *node = NodeResult::Cancelled(*public_key);
+            }
+        }
+
+        let id = *self.id();
+        for key in superseded {
+            info!("Query: {id}. Cancel the request superseded by the hedges: {key}");
+            self.in_flight.remove(&key);
+            let Some(node) = self.used_nodes.iter().find(|node| node.key == key) else {
+                continue;
+            };
+            let peer_id = node.peer_id;
+            if self.env.send_cancel(peer_id, id).await.is_err() {
+                warn!("Failed to send cancel to node: {key}");
+            }
+            self.partial.remove(&peer_id);
+        }
+
+        self.store_query().await
+    }
+
     async fn set_timeout_error(&mut self) -> Result<(), OrchestratorError> {
         info!("Set timeout error for query: {}", self.id());
         let query = self.query.as_mut().expect("Query is not set");
+        let mut outcomes = vec![];
         for node in query.response.iter_mut() {
             match node {
                 NodeResult::SentRequest(public_key) => {
+                    outcomes.push((*public_key, TaskOutcome::Timeout));
                     *node = NodeResult::Timeout(Box::new(NodeResult::SentRequest(*public_key)));
                 }
                 NodeResult::Verified(_)
+                | NodeResult::Unverified(_, _)
+                | NodeResult::Cancelled(_)
                 | NodeResult::Error(_, _)
                 | NodeResult::NodeResponse(_)
                 | NodeResult::Timeout(_) => {
@@ -232,10 +929,68 @@ impl Task {
                 }
             };
         }
+        self.record_outcomes(outcomes).await;
+        self.in_flight.clear();
 
         self.store_query().await
     }
 
+    async fn settle(&self, node: PublicKey, amount: u64) {
+        let env = self.env.clone();
+        let id = *self.id();
+        let result = tokio::task::spawn_blocking(move || env.settle(id, node, amount)).await;
+        match result {
+            Ok(Ok(paid)) if paid < amount => {
+                warn!("Query {id}: node {node} is paid {paid} instead of {amount}")
+            }
+            Ok(Err(err)) => warn!("Failed to pay node {node}: {:?}", err),
+            Err(err) => warn!("Failed to pay node {node}: {:?}", err),
+            Ok(Ok(_)) => {}
+        }
+    }
+
+    /// Announces the end of the task to the event feed, even if it failed, so the
+    /// subscribers don't wait for it. The callback of the requester gets the completed query.
+    pub fn notify(&self, webhooks: Option<&Webhooks>) {
+        let query = self.query.as_ref().expect("Query is not set");
+        self.env.publish(QueryEvent::Complete {
+            id: query.id,
+            requester: query.request.query.pubkey,
+        });
+        if !query.is_complete() {
+            return;
+        }
+        if let Some(webhooks) = webhooks {
+            webhooks.notify(query);
+        }
+    }
+
+    /// Returns the unspent reservation to the user. Called when the task is finished.
+    pub async fn refund(&self) {
+        let env = self.env.clone();
+        let id = *self.id();
+        let result = tokio::task::spawn_blocking(move || env.refund(id)).await;
+        match result {
+            Ok(Ok(amount)) => info!("Query {id}: {amount} refunded"),
+            Ok(Err(err)) => warn!("Failed to refund query {id}: {:?}", err),
+            Err(err) => warn!("Failed to refund query {id}: {:?}", err),
+        }
+    }
+
+    async fn record_outcomes(&self, outcomes: Vec<(PublicKey, TaskOutcome)>) {
+        if outcomes.is_empty() {
+            return;
+        }
+
+        let env = self.env.clone();
+        let result = tokio::task::spawn_blocking(move || env.record_outcomes(&outcomes)).await;
+        match result {
+            Ok(Err(err)) => warn!("Failed to store reputation: {:?}", err),
+            Err(err) => warn!("Failed to store reputation: {:?}", err),
+            Ok(Ok(())) => {}
+        }
+    }
+
     async fn store_query(&mut self) -> Result<(), OrchestratorError> {
         let env = self.env.clone();
         let query = self.query.take().expect("Query is not set");
@@ -246,24 +1001,47 @@ impl Task {
             query
         })
         .await?;
+        if self.events.receiver_count() > 0 {
+            // there may be no subscribers
+            let _ = self.events.send(StreamEvent::Query(query.clone()));
+        }
+        self.publish_transitions(&query);
         self.query = Some(query);
         Ok(())
     }
 
+    /// Publishes the node results changed since the last store to the event feed.
+    fn publish_transitions(&mut self, query: &Query) {
+        let requester = query.request.query.pubkey;
+        for result in &query.response {
+            let key = result.node_key();
+            if self.published.get(&key) == Some(result) {
+                continue;
+            }
+            self.published.insert(key, result.clone());
+            self.env.publish(QueryEvent::Transition {
+                id: query.id,
+                requester,
+                result: result.clone(),
+            });
+        }
+    }
+
     async fn select_workers(
         &mut self,
         nodes_count: usize,
     ) -> Result<Vec<PublicKey>, OrchestratorError> {
         let mut results = vec![];
 
-        for _ in 0..nodes_count {
-            if self.peer_pool.is_empty() {
-                break;
-            }
-
-            let node = self
-                .peer_pool
-                .remove(rand::thread_rng().gen_range(0..self.peer_pool.len()));
+        while results.len() < nodes_count && !self.peer_pool.is_empty() {
+            let index = WeightedIndex::new(self.peer_pool.iter().map(|(_, weight)| *weight))
+                .map(|weights| weights.sample(&mut rand::thread_rng()))
+                .unwrap_or_default();
+            let (node, _) = self.peer_pool.remove(index);
+            let Some(guard) = node.try_acquire() else {
+                debug!("Node {} is saturated, skipping", node.key);
+                continue;
+            };
             let result_rx = self
                 .env
                 .send_request(
@@ -276,15 +1054,17 @@ impl Task {
                         .clone(),
                 )
                 .await;
-            results.push((result_rx, node));
+            results.push((result_rx, node, guard));
         }
 
         let mut nodes = vec![];
-        for (result_rx, node) in results {
+        for (result_rx, node, guard) in results {
             if let Ok(rx) = result_rx {
                 if let Ok(result) = rx.await {
                     if result.is_success() {
                         nodes.push(node.key);
+                        self.sent_at.insert(node.key, Instant::now());
+                        self.in_flight.insert(node.key, guard);
                         self.used_nodes.push(node);
                     } else {
                         warn!(
@@ -301,3 +1081,104 @@ impl Task {
         Ok(nodes)
     }
 }
+
+/// Verdicts of the inspector nodes on a node response.
+struct Inspection {
+    /// Inspectors that haven't responded yet, with their slots.
+    pending: HashMap<PeerId, (PublicKey, LoadGuard)>,
+    verdicts: Vec<InspectorVerdict>,
+    /// Time the verification requests were sent, for the latency of the inspectors.
+    sent_at: Instant,
+    deadline: Instant,
+    on_result: oneshot::Sender<VerificationResponse>,
+}
+
+/// Text received from a node before its final response.
+#[derive(Default)]
+struct PartialAnswer {
+    text: String,
+    /// Chunks received ahead of the missing ones, by offset.
+    pending: BTreeMap<u64, String>,
+    /// Total length of the pending chunks.
+    pending_bytes: usize,
+}
+
+impl PartialAnswer {
+    /// Returns the chunks appended to the text, with their offsets.
+    /// Returns `None` if the chunk is too far ahead or too much is buffered already,
+    /// the chunk is dropped then.
+    fn push(&mut self, offset: u64, chunk: String) -> Option<Vec<(u64, String)>> {
+        let len = self.text.len() as u64;
+        if offset > len {
+            let window_end = len + MAX_PENDING_BYTES as u64;
+            let overflow = self.pending.len() >= MAX_PENDING_CHUNKS
+                || self.pending_bytes + chunk.len() > MAX_PENDING_BYTES
+                || offset.saturating_add(chunk.len() as u64) > window_end;
+            if overflow {
+                return None;
+            }
+        }
+        self.pending_bytes += chunk.len();
+        if let Some(replaced) = self.pending.insert(offset, chunk) {
+            self.pending_bytes -= replaced.len();
+        }
+
+        let mut appended = vec![];
+        while let Some(chunk) = self.pending.remove(&(self.text.len() as u64)) {
+            self.pending_bytes -= chunk.len();
+            if chunk.is_empty() {
+                continue;
+            }
+            appended.push((self.text.len() as u64, chunk.clone()));
+            self.text.push_str(&chunk);
+        }
+
+        let len = self.text.len() as u64;
+        let pending_bytes = &mut self.pending_bytes;
+        self.pending.retain(|offset, chunk| {
+            let keep = *offset > len;
+            if !keep {
+                *pending_bytes -= chunk.len();
+            }
+            keep
+        });
+        Some(appended)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartialAnswer, MAX_PENDING_BYTES, MAX_PENDING_CHUNKS};
+
+    #[test]
+    fn test_partial_answer_reorder() {
+        let mut partial = PartialAnswer::default();
+        assert_eq!(partial.push(5, " world".to_string()), Some(vec![]));
+        assert_eq!(
+            partial.push(0, "hello".to_string()),
+            Some(vec![(0, "hello".to_string()), (5, " world".to_string())])
+        );
+        // a repeated chunk
+        assert_eq!(partial.push(0, "hello".to_string()), Some(vec![]));
+        assert_eq!(partial.text, "hello world");
+        assert_eq!(partial.pending_bytes, 0);
+    }
+
+    #[test]
+    fn test_partial_answer_limits() {
+        let mut partial = PartialAnswer::default();
+        assert_eq!(
+            partial.push(MAX_PENDING_BYTES as u64, "a".to_string()),
+            None
+        );
+        assert_eq!(partial.push(1, "a".repeat(MAX_PENDING_BYTES)), None);
+
+        for offset in 1..=MAX_PENDING_CHUNKS as u64 {
+            assert!(partial.push(offset * 2, "a".to_string()).is_some());
+        }
+        assert_eq!(partial.push(1000, "a".to_string()), None);
+        // the missing chunk is always accepted
+        assert_eq!(partial.push(0, "ab".to_string()).unwrap().len(), 2);
+        assert_eq!(partial.pending.len(), MAX_PENDING_CHUNKS - 1);
+    }
+}
@@ -0,0 +1,142 @@
+use super::{percent, Evaluation, VerificationInput, Verifier};
+use crate::error::EvaluatorError;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use types::{ai::verification::CriterionScore, percent::Percent};
+
+/// Checks of the response text. The score is the lowest score of the checks.
+pub struct Heuristic {
+    min_length: usize,
+    check_language: bool,
+}
+
+impl Heuristic {
+    pub fn new(min_length: usize, check_language: bool) -> Self {
+        Self {
+            min_length,
+            check_language,
+        }
+    }
+}
+
+#[async_trait]
+impl Verifier for Heuristic {
+    fn name(&self) -> &'static str {
+        "heuristic"
+    }
+
+    async fn evaluate(
+        &self,
+        input: &VerificationInput,
+    ) -> Result<Option<Evaluation>, EvaluatorError> {
+        let response = input.response().trim();
+        let mut scores = vec![];
+        let mut failed = vec![];
+
+        let non_empty = if response.is_empty() {
+            failed.push("the response is empty");
+            Percent::zero()
+        } else {
+            percent(100)
+        };
+        scores.push(("non_empty", non_empty));
+
+        if self.min_length > 0 {
+            let length = response.chars().count();
+            if length < self.min_length {
+                failed.push("the response is too short");
+            }
+            scores.push(("length", percent((length * 100 / self.min_length) as u64)));
+        }
+
+        if self.check_language {
+            let matches = match (dominant_script(&input.message), dominant_script(response)) {
+                (Some(request), Some(response)) => request == response,
+                _ => true,
+            };
+            if !matches {
+                failed.push("the response language doesn't match the request");
+            }
+            scores.push(("language", percent(if matches { 100 } else { 0 })));
+        }
+
+        let score = scores
+            .iter()
+            .map(|(_, score)| score.clone())
+            .min()
+            .unwrap_or_else(Percent::zero);
+        let description = if failed.is_empty() {
+            String::new()
+        } else {
+            format!("Heuristic checks failed: {}", failed.join(", "))
+        };
+        Ok(Some(Evaluation {
+            score,
+            scores: scores
+                .into_iter()
+                .map(|(criterion, score)| CriterionScore {
+                    criterion: criterion.to_owned(),
+                    score,
+                })
+                .collect(),
+            description,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Thai,
+    Hangul,
+    /// Chinese and Japanese.
+    Cjk,
+}
+
+impl Script {
+    fn of(ch: char) -> Option<Self> {
+        let script = match ch as u32 {
+            0x0041..=0x024F => Script::Latin,
+            0x0370..=0x03FF => Script::Greek,
+            0x0400..=0x052F => Script::Cyrillic,
+            0x0590..=0x05FF => Script::Hebrew,
+            0x0600..=0x06FF | 0x0750..=0x077F => Script::Arabic,
+            0x0900..=0x097F => Script::Devanagari,
+            0x0E00..=0x0E7F => Script::Thai,
+            0x1100..=0x11FF | 0xAC00..=0xD7AF => Script::Hangul,
+            0x3040..=0x30FF | 0x4E00..=0x9FFF => Script::Cjk,
+            _ => return None,
+        };
+        ch.is_alphabetic().then_some(script)
+    }
+}
+
+/// The script most of the letters of the text are written in.
+fn dominant_script(text: &str) -> Option<Script> {
+    let mut counts = HashMap::new();
+    for script in text.chars().filter_map(Script::of) {
+        *counts.entry(script).or_insert(0usize) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(script, _)| script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dominant_script, Script};
+
+    #[test]
+    fn test_dominant_script() {
+        assert_eq!(dominant_script("Hello, world!"), Some(Script::Latin));
+        assert_eq!(dominant_script("Привет, Rust"), Some(Script::Cyrillic));
+        assert_eq!(dominant_script("東京は日本の首都です"), Some(Script::Cjk));
+        assert_eq!(dominant_script("42 + 1 = 43"), None);
+    }
+}
@@ -0,0 +1,132 @@
+use super::{percent, Evaluation, VerificationInput, Verifier};
+use crate::error::EvaluatorError;
+use ai::{
+    judge::{ask_verdict, SYSTEM_PROMPT},
+    Ai, Question, QuestionOptions,
+};
+use async_trait::async_trait;
+use eyre::{Context, Error};
+use node_config::verifier::Criterion;
+use std::{collections::HashMap, path::Path, sync::Arc};
+use types::{
+    ai::{
+        request::{History, Role},
+        verification::CriterionScore,
+    },
+    percent::Percent,
+};
+
+const RUBRIC_PROMPT: &str = "Additionally, score the response by each of the following criteria with a number from 0 to 100 and return the numbers in the 'scores' field, an object mapping the criterion names to the scores:";
+
+/// The orchestrator's model grades the response.
+pub struct LlmJudge<A> {
+    ai: Arc<A>,
+    prompt: String,
+    rubric: Vec<Criterion>,
+    /// Names of the rubric criteria.
+    criteria: Vec<String>,
+}
+
+impl<A> LlmJudge<A> {
+    pub fn new(ai: Arc<A>, prompt: Option<&Path>, rubric: Vec<Criterion>) -> Result<Self, Error> {
+        let mut prompt = match prompt {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read judge prompt {path:?}"))?,
+            None => SYSTEM_PROMPT.to_owned(),
+        };
+        if !rubric.is_empty() {
+            prompt.push('\n');
+            prompt.push_str(RUBRIC_PROMPT);
+            for criterion in &rubric {
+                prompt.push_str(&format!(
+                    "\n'{}' — {}",
+                    criterion.name, criterion.description
+                ));
+            }
+        }
+
+        let criteria = rubric
+            .iter()
+            .map(|criterion| criterion.name.clone())
+            .collect();
+        Ok(Self {
+            ai,
+            prompt,
+            rubric,
+            criteria,
+        })
+    }
+
+    /// Weighted average of the rubric scores.
+    fn score_rubric(
+        &self,
+        mut scores: HashMap<String, u8>,
+    ) -> Result<(Percent, Vec<CriterionScore>), EvaluatorError> {
+        let mut total = 0u64;
+        let mut weighted = 0u64;
+        let mut criteria = vec![];
+        for criterion in &self.rubric {
+            let score = scores
+                .remove(&criterion.name)
+                .ok_or_else(|| EvaluatorError::MissingCriterion(criterion.name.clone()))?;
+            let score = Percent::try_from(score).map_err(EvaluatorError::InvalidRelevance)?;
+            total += criterion.weight as u64;
+            weighted += criterion.weight as u64 * score.inner() as u64;
+            criteria.push(CriterionScore {
+                criterion: criterion.name.clone(),
+                score,
+            });
+        }
+
+        let score = (weighted + total / 2)
+            .checked_div(total)
+            .map_or_else(Percent::zero, percent);
+        Ok((score, criteria))
+    }
+}
+
+#[async_trait]
+impl<A: Ai + Send + Sync + 'static> Verifier for LlmJudge<A> {
+    fn name(&self) -> &'static str {
+        "llm_judge"
+    }
+
+    async fn evaluate(
+        &self,
+        input: &VerificationInput,
+    ) -> Result<Option<Evaluation>, EvaluatorError> {
+        let question = Question {
+            message: input.question.clone(),
+            history: vec![History {
+                content: self.prompt.clone(),
+                role: Role::System,
+            }],
+            options: QuestionOptions {
+                seed: input.seed,
+                ..Default::default()
+            },
+        };
+
+        let answer = ask_verdict(self.ai.as_ref(), question, &self.criteria).await?;
+        let (score, scores) = if self.rubric.is_empty() {
+            let relevance = answer
+                .relevance
+                .ok_or(EvaluatorError::InvalidJson("No relevance found"))?;
+            let relevance =
+                Percent::try_from(relevance).map_err(EvaluatorError::InvalidRelevance)?;
+            let scores = vec![CriterionScore {
+                criterion: "relevance".to_owned(),
+                score: relevance.clone(),
+            }];
+            (relevance, scores)
+        } else {
+            self.score_rubric(answer.scores)?
+        };
+
+        Ok(Some(Evaluation {
+            score,
+            scores,
+            description: answer.description,
+        }))
+    }
+}
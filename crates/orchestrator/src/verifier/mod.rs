@@ -0,0 +1,311 @@
+mod heuristic;
+mod llm;
+mod reference;
+
+use crate::error::EvaluatorError;
+use ai::Ai;
+use async_trait::async_trait;
+use crypto::ed25519::{private::PrivateKey, public::PublicKey};
+use eyre::{bail, Error};
+use heuristic::Heuristic;
+use llm::LlmJudge;
+use node_config::verifier::{EvaluatorKind, VerifierConfig};
+use reference::Reference;
+use std::sync::Arc;
+use tokio::{
+    select,
+    sync::{mpsc::Receiver, oneshot::Sender},
+};
+use tracing::{error, warn};
+use types::{
+    ai::{
+        query::{NodeResult, Query},
+        response::SignedAiResponse,
+        verification::{
+            CriterionScore, InspectorVerdict, SignedVerificationResult, VerificationResult,
+        },
+    },
+    percent::Percent,
+};
+
+/// Scores a node response by some criteria.
+#[async_trait]
+pub trait Verifier: Send + Sync {
+    /// Name of the verifier the scores of its criteria are prefixed with.
+    fn name(&self) -> &'static str;
+
+    /// Returns `None` if the verifier can't judge the response.
+    async fn evaluate(
+        &self,
+        input: &VerificationInput,
+    ) -> Result<Option<Evaluation>, EvaluatorError>;
+}
+
+pub struct Evaluation {
+    pub score: Percent,
+    pub scores: Vec<CriterionScore>,
+    pub description: String,
+}
+
+/// Verifiers with their weights.
+pub struct Pipeline {
+    verifiers: Vec<(Box<dyn Verifier>, u32)>,
+}
+
+impl Pipeline {
+    pub fn new<A: Ai + Send + Sync + 'static>(
+        cfg: &VerifierConfig,
+        ai: Arc<A>,
+    ) -> Result<Self, Error> {
+        cfg.validate()?;
+
+        let verifiers = cfg
+            .evaluators
+            .iter()
+            .map(|evaluator| {
+                let verifier: Box<dyn Verifier> = match &evaluator.kind {
+                    EvaluatorKind::LlmJudge { prompt, rubric } => Box::new(LlmJudge::new(
+                        ai.clone(),
+                        prompt.as_deref(),
+                        rubric.clone(),
+                    )?),
+                    EvaluatorKind::Heuristic {
+                        min_length,
+                        check_language,
+                    } => Box::new(Heuristic::new(*min_length, *check_language)),
+                    EvaluatorKind::Reference { answers } => Box::new(Reference::load(answers)?),
+                };
+                Ok((verifier, evaluator.weight))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { verifiers })
+    }
+
+    /// Combines the scores of the verifiers into the weighted average.
+    /// The failed verifiers are skipped, the error is returned only if none of them scored.
+    pub async fn evaluate(&self, input: &VerificationInput) -> Result<Evaluation, EvaluatorError> {
+        let mut failure = None;
+        let mut total = 0u64;
+        let mut weighted = 0u64;
+        let mut scores = vec![];
+        let mut descriptions = vec![];
+        for (verifier, weight) in &self.verifiers {
+            let evaluation = match verifier.evaluate(input).await {
+                Ok(Some(evaluation)) => evaluation,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!("The {} evaluator failed: {}", verifier.name(), err);
+                    failure = Some(err);
+                    continue;
+                }
+            };
+            total += *weight as u64;
+            weighted += *weight as u64 * evaluation.score.inner() as u64;
+            scores.extend(evaluation.scores.into_iter().map(|score| CriterionScore {
+                criterion: format!("{}.{}", verifier.name(), score.criterion),
+                score: score.score,
+            }));
+            if !evaluation.description.is_empty() {
+                descriptions.push(evaluation.description);
+            }
+        }
+        if total == 0 {
+            return Err(failure.unwrap_or(EvaluatorError::NoEvaluation));
+        }
+
+        Ok(Evaluation {
+            score: percent((weighted + total / 2) / total),
+            scores,
+            description: descriptions.join("\n"),
+        })
+    }
+}
+
+/// The median is not affected by a minority of dishonest inspectors.
+fn median_relevance(verdicts: &[InspectorVerdict]) -> Percent {
+    let mut relevance = verdicts
+        .iter()
+        .map(|verdict| verdict.relevance.clone())
+        .collect::<Vec<_>>();
+    relevance.sort();
+    relevance
+        .get(relevance.len() / 2)
+        .cloned()
+        .unwrap_or_else(Percent::zero)
+}
+
+/// Clamps the score to 100.
+fn percent(score: u64) -> Percent {
+    Percent::try_from(score.min(100) as u8).expect("Never")
+}
+
+pub struct VerifierTask {
+    key: PrivateKey,
+    pipeline: Arc<Pipeline>,
+    receiver: Receiver<VerificationRequest>,
+}
+
+impl VerifierTask {
+    pub fn new(
+        key: PrivateKey,
+        pipeline: Pipeline,
+        receiver: Receiver<VerificationRequest>,
+    ) -> Self {
+        Self {
+            key,
+            pipeline: Arc::new(pipeline),
+            receiver,
+        }
+    }
+
+    async fn handle_request(&self, request: VerificationRequest) -> Result<(), Error> {
+        let pipeline = self.pipeline.clone();
+        let key = self.key.clone();
+
+        tokio::spawn(async move {
+            let material = request.input.node_response.clone();
+            let result = if request.verdicts.is_empty() {
+                match pipeline.evaluate(&request.input).await {
+                    Ok(evaluation) => VerificationResult {
+                        material,
+                        inspector: key.public_key(),
+                        relevance: evaluation.score,
+                        description: evaluation.description,
+                        scores: evaluation.scores,
+                        verdicts: vec![],
+                    },
+                    Err(err) => {
+                        warn!("Failed to evaluate AI response: {}", err);
+                        let response = VerificationResponse {
+                            node_key: material.node_key(),
+                            verification_result: Err(err),
+                        };
+                        if request.on_result.send(response).is_err() {
+                            warn!("Failed to send verification result: task is closed");
+                        }
+                        return;
+                    }
+                }
+            } else {
+                VerificationResult {
+                    material,
+                    inspector: key.public_key(),
+                    relevance: median_relevance(&request.verdicts),
+                    description: format!("Verified by {} inspectors", request.verdicts.len()),
+                    scores: vec![],
+                    verdicts: request.verdicts,
+                }
+            };
+
+            match result.sign(&key) {
+                Ok(verification_result) => {
+                    let response = VerificationResponse {
+                        node_key: verification_result.result.material.node_key(),
+                        verification_result: Ok(verification_result),
+                    };
+                    if request.on_result.send(response).is_err() {
+                        warn!("Failed to send verification result: task is closed");
+                    }
+                }
+                Err((_, err)) => warn!("Failed to sign verification result: {}", err),
+            }
+        });
+
+        Ok(())
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            select! {
+                Some(request) = self.receiver.recv() => {
+                    if let Err(err) = self.handle_request(request).await {
+                        error!("Error handling request: {}", err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The node response with the request it answers.
+pub struct VerificationInput {
+    pub seed: i32,
+    /// The conversation and the response prepared for the model.
+    pub question: String,
+    /// The user request.
+    pub message: String,
+    pub node_response: SignedAiResponse,
+}
+
+impl VerificationInput {
+    pub fn response(&self) -> &str {
+        &self.node_response.node_response.response
+    }
+}
+
+pub struct VerificationRequest {
+    pub input: VerificationInput,
+    /// Verdicts of the nodes. The configured evaluators are used if empty.
+    pub verdicts: Vec<InspectorVerdict>,
+    pub on_result: Sender<VerificationResponse>,
+}
+
+impl VerificationRequest {
+    pub fn new(
+        query: &Query,
+        node_key: PublicKey,
+        on_result: Sender<VerificationResponse>,
+    ) -> Result<Self, Error> {
+        let node_response = query
+            .response
+            .iter()
+            .find(|response| response.node_key() == node_key)
+            .ok_or_else(|| eyre::eyre!("Node response not found"))?
+            .clone();
+        let node_response = if let NodeResult::NodeResponse(node_result) = node_response {
+            node_result
+        } else {
+            bail!("Node response not found");
+        };
+
+        Ok(Self {
+            input: VerificationInput {
+                question: Self::prepare_question(query, node_key)?,
+                seed: query.request.query.seed,
+                message: query.request.query.message.clone(),
+                node_response,
+            },
+            verdicts: vec![],
+            on_result,
+        })
+    }
+
+    /// The result is combined from the verdicts of the inspectors.
+    pub fn with_verdicts(mut self, verdicts: Vec<InspectorVerdict>) -> Self {
+        self.verdicts = verdicts;
+        self
+    }
+
+    fn prepare_question(query: &Query, node_key: PublicKey) -> Result<String, Error> {
+        let node_response = query
+            .response
+            .iter()
+            .find(|response| response.node_key() == node_key);
+
+        if let Some(NodeResult::NodeResponse(resp)) = node_response {
+            Ok(ai::judge::question(
+                &query.id,
+                &query.request.query,
+                &resp.node_response.response,
+            ))
+        } else {
+            bail!("Node response not found");
+        }
+    }
+}
+
+pub struct VerificationResponse {
+    pub node_key: PublicKey,
+    /// The error is of the orchestrator's evaluators, not of the node.
+    pub verification_result: Result<SignedVerificationResult, EvaluatorError>,
+}
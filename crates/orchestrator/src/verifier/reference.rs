@@ -0,0 +1,95 @@
+use super::{percent, Evaluation, VerificationInput, Verifier};
+use crate::error::EvaluatorError;
+use async_trait::async_trait;
+use eyre::{Context, Error};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+use types::ai::verification::CriterionScore;
+
+/// Compares the response with the known answer to the request.
+/// The requests without a known answer are not judged.
+pub struct Reference {
+    answers: HashMap<String, String>,
+}
+
+impl Reference {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let answers = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read reference answers {path:?}"))?;
+        let answers: HashMap<String, String> = serde_json::from_str(&answers)
+            .with_context(|| format!("Failed to parse reference answers {path:?}"))?;
+        Ok(Self {
+            answers: answers
+                .into_iter()
+                .map(|(request, answer)| (request.trim().to_owned(), answer))
+                .collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl Verifier for Reference {
+    fn name(&self) -> &'static str {
+        "reference"
+    }
+
+    async fn evaluate(
+        &self,
+        input: &VerificationInput,
+    ) -> Result<Option<Evaluation>, EvaluatorError> {
+        let Some(reference) = self.answers.get(input.message.trim()) else {
+            return Ok(None);
+        };
+
+        let score = percent((similarity(reference, input.response()) * 100.0).round() as u64);
+        Ok(Some(Evaluation {
+            scores: vec![CriterionScore {
+                criterion: "similarity".to_owned(),
+                score: score.clone(),
+            }],
+            description: format!("Similarity to the reference answer: {score}%"),
+            score,
+        }))
+    }
+}
+
+/// F1 score of the words of the response against the words of the reference.
+fn similarity(reference: &str, response: &str) -> f64 {
+    let reference = words(reference);
+    let response = words(response);
+    if reference.is_empty() || response.is_empty() {
+        return 0.0;
+    }
+
+    let common = reference.intersection(&response).count() as f64;
+    let precision = common / response.len() as f64;
+    let recall = common / reference.len() as f64;
+    if common == 0.0 {
+        0.0
+    } else {
+        2.0 * precision * recall / (precision + recall)
+    }
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::similarity;
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Paris", "paris."), 1.0);
+        assert_eq!(similarity("Paris", "London"), 0.0);
+        assert_eq!(similarity("", "Paris"), 0.0);
+        let score = similarity("The capital is Paris", "Paris is the capital of France");
+        assert!((score - 0.8).abs() < 1e-9);
+    }
+}
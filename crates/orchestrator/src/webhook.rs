@@ -0,0 +1,346 @@
+use crate::store::queries::Queries;
+use backon::{ExponentialBuilder, Retryable};
+use crypto::ed25519::private::PrivateKey;
+use eyre::{Context, Error};
+use node_config::tasks::WebhookConfig;
+use p2p::sys::now_secs;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::CONTENT_TYPE,
+    redirect::Policy,
+    StatusCode, Url,
+};
+use std::{
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tracing::{info, warn};
+use types::ai::{
+    query::{Query, QueryId},
+    webhook::{WebhookDelivery, QUERY_HEADER, SIGNATURE_HEADER},
+};
+
+/// Posts the completed queries to the callback URLs of the requesters.
+/// The body is signed by the orchestrator key.
+///
+/// The queries waiting for the delivery are stored, so the deliveries interrupted
+/// by a restart are resumed by [`Webhooks::resume`].
+#[derive(Clone)]
+pub struct Webhooks {
+    client: reqwest::Client,
+    key: PrivateKey,
+    cfg: WebhookConfig,
+    queries: Queries,
+    /// Queries being delivered by this run.
+    active: Arc<Mutex<HashSet<QueryId>>>,
+}
+
+impl Webhooks {
+    pub fn new(key: PrivateKey, cfg: &WebhookConfig, queries: Queries) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(cfg.timeout_secs))
+            // a redirect could lead to a host that is not allowed
+            .redirect(Policy::none())
+            .dns_resolver(Arc::new(PublicResolver {
+                allowed_hosts: cfg.allowed_hosts.clone(),
+            }))
+            .build()
+            .context("Failed to create webhook client")?;
+        Ok(Self {
+            client,
+            key,
+            cfg: cfg.clone(),
+            queries,
+            active: Arc::default(),
+        })
+    }
+
+    /// Delivers the query in the background if the requester set a callback URL.
+    pub fn notify(&self, query: &Query) {
+        let Some(url) = query.request.query.options.callback.clone() else {
+            return;
+        };
+        let webhooks = self.clone();
+        let query = query.clone();
+        tokio::spawn(async move {
+            let queries = webhooks.queries.clone();
+            let (id, pending_url) = (query.id, url.clone());
+            let result = tokio::task::spawn_blocking(move || {
+                queries.add_pending_delivery(&id, &pending_url)
+            })
+            .await;
+            match result {
+                Ok(Err(err)) => warn!("Failed to store pending delivery: {:?}", err),
+                Err(err) => warn!("Failed to store pending delivery: {:?}", err),
+                Ok(Ok(())) => {}
+            }
+            webhooks.deliver(url, query, 0).await
+        });
+    }
+
+    /// Continues the deliveries interrupted by the previous run.
+    pub async fn resume(&self) {
+        let queries = self.queries.clone();
+        let pending = match tokio::task::spawn_blocking(move || queries.pending_deliveries()).await
+        {
+            Ok(Ok(pending)) => pending,
+            Ok(Err(err)) => return warn!("Failed to load pending deliveries: {:?}", err),
+            Err(err) => return warn!("Failed to load pending deliveries: {:?}", err),
+        };
+        if !pending.is_empty() {
+            info!("Resuming {} webhook deliveries", pending.len());
+        }
+        for (query, url, attempts) in pending {
+            let webhooks = self.clone();
+            tokio::spawn(async move { webhooks.deliver(url, query, attempts).await });
+        }
+    }
+
+    /// `made` is the number of the attempts made before.
+    async fn deliver(&self, url: String, query: Query, made: u32) {
+        if !self.active.lock().unwrap().insert(query.id) {
+            // started by this run before the resume
+            return;
+        }
+        let result = self.try_deliver(&url, &query, made).await;
+        match result {
+            Ok(()) => info!("Query {} is delivered to the callback", query.id),
+            Err(err) => warn!("Failed to deliver query {}: {err}", query.id),
+        }
+
+        let queries = self.queries.clone();
+        let id = query.id;
+        let result = tokio::task::spawn_blocking(move || queries.delivered(&id)).await;
+        match result {
+            Ok(Err(err)) => warn!("Failed to remove pending delivery: {:?}", err),
+            Err(err) => warn!("Failed to remove pending delivery: {:?}", err),
+            Ok(Ok(())) => {}
+        }
+        self.active.lock().unwrap().remove(&id);
+    }
+
+    async fn try_deliver(&self, url: &str, query: &Query, made: u32) -> Result<(), String> {
+        let remaining = u32::try_from(self.cfg.retries)
+            .unwrap_or(u32::MAX)
+            .saturating_add(1)
+            .saturating_sub(made);
+        if remaining == 0 {
+            return Err("No attempts left".to_string());
+        }
+        // the allowed hosts may have changed since the request
+        check_callback(&self.cfg, url).map_err(str::to_string)?;
+        let body =
+            serde_json::to_vec(query).map_err(|err| format!("Failed to serialize query: {err}"))?;
+        let signature = self.key.sign(&body).to_hex();
+
+        let attempts = AtomicU32::new(made);
+        (|| async {
+            let attempt = attempts.fetch_add(1, Ordering::AcqRel) + 1;
+            let (status, error) = match self.post(url, query, &body, &signature).await {
+                Ok(status) if status.is_success() => (Some(status.as_u16()), None),
+                Ok(status) => (
+                    Some(status.as_u16()),
+                    Some(format!("Callback responded with {status}")),
+                ),
+                Err(err) => (None, Some(err.to_string())),
+            };
+            self.log(
+                query,
+                WebhookDelivery {
+                    attempt,
+                    timestamp: now_secs(),
+                    status,
+                    error: error.clone(),
+                },
+            )
+            .await;
+            error.map_or(Ok(()), Err)
+        })
+        .retry(
+            ExponentialBuilder::default()
+                .with_min_delay(Duration::from_millis(self.cfg.min_delay_ms))
+                .with_max_times(remaining as usize - 1),
+        )
+        .notify(|err, delay| {
+            warn!(
+                "Webhook of the query {} failed, retry in {delay:?}: {err}",
+                query.id
+            );
+        })
+        .await
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        query: &Query,
+        body: &[u8],
+        signature: &str,
+    ) -> Result<StatusCode, reqwest::Error> {
+        let response = self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(QUERY_HEADER, query.id.to_hex())
+            .header(SIGNATURE_HEADER, signature)
+            .body(body.to_vec())
+            .send()
+            .await?;
+        Ok(response.status())
+    }
+
+    async fn log(&self, query: &Query, delivery: WebhookDelivery) {
+        let queries = self.queries.clone();
+        let id = query.id;
+        let result = tokio::task::spawn_blocking(move || queries.log_delivery(&id, delivery)).await;
+        match result {
+            Ok(Err(err)) => warn!("Failed to log webhook delivery: {:?}", err),
+            Err(err) => warn!("Failed to log webhook delivery: {:?}", err),
+            Ok(Ok(())) => {}
+        }
+    }
+}
+
+/// Checks the callback URL requested by the user. The orchestrator must not be
+/// used to reach its internal network, so the hosts out of `allowed_hosts` can't be
+/// loopback, private or link-local addresses. The names are checked once resolved.
+pub(crate) fn check_callback(cfg: &WebhookConfig, callback: &str) -> Result<(), &'static str> {
+    let url = Url::parse(callback).map_err(|_| "invalid callback URL")?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("invalid callback URL");
+    }
+    let host = url
+        .host_str()
+        .ok_or("invalid callback URL")?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    if is_allowed(&cfg.allowed_hosts, host) {
+        return Ok(());
+    }
+    let public = match host.parse::<IpAddr>() {
+        Ok(ip) => is_public(ip),
+        Err(_) => {
+            let host = host.to_ascii_lowercase();
+            host != "localhost" && !host.ends_with(".localhost")
+        }
+    };
+    if !public {
+        return Err("callback host is not public");
+    }
+    Ok(())
+}
+
+fn is_allowed(allowed_hosts: &[String], host: &str) -> bool {
+    allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host))
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            // "this network", the shared address space of the carrier-grade NAT,
+            // the benchmarking networks and the reserved 240.0.0.0/4
+            let reserved = first == 0
+                || (first == 100 && second & 0xc0 == 64)
+                || (first == 198 && second & 0xfe == 18)
+                || first >= 240;
+            !(reserved
+                || ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast())
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(ip.into());
+            }
+            let segments = ip.segments();
+            // the NAT64 prefix 64:ff9b::/96 reaches the embedded IPv4 address
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., a, b, c, d] = ip.octets();
+                return is_public(Ipv4Addr::new(a, b, c, d).into());
+            }
+            // the unique local fc00::/7, the link local fe80::/10 and the documentation 2001:db8::/32
+            let reserved = segments[0] & 0xfe00 == 0xfc00
+                || segments[0] & 0xffc0 == 0xfe80
+                || segments[..2] == [0x2001, 0xdb8];
+            !(reserved || ip.is_loopback() || ip.is_unspecified() || ip.is_multicast())
+        }
+    }
+}
+
+/// Resolves the names of the callbacks to the public addresses only,
+/// so a public name can't point to the internal network.
+struct PublicResolver {
+    allowed_hosts: Vec<String>,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allowed = is_allowed(&self.allowed_hosts, name.as_str());
+        let host = name.as_str().to_owned();
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| allowed || is_public(addr.ip()))
+                .collect::<Vec<_>>();
+            if addrs.is_empty() {
+                return Err(format!("{host} has no public addresses").into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_callback;
+    use node_config::tasks::WebhookConfig;
+
+    #[test]
+    fn test_check_callback() {
+        let mut cfg = WebhookConfig::default();
+        for public in [
+            "https://example.com/hook",
+            "http://8.8.8.8:8080/hook",
+            "http://[2606:4700::1111]/hook",
+            "http://[64:ff9b::808:808]/hook",
+        ] {
+            assert!(check_callback(&cfg, public).is_ok(), "{public}");
+        }
+        for internal in [
+            "ftp://example.com/hook",
+            "http://localhost/hook",
+            "http://127.0.0.1:8080/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.1/hook",
+            "http://100.64.0.1/hook",
+            "http://0.0.0.0/hook",
+            "http://198.18.0.1/hook",
+            "http://240.0.0.1/hook",
+            "http://255.255.255.255/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:192.168.0.1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[2001:db8::1]/hook",
+            "http://[64:ff9b::7f00:1]/hook",
+        ] {
+            assert!(check_callback(&cfg, internal).is_err(), "{internal}");
+        }
+
+        cfg.allowed_hosts = vec!["127.0.0.1".to_owned(), "::1".to_owned()];
+        assert!(check_callback(&cfg, "http://127.0.0.1:8080/hook").is_ok());
+        assert!(check_callback(&cfg, "http://[::1]/hook").is_ok());
+    }
+}
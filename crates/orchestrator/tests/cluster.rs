@@ -0,0 +1,268 @@
+use ai::mock::Mock;
+use crypto::ed25519::private::PrivateKey;
+use multiaddr::Multiaddr;
+use node::spawn_node;
+use node_config::{llm::MockConfig, tasks::AiTasksConfig};
+use orchestrator::{spawn_orchestrator, ApiSender, OrchRequest, OrchestratorHandles};
+use std::{path::Path, sync::Arc, time::Duration};
+use storage::EveStorage;
+use tempdir::TempDir;
+use tokio::sync::oneshot;
+use types::{
+    ai::{
+        aggregation::AggregationStrategy,
+        query::{NodeResult, Query, QueryId},
+        request::AiRequest,
+    },
+    p2p::Peer,
+};
+
+const LISTENING_ADDRESS: &str = "/ip4/127.0.0.1/udp/0/quic-v1";
+
+/// Orchestrator and nodes connected over p2p, answering with the mock backend.
+struct Cluster {
+    storage: Arc<EveStorage>,
+    api: ApiSender,
+    _orch: (OrchestratorHandles, p2p::P2PHandler),
+    _nodes: Vec<(node::NodeHandler, p2p::P2PHandler)>,
+}
+
+impl Cluster {
+    /// Starts a node for each of the mock configurations.
+    async fn start(path: &Path, nodes: &[MockConfig]) -> Self {
+        Self::start_with(
+            path,
+            AggregationStrategy::default(),
+            &Default::default(),
+            nodes,
+        )
+        .await
+    }
+
+    /// Starts the orchestrator judging and aggregating with the `judge` mock.
+    async fn start_with(
+        path: &Path,
+        aggregation: AggregationStrategy,
+        judge: &MockConfig,
+        nodes: &[MockConfig],
+    ) -> Self {
+        let storage = Arc::new(EveStorage::new(path, &Default::default()).unwrap());
+        let orch_key = PrivateKey::generate();
+        let node_keys = nodes
+            .iter()
+            .map(|_| PrivateKey::generate())
+            .collect::<Vec<_>>();
+
+        let (orch_p2p, from_p2p, to_p2p) = p2p::spawn(
+            orch_key.clone(),
+            orch_key.public_key(),
+            vec![],
+            &[LISTENING_ADDRESS.parse().unwrap()],
+            p2p::Config::default(),
+        )
+        .await
+        .unwrap();
+        let (api, api_rx) = tokio::sync::mpsc::channel(100);
+        let cfg = AiTasksConfig {
+            whitelist: node_keys
+                .iter()
+                .map(|key| Peer {
+                    public_key: key.public_key(),
+                    address: None,
+                })
+                .collect(),
+            replication_factor: nodes.len() as u64,
+            task_timeout_secs: 5,
+            aggregation,
+            ..Default::default()
+        };
+        let orch = spawn_orchestrator(
+            storage.clone(),
+            api_rx,
+            (to_p2p, from_p2p),
+            Arc::new(Mock::new(judge)),
+            orch_key.clone(),
+            &cfg,
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        let orch_address = orch_address(&api).await;
+
+        let mut handlers = Vec::new();
+        for (key, config) in node_keys.into_iter().zip(nodes) {
+            let (node_p2p, from_p2p, to_p2p) = p2p::spawn(
+                key.clone(),
+                orch_key.public_key(),
+                vec![],
+                &[LISTENING_ADDRESS.parse().unwrap()],
+                p2p::Config::default(),
+            )
+            .await
+            .unwrap();
+            let node = spawn_node(
+                to_p2p,
+                from_p2p,
+                Arc::new(Mock::new(config)),
+                orch_key.public_key(),
+                key,
+                orch_address.clone(),
+                config.capabilities(),
+            )
+            .await
+            .unwrap();
+            handlers.push((node, node_p2p));
+        }
+
+        let cluster = Self {
+            storage,
+            api,
+            _orch: (orch, orch_p2p),
+            _nodes: handlers,
+        };
+        cluster.wait_connected(nodes.len()).await;
+        cluster
+    }
+
+    async fn wait_connected(&self, count: usize) {
+        tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                let (tx, rx) = oneshot::channel();
+                self.api
+                    .send(OrchRequest::ClusterInfo { tx })
+                    .await
+                    .unwrap();
+                let info = rx.await.unwrap();
+                if info
+                    .nodes
+                    .values()
+                    .filter(|node| node.is_connected())
+                    .count()
+                    == count
+                {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("nodes are not connected");
+    }
+
+    async fn ask(&self, user: &PrivateKey, message: &str) -> QueryId {
+        let (tx, rx) = oneshot::channel();
+        self.api
+            .send(OrchRequest::Airdrop {
+                address: user.public_key(),
+                amount: 100_000,
+                tx,
+            })
+            .await
+            .unwrap();
+        rx.await.unwrap().unwrap();
+
+        let request = AiRequest::new(message.to_string(), vec![], user.public_key())
+            .sign(user)
+            .unwrap()
+            .verify()
+            .unwrap();
+        let (tx, rx) = oneshot::channel();
+        self.api
+            .send(OrchRequest::Ask { request, tx })
+            .await
+            .unwrap();
+        rx.await.unwrap().unwrap()
+    }
+
+    async fn wait_query(&self, id: QueryId, until: impl Fn(&Query) -> bool) -> Query {
+        tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                let query = self.storage.query_table.get_query(&id).unwrap();
+                if let Some(query) = query.filter(&until) {
+                    return query;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("query is not updated")
+    }
+}
+
+async fn orch_address(api: &ApiSender) -> Multiaddr {
+    loop {
+        let (tx, rx) = oneshot::channel();
+        api.send(OrchRequest::ClusterInfo { tx }).await.unwrap();
+        let info = rx.await.unwrap();
+        if let Some(address) = info.cluster_info.find_quic() {
+            return address.clone();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cluster() {
+    let tmp = TempDir::new("cluster").unwrap();
+    let echo = MockConfig {
+        min_latency_millis: 10,
+        max_latency_millis: 50,
+        ..Default::default()
+    };
+    let failing = MockConfig {
+        failure_percent: 100,
+        ..Default::default()
+    };
+    let cluster = Cluster::start(tmp.path(), &[echo.clone(), echo, failing]).await;
+    let user = PrivateKey::generate();
+
+    let id = cluster.ask(&user, "hello").await;
+    // the answer is stored after the query is complete
+    let query = cluster.wait_query(id, |query| query.answer.is_some()).await;
+
+    assert_eq!(query.response.len(), 3);
+    let verified = query
+        .response
+        .iter()
+        .filter(|result| result.is_verified())
+        .count();
+    assert_eq!(verified, 2);
+    assert!(query
+        .response
+        .iter()
+        .any(|result| matches!(result, NodeResult::Error(..))));
+    assert_eq!(query.answer.unwrap().content, "hello");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cluster_pairwise() {
+    let tmp = TempDir::new("cluster").unwrap();
+    // both responses are judged equally, the second one wins the comparison
+    let judge = MockConfig {
+        verdicts: [
+            r#"{"relevance": 90, "description": "ok"}"#,
+            r#"{"relevance": 90, "description": "ok"}"#,
+            r#"{"better": 2, "description": "more complete"}"#,
+        ]
+        .map(String::from)
+        .to_vec(),
+        ..Default::default()
+    };
+    let echo = MockConfig::default();
+    let cluster = Cluster::start_with(
+        tmp.path(),
+        AggregationStrategy::Pairwise,
+        &judge,
+        &[echo.clone(), echo],
+    )
+    .await;
+    let user = PrivateKey::generate();
+
+    let id = cluster.ask(&user, "hello").await;
+    let query = cluster.wait_query(id, |query| query.answer.is_some()).await;
+
+    assert!(query.response.iter().all(NodeResult::is_verified));
+    let answer = query.answer.unwrap();
+    assert_eq!(answer.strategy, AggregationStrategy::Pairwise);
+    assert_eq!(answer.node, query.response[1].node_key());
+}
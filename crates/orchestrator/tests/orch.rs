@@ -1 +1,1130 @@
+use crypto::ed25519::{private::PrivateKey, signature::Signature};
+use node_config::{
+    tasks::{
+        BillingConfig, CrossVerificationConfig, HedgeConfig, RecoveryPolicy, RequestLimits,
+        WebhookConfig,
+    },
+    verifier::{EvaluatorConfig, EvaluatorKind, VerifierConfig},
+};
+use orchestrator::OrchestratorError;
+use p2p::key::ToP2P as _;
+use poem::{
+    handler, http::StatusCode, listener::TcpAcceptor, post, web::Data, EndpointExt as _, Request,
+    Route, Server,
+};
+use rt::{capabilities, config, runtime, AiMock, Orch};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tempdir::TempDir;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use types::{
+    ai::{
+        aggregation::AggregationStrategy,
+        query::{NodeResult, Query},
+        request::AiRequestOptions,
+        stream::QueryEvent,
+        verification::VerificationResult,
+        webhook::SIGNATURE_HEADER,
+    },
+    cluster::{Capabilities, LoadReport},
+    p2p::Peer,
+};
 
+mod rt;
+
+/// Starts a query and kills the orchestrator when the request is sent to the node.
+/// If `respond` is set, the node responds, but the verification never completes.
+fn interrupted_query(path: &std::path::Path, node: &PrivateKey, respond: bool) -> Query {
+    let user = PrivateKey::generate();
+    let cfg = config(node, RecoveryPolicy::Resume);
+
+    // dropping the runtime kills all tasks of the orchestrator
+    runtime().block_on(async {
+        let mut orch = Orch::start(path, &cfg, AiMock::new(Duration::from_secs(3600))).await;
+        orch.connect(node).await;
+        orch.airdrop(&user, 10_000).await;
+        let id = orch.ask(&user, "hello").await;
+        let (sent_id, request) = orch.next_request().await;
+        let id = id.await.unwrap().unwrap();
+        assert_eq!(sent_id, id);
+
+        if respond {
+            orch.respond(node, id, &request, 0).await;
+            orch.wait_query(id, |query| query.response[0].is_node_response())
+                .await
+        } else {
+            orch.wait_query(id, |query| query.response[0].is_sent_request())
+                .await
+        }
+    })
+}
+
+#[test]
+fn test_recovery_resume() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let query = interrupted_query(tmp.path(), &node, false);
+
+    runtime().block_on(async {
+        let cfg = config(&node, RecoveryPolicy::Resume);
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        orch.connect(&node).await;
+
+        let (id, request) = orch.next_request().await;
+        assert_eq!(id, query.id);
+        assert_eq!(request, query.request);
+        orch.respond(&node, id, &request, 0).await;
+
+        let query = orch.wait_query(id, Query::is_complete).await;
+        assert_eq!(query.response.len(), 1);
+        assert!(query.response[0].is_verified());
+        let answer = query.answer.unwrap();
+        assert_eq!(answer.node, node.public_key());
+        assert_eq!(answer.content, "ai:hello");
+        assert!(orch
+            .storage
+            .query_table
+            .get_in_progress_ids(10, 0)
+            .unwrap()
+            .is_empty());
+    });
+}
+
+#[test]
+fn test_recovery_verification() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let query = interrupted_query(tmp.path(), &node, true);
+
+    runtime().block_on(async {
+        let cfg = config(&node, RecoveryPolicy::Resume);
+        let orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+
+        // the response is verified again without sending the request to the node
+        let query = orch.wait_query(query.id, Query::is_complete).await;
+        assert_eq!(query.response.len(), 1);
+        assert!(query.response[0].is_verified());
+        assert_eq!(query.answer.unwrap().node, node.public_key());
+    });
+}
+
+#[test]
+fn test_recovery_timeout() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let query = interrupted_query(tmp.path(), &node, false);
+
+    runtime().block_on(async {
+        let cfg = config(&node, RecoveryPolicy::Timeout);
+        let orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+
+        let query = orch.wait_query(query.id, Query::is_complete).await;
+        assert_eq!(
+            query.response,
+            vec![NodeResult::Timeout(Box::new(NodeResult::SentRequest(
+                node.public_key()
+            )))]
+        );
+        assert!(query.answer.is_none());
+    });
+}
+
+#[test]
+fn test_escrow() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&node, RecoveryPolicy::Resume);
+        cfg.billing = BillingConfig {
+            price: 2,
+            max_tokens: 1_000,
+            min_relevance: Some(95),
+            low_relevance_payout: 50,
+        };
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        orch.connect(&node).await;
+
+        let result = orch.ask(&user, "hello").await.await.unwrap();
+        assert!(matches!(
+            result,
+            Err(OrchestratorError::InsufficientBalance {
+                balance: 0,
+                required: 2_000
+            })
+        ));
+
+        orch.airdrop(&user, 5_000).await;
+        let id = orch.ask(&user, "hello").await;
+        let (_, request) = orch.next_request().await;
+        let id = id.await.unwrap().unwrap();
+        assert_eq!(orch.balance(&user), 3_000);
+
+        // the relevance of the response is 90: a half of the cost is paid
+        orch.respond(&node, id, &request, 300).await;
+        orch.wait_query(id, Query::is_complete).await;
+        orch.wait_query(id, |_| orch.balance(&user) == 4_700).await;
+        assert_eq!(orch.balance(&node), 300);
+        assert!(orch.storage.escrow_table.get(&id).unwrap().is_none());
+    });
+}
+
+#[test]
+fn test_request_options() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&node, RecoveryPolicy::Resume);
+        cfg.request_limits = RequestLimits {
+            min_replication: 1,
+            max_replication: 2,
+            max_tokens: 100,
+        };
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        orch.connect_with(&node, capabilities("deepseek-r1:7b", 4096))
+            .await;
+        orch.airdrop(&user, 100_000).await;
+
+        for options in [
+            AiRequestOptions {
+                temperature: Some(2.5),
+                ..Default::default()
+            },
+            AiRequestOptions {
+                top_p: Some(f32::NAN),
+                ..Default::default()
+            },
+            AiRequestOptions {
+                max_tokens: Some(101),
+                ..Default::default()
+            },
+            AiRequestOptions {
+                model: Some(String::new()),
+                ..Default::default()
+            },
+            AiRequestOptions {
+                replication: Some(3),
+                ..Default::default()
+            },
+        ] {
+            let result = orch.ask_with_options(&user, "hello", options).await;
+            assert!(matches!(
+                result.await.unwrap(),
+                Err(OrchestratorError::InvalidOptions(_))
+            ));
+        }
+        assert_eq!(orch.balance(&user), 100_000);
+
+        let options = AiRequestOptions {
+            temperature: Some(0.7),
+            top_p: Some(0.9),
+            max_tokens: Some(100),
+            model: Some("deepseek-r1".to_string()),
+            min_context: Some(4096),
+            replication: Some(2),
+            callback: None,
+        };
+        let id = orch.ask_with_options(&user, "hello", options.clone()).await;
+        let (_, request) = orch.next_request().await;
+        id.await.unwrap().unwrap();
+        assert_eq!(request.query.options, options);
+        // the cost is reserved for the requested number of nodes and tokens
+        assert_eq!(orch.balance(&user), 100_000 - 2 * 100 * cfg.billing.price);
+    });
+}
+
+#[test]
+fn test_model_routing() {
+    let tmp = TempDir::new("orch").unwrap();
+    let small = PrivateKey::generate();
+    let large = PrivateKey::generate();
+    let unknown = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&small, RecoveryPolicy::Resume);
+        cfg.whitelist = [&small, &large, &unknown]
+            .into_iter()
+            .map(|node| Peer {
+                public_key: node.public_key(),
+                address: None,
+            })
+            .collect();
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        orch.connect_with(&small, capabilities("deepseek-r1:1.5b", 2048))
+            .await;
+        orch.connect_with(&large, capabilities("llama3:70b", 32768))
+            .await;
+        orch.connect(&unknown).await;
+        orch.airdrop(&user, 100_000).await;
+
+        let cases = [
+            (Some("deepseek-r1"), None, &small),
+            (Some("llama3:70b"), None, &large),
+            (None, Some(4096), &large),
+        ];
+        for (model, min_context, expected) in cases {
+            let options = AiRequestOptions {
+                model: model.map(str::to_string),
+                min_context,
+                ..Default::default()
+            };
+            for _ in 0..5 {
+                let id = orch.ask_with_options(&user, "hello", options.clone()).await;
+                let (to, ..) = orch.next_request_to().await;
+                id.await.unwrap().unwrap();
+                assert_eq!(to, expected.public_key().to_p2p().to_peer_id());
+            }
+        }
+
+        // no connected node serves the model
+        let options = AiRequestOptions {
+            model: Some("mistral".to_string()),
+            ..Default::default()
+        };
+        let id = orch.ask_with_options(&user, "hello", options).await;
+        let id = id.await.unwrap().unwrap();
+        let query = orch.wait_query(id, Query::is_complete).await;
+        assert!(query.response.is_empty());
+    });
+}
+
+#[test]
+fn test_saturated_nodes() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let cfg = config(&node, RecoveryPolicy::Resume);
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        orch.connect_with(
+            &node,
+            Capabilities {
+                max_jobs: 1,
+                ..capabilities("deepseek-r1:7b", 4096)
+            },
+        )
+        .await;
+        orch.airdrop(&user, 100_000).await;
+
+        let first = orch.ask(&user, "first").await;
+        let (_, request) = orch.next_request().await;
+        let first = first.await.unwrap().unwrap();
+
+        // the only slot of the node is taken
+        let second = orch.ask(&user, "second").await.await.unwrap().unwrap();
+        let query = orch.wait_query(second, Query::is_complete).await;
+        assert!(query.response.is_empty());
+
+        orch.respond(&node, first, &request, 0).await;
+        orch.wait_query(first, Query::is_complete).await;
+
+        let third = orch.ask(&user, "third").await;
+        let (id, request) = orch.next_request().await;
+        assert_eq!(id, third.await.unwrap().unwrap());
+        orch.respond(&node, id, &request, 0).await;
+        orch.wait_query(id, Query::is_complete).await;
+
+        // the node reports the requests queued by other clients
+        orch.report_load(
+            &node,
+            LoadReport {
+                in_flight: 3,
+                queued: 2,
+            },
+        )
+        .await;
+        let fourth = orch.ask(&user, "fourth").await.await.unwrap().unwrap();
+        let query = orch.wait_query(fourth, Query::is_complete).await;
+        assert!(query.response.is_empty());
+    });
+}
+
+#[test]
+fn test_hedging() {
+    let tmp = TempDir::new("orch").unwrap();
+    let nodes = [
+        PrivateKey::generate(),
+        PrivateKey::generate(),
+        PrivateKey::generate(),
+    ];
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&nodes[0], RecoveryPolicy::Resume);
+        cfg.whitelist = nodes
+            .iter()
+            .map(|node| Peer {
+                public_key: node.public_key(),
+                address: None,
+            })
+            .collect();
+        cfg.hedging = HedgeConfig {
+            max_hedges: 1,
+            soft_deadline_secs: 1,
+        };
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        for node in &nodes {
+            orch.connect_with(node, capabilities("deepseek-r1:7b", 4096))
+                .await;
+        }
+        orch.airdrop(&user, 100_000).await;
+        let by_peer = |peer| {
+            nodes
+                .iter()
+                .find(|node| node.public_key().to_p2p().to_peer_id() == peer)
+                .unwrap()
+        };
+
+        // the failed node is replaced immediately
+        let id = orch.ask(&user, "first").await;
+        let (failed, sent_id, _) = orch.next_request_to().await;
+        let id = id.await.unwrap().unwrap();
+        assert_eq!(sent_id, id);
+        orch.respond_error(by_peer(failed), id, "failed").await;
+        let (hedged, sent_id, request) = orch.next_request_to().await;
+        assert_eq!(sent_id, id);
+        assert_ne!(hedged, failed);
+        orch.respond(by_peer(hedged), id, &request, 0).await;
+        let query = orch.wait_query(id, Query::is_complete).await;
+        assert_eq!(query.response.len(), 2);
+        assert!(matches!(query.response[0], NodeResult::Error(..)));
+        assert!(query.response[1].is_verified());
+
+        // the silent node is replaced after the soft deadline
+        let id = orch.ask(&user, "second").await;
+        let (silent, sent_id, _) = orch.next_request_to().await;
+        let id = id.await.unwrap().unwrap();
+        assert_eq!(sent_id, id);
+        let (hedged, sent_id, request) = orch.next_request_to().await;
+        assert_eq!(sent_id, id);
+        assert_ne!(hedged, silent);
+        orch.respond(by_peer(hedged), id, &request, 0).await;
+        // the outpaced node is stopped, not blamed for a timeout
+        assert_eq!(orch.next_cancel().await, (silent, id));
+        let query = orch.wait_query(id, Query::is_complete).await;
+        assert_eq!(query.response.len(), 2);
+        assert!(query.response[0].is_cancelled());
+        assert!(query.response[1].is_verified());
+    });
+}
+
+#[test]
+fn test_verifier_pipeline() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+    let answers = tmp.path().join("answers.json");
+    std::fs::write(&answers, r#"{"hello": "ai:hello"}"#).unwrap();
+
+    runtime().block_on(async {
+        let cfg = config(&node, RecoveryPolicy::Resume);
+        let verifier = VerifierConfig {
+            evaluators: vec![
+                EvaluatorConfig {
+                    weight: 1,
+                    kind: EvaluatorKind::LlmJudge {
+                        prompt: None,
+                        rubric: vec![],
+                    },
+                },
+                EvaluatorConfig {
+                    weight: 1,
+                    kind: EvaluatorKind::Heuristic {
+                        min_length: 20,
+                        check_language: true,
+                    },
+                },
+                EvaluatorConfig {
+                    weight: 2,
+                    kind: EvaluatorKind::Reference { answers },
+                },
+            ],
+        };
+        let mut orch = Orch::start_with_verifier(
+            &tmp.path().join("db"),
+            &cfg,
+            &verifier,
+            AiMock::new(Duration::ZERO),
+        )
+        .await;
+        orch.connect(&node).await;
+        orch.airdrop(&user, 100_000).await;
+
+        let id = orch.ask(&user, "hello").await;
+        let (_, request) = orch.next_request().await;
+        let id = id.await.unwrap().unwrap();
+        orch.respond(&node, id, &request, 0).await;
+        let query = orch.wait_query(id, Query::is_complete).await;
+
+        let result = &query.response[0].verified().unwrap().result;
+        // (90 + 40 + 2 * 100) / 4
+        assert_eq!(result.relevance.inner(), 83);
+        let scores = result
+            .scores
+            .iter()
+            .map(|score| (score.criterion.as_str(), score.score.inner()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            scores,
+            vec![
+                ("llm_judge.relevance", 90),
+                ("heuristic.non_empty", 100),
+                ("heuristic.length", 40),
+                ("heuristic.language", 100),
+                ("reference.similarity", 100),
+            ]
+        );
+    });
+}
+
+#[test]
+fn test_verifier_pipeline_failure() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let cfg = config(&node, RecoveryPolicy::Resume);
+        let verifier = VerifierConfig {
+            evaluators: vec![
+                EvaluatorConfig {
+                    weight: 1,
+                    kind: EvaluatorKind::LlmJudge {
+                        prompt: None,
+                        rubric: vec![],
+                    },
+                },
+                EvaluatorConfig {
+                    weight: 1,
+                    kind: EvaluatorKind::Heuristic {
+                        min_length: 20,
+                        check_language: false,
+                    },
+                },
+            ],
+        };
+        // the judge is never repaired
+        let ai = AiMock::with_answers(Duration::ZERO, &["{", "{", "{"]);
+        let mut orch = Orch::start_with_verifier(&tmp.path().join("db"), &cfg, &verifier, ai).await;
+        orch.connect(&node).await;
+        orch.airdrop(&user, 100_000).await;
+
+        let id = orch.ask(&user, "hello").await;
+        let (_, request) = orch.next_request().await;
+        let id = id.await.unwrap().unwrap();
+        orch.respond(&node, id, &request, 0).await;
+        let query = orch.wait_query(id, Query::is_complete).await;
+
+        // the failed judge is skipped
+        let result = &query.response[0].verified().unwrap().result;
+        assert_eq!(result.relevance.inner(), 40);
+        assert!(result
+            .scores
+            .iter()
+            .all(|score| score.criterion.starts_with("heuristic.")));
+    });
+}
+
+#[test]
+fn test_verdict_repair() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&node, RecoveryPolicy::Resume);
+        cfg.billing = BillingConfig {
+            price: 1,
+            max_tokens: 1_000,
+            min_relevance: Some(95),
+            low_relevance_payout: 50,
+        };
+        let ai = AiMock::with_answers(
+            Duration::ZERO,
+            &[
+                // the reasoning is ignored
+                r#"<think>{"relevance": 10, "description": "draft"}</think>{"relevance": 70, "description": "fine"}"#,
+                // repaired by the second answer
+                "I'd say 70",
+                r#"{"relevance": 60, "description": "repaired"}"#,
+                // never repaired
+                "I'd say 70",
+                r#"{"description": "no relevance"}"#,
+                "{",
+            ],
+        );
+        let mut orch = Orch::start(tmp.path(), &cfg, ai).await;
+        orch.connect(&node).await;
+        orch.airdrop(&user, 100_000).await;
+
+        let mut results = vec![];
+        for _ in 0..3 {
+            let id = orch.ask(&user, "hello").await;
+            let (_, request) = orch.next_request().await;
+            let id = id.await.unwrap().unwrap();
+            orch.respond(&node, id, &request, 100).await;
+            let query = orch.wait_query(id, Query::is_complete).await;
+            results.push(query.response[0].clone());
+        }
+
+        assert_eq!(results[0].verified().unwrap().result.relevance.inner(), 70);
+        assert_eq!(results[1].verified().unwrap().result.relevance.inner(), 60);
+        assert!(results[2].is_unverified());
+        // the unverified response is paid like the ones below min_relevance
+        assert_eq!(orch.balance(&node), 150);
+    });
+}
+
+#[test]
+fn test_cross_verification() {
+    let tmp = TempDir::new("orch").unwrap();
+    let nodes = [PrivateKey::generate(), PrivateKey::generate()];
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&nodes[0], RecoveryPolicy::Resume);
+        cfg.whitelist = nodes
+            .iter()
+            .map(|node| Peer {
+                public_key: node.public_key(),
+                address: None,
+            })
+            .collect();
+        cfg.cross_verification = CrossVerificationConfig {
+            inspectors: 1,
+            timeout_secs: 1,
+            payout: 10,
+        };
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        for node in &nodes {
+            orch.connect_with(node, capabilities("deepseek-r1:7b", 4096))
+                .await;
+        }
+        orch.airdrop(&user, 100_000).await;
+        let by_peer = |peer| {
+            nodes
+                .iter()
+                .find(|node| node.public_key().to_p2p().to_peer_id() == peer)
+                .unwrap()
+        };
+
+        // the response is judged by the other node
+        let id = orch.ask(&user, "first").await;
+        let (responder, sent_id, request) = orch.next_request_to().await;
+        let id = id.await.unwrap().unwrap();
+        assert_eq!(sent_id, id);
+        orch.respond(by_peer(responder), id, &request, 100).await;
+        let (inspector, sent_id, response) = orch.next_verify_request().await;
+        assert_eq!(sent_id, id);
+        assert_ne!(inspector, responder);
+        let inspector = by_peer(inspector);
+        let verdict = VerificationResult {
+            material: response.clone(),
+            inspector: inspector.public_key(),
+            relevance: 70.try_into().unwrap(),
+            description: "fine".to_string(),
+            scores: vec![],
+            verdicts: vec![],
+        }
+        .sign(inspector)
+        .unwrap();
+        orch.send_verdict(inspector, id, verdict).await;
+
+        let query = orch.wait_query(id, Query::is_complete).await;
+        let NodeResult::Verified(result) = &query.response[0] else {
+            panic!("unexpected result: {:?}", query.response[0]);
+        };
+        result.verify().unwrap();
+        assert_eq!(result.result.relevance.inner(), 70);
+        assert_eq!(result.result.verdicts.len(), 1);
+        let verdict = &result.result.verdicts[0];
+        assert_eq!(verdict.inspector, inspector.public_key());
+        verdict.verify(&response).unwrap();
+        // the inspector is paid a share of the cost of the judged response
+        assert_eq!(orch.balance(inspector), 10);
+        let reputation = orch
+            .storage
+            .reputation_table
+            .get(&inspector.public_key())
+            .unwrap();
+        assert_eq!(reputation.tasks, 1);
+
+        // the orchestrator judges the response itself if the inspector is silent
+        let id = orch.ask(&user, "second").await;
+        let (responder, _, request) = orch.next_request_to().await;
+        let id = id.await.unwrap().unwrap();
+        orch.respond(by_peer(responder), id, &request, 0).await;
+        let (inspector, _, _) = orch.next_verify_request().await;
+        assert_ne!(inspector, responder);
+        let inspector = by_peer(inspector);
+
+        let query = orch.wait_query(id, Query::is_complete).await;
+        let NodeResult::Verified(result) = &query.response[0] else {
+            panic!("unexpected result: {:?}", query.response[0]);
+        };
+        assert_eq!(result.result.relevance.inner(), 90);
+        assert!(result.result.verdicts.is_empty());
+        let reputation = orch
+            .storage
+            .reputation_table
+            .get(&inspector.public_key())
+            .unwrap();
+        assert!(reputation.timeout_rate > 0.0);
+    });
+}
+
+#[test]
+fn test_aggregation_deadline() {
+    let tmp = TempDir::new("orch").unwrap();
+    let nodes = [PrivateKey::generate(), PrivateKey::generate()];
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&nodes[0], RecoveryPolicy::Resume);
+        cfg.whitelist = nodes
+            .iter()
+            .map(|node| Peer {
+                public_key: node.public_key(),
+                address: None,
+            })
+            .collect();
+        cfg.replication_factor = 2;
+        cfg.task_timeout_secs = 2;
+        cfg.aggregation = AggregationStrategy::Pairwise;
+        // the responses are judged, the comparison never completes
+        let verdict = r#"{"relevance": 90, "description": "ok"}"#;
+        let ai = AiMock::stalling(&[verdict, verdict]);
+        let mut orch = Orch::start(tmp.path(), &cfg, ai).await;
+        for node in &nodes {
+            orch.connect(node).await;
+        }
+        orch.airdrop(&user, 100_000).await;
+        let by_peer = |peer| {
+            nodes
+                .iter()
+                .find(|node| node.public_key().to_p2p().to_peer_id() == peer)
+                .unwrap()
+        };
+
+        let id = orch.ask(&user, "hello").await;
+        let requests = [orch.next_request_to().await, orch.next_request_to().await];
+        let id = id.await.unwrap().unwrap();
+        for (peer, _, request) in &requests {
+            orch.respond(by_peer(*peer), id, request, 0).await;
+        }
+
+        let query = orch.wait_query(id, |query| query.answer.is_some()).await;
+        assert!(query.response.iter().all(NodeResult::is_verified));
+        let answer = query.answer.unwrap();
+        assert_eq!(answer.strategy, AggregationStrategy::BestOf);
+        assert_eq!(answer.node, query.response[0].node_key());
+    });
+}
+
+#[test]
+fn test_confidential() {
+    let tmp = TempDir::new("orch").unwrap();
+    let nodes = [PrivateKey::generate(), PrivateKey::generate()];
+    let outsider = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&nodes[0], RecoveryPolicy::Resume);
+        cfg.whitelist = nodes
+            .iter()
+            .chain([&outsider])
+            .map(|node| Peer {
+                public_key: node.public_key(),
+                address: None,
+            })
+            .collect();
+        cfg.cross_verification = CrossVerificationConfig {
+            inspectors: 1,
+            timeout_secs: 1,
+            payout: 10,
+        };
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        for node in nodes.iter().chain([&outsider]) {
+            orch.connect(node).await;
+        }
+        orch.airdrop(&user, 100_000).await;
+        let recipients = nodes.iter().map(PrivateKey::public_key).collect::<Vec<_>>();
+        let by_peer = |peer| {
+            nodes
+                .iter()
+                .find(|node| node.public_key().to_p2p().to_peer_id() == peer)
+                .expect("the request is sent to a node out of the recipients")
+        };
+
+        // the response is sealed to the user and judged by the other recipient
+        let id = orch.ask_confidential(&user, "secret", &recipients).await;
+        let (responder, _, request) = orch.next_request_to().await;
+        let id = id.await.unwrap().unwrap();
+        assert!(request.query.message.is_empty());
+        let responder = by_peer(responder);
+        let inspector = nodes.iter().find(|node| *node != responder).unwrap();
+        orch.respond_sealed(
+            responder,
+            id,
+            &request,
+            &[user.public_key(), inspector.public_key()],
+        )
+        .await;
+        let (to, _, response) = orch.next_verify_request().await;
+        assert_eq!(to, inspector.public_key().to_p2p().to_peer_id());
+        let mut opened = response.node_response.clone();
+        opened.open(inspector).unwrap();
+        assert_eq!(opened.response, "ai:secret");
+        let verdict = VerificationResult {
+            material: response.clone(),
+            inspector: inspector.public_key(),
+            relevance: 70.try_into().unwrap(),
+            description: "fine".to_string(),
+            scores: vec![],
+            verdicts: vec![],
+        }
+        .sign(inspector)
+        .unwrap();
+        orch.send_verdict(inspector, id, verdict).await;
+
+        let mut query = orch.wait_query(id, Query::is_complete).await;
+        assert!(query.response[0].is_verified());
+        assert!(query
+            .as_history()
+            .iter()
+            .all(|h| !h.content.contains("secret")));
+        query.open(&user).unwrap();
+        assert_eq!(query.request.query.message, "secret");
+        assert_eq!(query.answer.unwrap().content, "ai:secret");
+
+        // the orchestrator can't judge the response itself if the inspector is silent
+        let id = orch.ask_confidential(&user, "secret", &recipients).await;
+        let (responder, _, request) = orch.next_request_to().await;
+        let id = id.await.unwrap().unwrap();
+        let responder = by_peer(responder);
+        orch.respond_sealed(responder, id, &request, &[user.public_key()])
+            .await;
+        orch.next_verify_request().await;
+
+        let query = orch.wait_query(id, Query::is_complete).await;
+        assert!(matches!(query.response[0], NodeResult::Unverified(..)));
+
+        // no other recipient could inspect the response
+        let id = orch
+            .ask_confidential(&user, "secret", &recipients[..1])
+            .await;
+        assert!(matches!(
+            id.await.unwrap(),
+            Err(OrchestratorError::InvalidOptions(_))
+        ));
+    });
+}
+
+#[test]
+fn test_cancel() {
+    let tmp = TempDir::new("orch").unwrap();
+    let nodes = [PrivateKey::generate(), PrivateKey::generate()];
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let mut cfg = config(&nodes[0], RecoveryPolicy::Resume);
+        cfg.whitelist = nodes
+            .iter()
+            .map(|node| Peer {
+                public_key: node.public_key(),
+                address: None,
+            })
+            .collect();
+        cfg.replication_factor = 2;
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        for node in &nodes {
+            orch.connect(node).await;
+        }
+        orch.airdrop(&user, 100_000).await;
+        let by_peer = |peer| {
+            nodes
+                .iter()
+                .find(|node| node.public_key().to_p2p().to_peer_id() == peer)
+                .unwrap()
+        };
+
+        let id = orch.ask(&user, "hello").await;
+        let (answering, _, request) = orch.next_request_to().await;
+        let (generating, _, _) = orch.next_request_to().await;
+        let id = id.await.unwrap().unwrap();
+        orch.respond(by_peer(answering), id, &request, 100).await;
+        orch.send_chunk(by_peer(generating), id, 0, "12345678")
+            .await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // only the author can cancel the query
+        let stranger = PrivateKey::generate();
+        assert!(matches!(
+            orch.cancel(&stranger, id).await,
+            Err(OrchestratorError::NotQueryOwner(_))
+        ));
+
+        orch.cancel(&user, id).await.unwrap();
+        assert_eq!(orch.next_cancel().await, (generating, id));
+
+        // the received response is verified, the partial one is paid by its length
+        let query = orch.wait_query(id, Query::is_complete).await;
+        let generating_key = by_peer(generating).public_key();
+        for result in &query.response {
+            if result.node_key() == generating_key {
+                assert!(result.is_cancelled());
+            } else {
+                assert!(result.is_verified());
+            }
+        }
+        orch.wait_query(id, |_| {
+            orch.balance(&user) + orch.balance(&nodes[0]) + orch.balance(&nodes[1]) == 100_000
+        })
+        .await;
+        assert_eq!(orch.balance(by_peer(generating)), 2);
+        assert_eq!(orch.balance(by_peer(answering)), 100);
+    });
+}
+
+/// Fails the first delivery and passes the next ones to the test.
+struct CallbackReceiver {
+    failed: AtomicBool,
+    deliveries: UnboundedSender<(String, Vec<u8>)>,
+}
+
+#[handler]
+async fn callback(
+    req: &Request,
+    body: Vec<u8>,
+    receiver: Data<&Arc<CallbackReceiver>>,
+) -> StatusCode {
+    if !receiver.failed.swap(true, Ordering::AcqRel) {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+    let signature = req.header(SIGNATURE_HEADER).unwrap_or_default().to_string();
+    receiver.deliveries.send((signature, body)).unwrap();
+    StatusCode::OK
+}
+
+#[test]
+fn test_webhook() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let (tx, mut deliveries) = unbounded_channel();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let receiver = Arc::new(CallbackReceiver {
+            failed: AtomicBool::new(false),
+            deliveries: tx,
+        });
+        tokio::spawn(
+            Server::new_with_acceptor(TcpAcceptor::from_std(listener).unwrap())
+                .run(Route::new().at("/callback", post(callback)).data(receiver)),
+        );
+
+        let mut cfg = config(&node, RecoveryPolicy::Resume);
+        cfg.webhooks = WebhookConfig {
+            enabled: true,
+            retries: 3,
+            min_delay_ms: 10,
+            timeout_secs: 5,
+            allowed_hosts: vec!["127.0.0.1".to_owned()],
+        };
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        orch.connect(&node).await;
+        orch.airdrop(&user, 10_000).await;
+
+        for invalid in [
+            "ftp://localhost/callback",
+            "http://169.254.169.254/latest/meta-data",
+        ] {
+            let invalid = AiRequestOptions {
+                callback: Some(invalid.to_owned()),
+                ..Default::default()
+            };
+            let result = orch.ask_with_options(&user, "hello", invalid).await;
+            assert!(matches!(
+                result.await.unwrap(),
+                Err(OrchestratorError::InvalidOptions(_))
+            ));
+        }
+
+        let options = AiRequestOptions {
+            callback: Some(format!("http://{addr}/callback")),
+            ..Default::default()
+        };
+        let id = orch.ask_with_options(&user, "hello", options).await;
+        let (_, request) = orch.next_request().await;
+        let id = id.await.unwrap().unwrap();
+        orch.respond(&node, id, &request, 0).await;
+
+        // the failed delivery is retried, the body is signed by the orchestrator
+        let (signature, body) = tokio::time::timeout(Duration::from_secs(10), deliveries.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let signature = Signature::try_from(signature.as_str()).unwrap();
+        orch.key.verify(&body, &signature).unwrap();
+        let query: Query = serde_json::from_slice(&body).unwrap();
+        assert_eq!(query.id, id);
+        assert!(query.is_complete());
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while orch.storage.webhook_table.get(&id).unwrap().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("deliveries are not logged");
+        let log = orch.storage.webhook_table.get(&id).unwrap();
+        assert_eq!(log[0].status, Some(500));
+        assert!(!log[0].is_success());
+        assert_eq!(log[1].status, Some(200));
+        assert!(log[1].is_success());
+    });
+}
+
+#[test]
+fn test_webhook_resume() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+    // the listener stays bound across the runs, so the port can't be taken by another test
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let mut cfg = config(&node, RecoveryPolicy::Resume);
+    cfg.webhooks = WebhookConfig {
+        enabled: true,
+        retries: 3,
+        min_delay_ms: 60_000,
+        timeout_secs: 5,
+        allowed_hosts: vec!["127.0.0.1".to_owned()],
+    };
+
+    // dropping the runtime kills the delivery waiting for the retry
+    let id = runtime().block_on(async {
+        let (tx, _deliveries) = unbounded_channel();
+        // the first delivery fails
+        let receiver = Arc::new(CallbackReceiver {
+            failed: AtomicBool::new(false),
+            deliveries: tx,
+        });
+        tokio::spawn(
+            Server::new_with_acceptor(
+                TcpAcceptor::from_std(listener.try_clone().unwrap()).unwrap(),
+            )
+            .run(Route::new().at("/callback", post(callback)).data(receiver)),
+        );
+
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        orch.connect(&node).await;
+        orch.airdrop(&user, 10_000).await;
+        let options = AiRequestOptions {
+            callback: Some(format!("http://{addr}/callback")),
+            ..Default::default()
+        };
+        let id = orch.ask_with_options(&user, "hello", options).await;
+        let (_, request) = orch.next_request().await;
+        let id = id.await.unwrap().unwrap();
+        orch.respond(&node, id, &request, 0).await;
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while orch.storage.webhook_table.get(&id).unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("delivery is not attempted");
+        assert_eq!(orch.storage.webhook_table.pending().unwrap().len(), 1);
+        id
+    });
+
+    runtime().block_on(async {
+        let (tx, mut deliveries) = unbounded_channel();
+        let receiver = Arc::new(CallbackReceiver {
+            failed: AtomicBool::new(true),
+            deliveries: tx,
+        });
+        tokio::spawn(
+            Server::new_with_acceptor(TcpAcceptor::from_std(listener).unwrap())
+                .run(Route::new().at("/callback", post(callback)).data(receiver)),
+        );
+
+        let orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        let (_, body) = tokio::time::timeout(Duration::from_secs(10), deliveries.recv())
+            .await
+            .expect("delivery is not resumed")
+            .unwrap();
+        let query: Query = serde_json::from_slice(&body).unwrap();
+        assert_eq!(query.id, id);
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !orch.storage.webhook_table.pending().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("delivery is still pending");
+        let log = orch.storage.webhook_table.get(&id).unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].status, Some(500));
+        assert_eq!(log[1].attempt, 2);
+        assert!(log[1].is_success());
+    });
+}
+
+#[test]
+fn test_events() {
+    let tmp = TempDir::new("orch").unwrap();
+    let node = PrivateKey::generate();
+    let user = PrivateKey::generate();
+
+    runtime().block_on(async {
+        let cfg = config(&node, RecoveryPolicy::Resume);
+        let mut orch = Orch::start(tmp.path(), &cfg, AiMock::new(Duration::ZERO)).await;
+        orch.connect(&node).await;
+        orch.airdrop(&user, 10_000).await;
+        let mut events = orch.events().await;
+
+        let id = orch.ask(&user, "hello").await;
+        let (_, request) = orch.next_request().await;
+        let id = id.await.unwrap().unwrap();
+        orch.respond(&node, id, &request, 0).await;
+
+        let mut states = vec![];
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(10), events.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(*event.id(), id);
+            assert_eq!(*event.requester(), user.public_key());
+            match event {
+                QueryEvent::Transition { result, .. } => {
+                    assert_eq!(result.node_key(), node.public_key());
+                    states.push(result);
+                }
+                QueryEvent::Complete { .. } => break,
+            }
+        }
+        assert_eq!(states.len(), 3);
+        assert!(states[0].is_sent_request());
+        assert!(states[1].is_node_response());
+        assert!(states[2].is_verified());
+    });
+}
@@ -1 +1,478 @@
+use ai::Ai;
+use crypto::ed25519::{private::PrivateKey, public::PublicKey};
+use futures::{
+    channel::mpsc::{Receiver, Sender},
+    SinkExt as _, StreamExt as _,
+};
+use node_config::{
+    tasks::{AiTasksConfig, RecoveryConfig, RecoveryPolicy},
+    verifier::VerifierConfig,
+};
+use orchestrator::{
+    spawn_orchestrator, ApiSender, OrchRequest, OrchestratorError, OrchestratorHandles,
+};
+use p2p::{
+    etp::{DeliveryResult, FromETP, ToETP},
+    key::ToP2P as _,
+    task::PeerId,
+};
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use storage::EveStorage;
+use tokio::sync::{broadcast, oneshot};
+use types::{
+    ai::{
+        query::{Query, QueryId},
+        request::{AiRequest, AiRequestOptions, CancelRequest, SignedAiRequest},
+        response::{AiResponse, SignedAiResponse},
+        stream::QueryEvent,
+        verification::SignedVerificationResult,
+    },
+    cluster::{Capabilities, HardwareClass, LoadReport},
+    p2p::{EveMessage, NodeMessage, OrchMessage, Peer},
+};
 
+pub struct AiMock {
+    delay: Duration,
+    /// Answers given before the default one.
+    answers: Mutex<VecDeque<String>>,
+    /// Never answers once the scripted answers are given.
+    stall: bool,
+}
+
+impl AiMock {
+    pub fn new(delay: Duration) -> Self {
+        Self::with_answers(delay, &[])
+    }
+
+    pub fn with_answers(delay: Duration, answers: &[&str]) -> Self {
+        Self {
+            delay,
+            answers: Mutex::new(answers.iter().map(|answer| answer.to_string()).collect()),
+            stall: false,
+        }
+    }
+
+    pub fn stalling(answers: &[&str]) -> Self {
+        Self {
+            stall: true,
+            ..Self::with_answers(Duration::ZERO, answers)
+        }
+    }
+}
+
+type AiResp = Result<ai::Answer, ai::error::AiError>;
+
+impl Ai for AiMock {
+    async fn ask(&self, _: ai::Question) -> AiResp {
+        tokio::time::sleep(self.delay).await;
+
+        let message = self.answers.lock().unwrap().pop_front();
+        if message.is_none() && self.stall {
+            std::future::pending::<()>().await;
+        }
+        Ok(ai::Answer {
+            message: message
+                .unwrap_or_else(|| r#"{"relevance": 90, "description": "ok"}"#.to_string()),
+            tokens: 0,
+            truncation: None,
+        })
+    }
+}
+
+pub fn capabilities(model: &str, context_length: u64) -> Capabilities {
+    Capabilities {
+        model: model.to_string(),
+        context_length,
+        max_jobs: 16,
+        hardware: HardwareClass::Cpu,
+    }
+}
+
+pub fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+pub fn config(node: &PrivateKey, policy: RecoveryPolicy) -> AiTasksConfig {
+    AiTasksConfig {
+        whitelist: vec![Peer {
+            public_key: node.public_key(),
+            address: None,
+        }],
+        replication_factor: 1,
+        task_timeout_secs: 30,
+        recovery: RecoveryConfig {
+            policy,
+            delay_secs: 1,
+        },
+        ..Default::default()
+    }
+}
+
+/// Orchestrator with the p2p layer replaced by channels.
+pub struct Orch {
+    pub storage: Arc<EveStorage>,
+    /// Public key of the orchestrator.
+    pub key: PublicKey,
+    api: ApiSender,
+    to_orch: Sender<FromETP<EveMessage>>,
+    from_orch: Receiver<ToETP<EveMessage>>,
+    _handles: OrchestratorHandles,
+}
+
+impl Orch {
+    pub async fn start(path: &Path, cfg: &AiTasksConfig, ai: AiMock) -> Self {
+        Self::start_with_verifier(path, cfg, &Default::default(), ai).await
+    }
+
+    pub async fn start_with_verifier(
+        path: &Path,
+        cfg: &AiTasksConfig,
+        verifier: &VerifierConfig,
+        ai: AiMock,
+    ) -> Self {
+        let storage = Arc::new(EveStorage::new(path, &Default::default()).unwrap());
+        let (api, api_rx) = tokio::sync::mpsc::channel(100);
+        let (to_p2p, from_orch) = futures::channel::mpsc::channel(100);
+        let (to_orch, from_p2p) = futures::channel::mpsc::channel(100);
+        let key = PrivateKey::generate();
+
+        let handles = spawn_orchestrator(
+            storage.clone(),
+            api_rx,
+            (to_p2p, from_p2p),
+            Arc::new(ai),
+            key.clone(),
+            cfg,
+            verifier,
+        )
+        .await
+        .unwrap();
+
+        Self {
+            storage,
+            key: key.public_key(),
+            api,
+            to_orch,
+            from_orch,
+            _handles: handles,
+        }
+    }
+
+    pub async fn connect(&mut self, node: &PrivateKey) {
+        let peer_id = node.public_key().to_p2p().to_peer_id();
+        self.to_orch.send(FromETP::Connect(peer_id)).await.unwrap();
+        // let the orchestrator handle the connection
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    /// Connects the node and reports its capabilities.
+    pub async fn connect_with(&mut self, node: &PrivateKey, capabilities: Capabilities) {
+        let peer_id = node.public_key().to_p2p().to_peer_id();
+        self.to_orch.send(FromETP::Connect(peer_id)).await.unwrap();
+        self.to_orch
+            .send(FromETP::Receive(
+                peer_id,
+                EveMessage::Node(NodeMessage::Capabilities(capabilities)),
+            ))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    pub async fn report_load(&mut self, node: &PrivateKey, report: LoadReport) {
+        let peer_id = node.public_key().to_p2p().to_peer_id();
+        self.to_orch
+            .send(FromETP::Receive(
+                peer_id,
+                EveMessage::Node(NodeMessage::Load(report)),
+            ))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    pub async fn airdrop(&self, user: &PrivateKey, amount: u64) {
+        let (tx, rx) = oneshot::channel();
+        self.api
+            .send(OrchRequest::Airdrop {
+                address: user.public_key(),
+                amount,
+                tx,
+            })
+            .await
+            .unwrap();
+        rx.await.unwrap().unwrap();
+    }
+
+    pub fn balance(&self, key: &PrivateKey) -> u64 {
+        self.storage
+            .account_table
+            .get(&key.public_key())
+            .unwrap()
+            .unwrap_or_default()
+            .balance
+    }
+
+    /// The query id is received once the request is delivered to the nodes.
+    pub async fn ask(
+        &self,
+        user: &PrivateKey,
+        message: &str,
+    ) -> oneshot::Receiver<Result<QueryId, OrchestratorError>> {
+        self.ask_with_options(user, message, AiRequestOptions::default())
+            .await
+    }
+
+    pub async fn ask_with_options(
+        &self,
+        user: &PrivateKey,
+        message: &str,
+        options: AiRequestOptions,
+    ) -> oneshot::Receiver<Result<QueryId, OrchestratorError>> {
+        let request =
+            AiRequest::new(message.to_string(), vec![], user.public_key()).with_options(options);
+        self.send_request(user, request).await
+    }
+
+    /// Asks the request readable only by the `nodes`.
+    pub async fn ask_confidential(
+        &self,
+        user: &PrivateKey,
+        message: &str,
+        nodes: &[PublicKey],
+    ) -> oneshot::Receiver<Result<QueryId, OrchestratorError>> {
+        let request =
+            AiRequest::confidential(message.to_string(), vec![], user.public_key(), nodes).unwrap();
+        self.send_request(user, request).await
+    }
+
+    async fn send_request(
+        &self,
+        user: &PrivateKey,
+        request: AiRequest,
+    ) -> oneshot::Receiver<Result<QueryId, OrchestratorError>> {
+        let request = request.sign(user).unwrap().verify().unwrap();
+        let (tx, rx) = oneshot::channel();
+        self.api
+            .send(OrchRequest::Ask { request, tx })
+            .await
+            .unwrap();
+        rx
+    }
+
+    pub async fn events(&self) -> broadcast::Receiver<QueryEvent> {
+        let (tx, rx) = oneshot::channel();
+        self.api.send(OrchRequest::Events { tx }).await.unwrap();
+        rx.await.unwrap()
+    }
+
+    pub async fn cancel(&self, user: &PrivateKey, id: QueryId) -> Result<(), OrchestratorError> {
+        let request = CancelRequest::new(id, user.public_key())
+            .sign(user)
+            .unwrap()
+            .verify()
+            .unwrap();
+        let (tx, rx) = oneshot::channel();
+        self.api
+            .send(OrchRequest::Cancel { request, tx })
+            .await
+            .unwrap();
+        rx.await.unwrap()
+    }
+
+    /// Waits for the cancel sent to a node.
+    pub async fn next_cancel(&mut self) -> (PeerId, QueryId) {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(10), self.from_orch.next())
+                .await
+                .expect("no cancel sent to the nodes")
+                .unwrap();
+            if let ToETP::Send {
+                to,
+                message: EveMessage::Orch(OrchMessage::Cancel { id }),
+                ..
+            } = msg
+            {
+                return (to, id);
+            }
+        }
+    }
+
+    /// Waits for the request sent to a node and confirms its delivery.
+    pub async fn next_request(&mut self) -> (QueryId, SignedAiRequest) {
+        let (_, id, request) = self.next_request_to().await;
+        (id, request)
+    }
+
+    /// Same as [`Orch::next_request`], but also returns the receiving node.
+    pub async fn next_request_to(&mut self) -> (PeerId, QueryId, SignedAiRequest) {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(10), self.from_orch.next())
+                .await
+                .expect("no request sent to the nodes")
+                .unwrap();
+            if let ToETP::Send {
+                to,
+                message: EveMessage::Orch(OrchMessage::AiRequest { id, request }),
+                on_received,
+            } = msg
+            {
+                if let Some(on_received) = on_received {
+                    on_received.send(DeliveryResult::Success).unwrap();
+                }
+                return (to, id, request);
+            }
+        }
+    }
+
+    /// Waits for the response sent to a node to judge and confirms its delivery.
+    pub async fn next_verify_request(&mut self) -> (PeerId, QueryId, SignedAiResponse) {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(10), self.from_orch.next())
+                .await
+                .expect("no verification request sent to the nodes")
+                .unwrap();
+            if let ToETP::Send {
+                to,
+                message: EveMessage::Orch(OrchMessage::VerifyRequest { id, response, .. }),
+                on_received,
+            } = msg
+            {
+                if let Some(on_received) = on_received {
+                    on_received.send(DeliveryResult::Success).unwrap();
+                }
+                return (to, id, response);
+            }
+        }
+    }
+
+    pub async fn send_verdict(
+        &mut self,
+        inspector: &PrivateKey,
+        id: QueryId,
+        result: SignedVerificationResult,
+    ) {
+        let peer_id = inspector.public_key().to_p2p().to_peer_id();
+        self.to_orch
+            .send(FromETP::Receive(
+                peer_id,
+                EveMessage::Node(NodeMessage::VerifyResponse {
+                    id,
+                    node: result.result.material.node_key(),
+                    result: Ok(result),
+                }),
+            ))
+            .await
+            .unwrap();
+    }
+
+    pub async fn respond(
+        &mut self,
+        node: &PrivateKey,
+        id: QueryId,
+        request: &SignedAiRequest,
+        cost: u64,
+    ) {
+        let response = AiResponse {
+            timestamp: request.query.timestamp,
+            response: format!("ai:{}", request.query.message),
+            pubkey: node.public_key(),
+            request_signature: request.signature().clone(),
+            cost,
+            reasoning: None,
+            sealed: None,
+            truncation: None,
+        };
+        self.send_response(node, id, response).await;
+    }
+
+    /// Responds to the confidential request like a node: opens the prompt and seals the answer.
+    pub async fn respond_sealed(
+        &mut self,
+        node: &PrivateKey,
+        id: QueryId,
+        request: &SignedAiRequest,
+        recipients: &[PublicKey],
+    ) {
+        let mut query = request.query.clone();
+        query.open(node).unwrap();
+        let mut response = AiResponse {
+            timestamp: query.timestamp,
+            response: format!("ai:{}", query.message),
+            pubkey: node.public_key(),
+            request_signature: request.signature().clone(),
+            cost: 0,
+            reasoning: None,
+            sealed: None,
+            truncation: None,
+        };
+        response.seal(recipients).unwrap();
+        self.send_response(node, id, response).await;
+    }
+
+    async fn send_response(&mut self, node: &PrivateKey, id: QueryId, response: AiResponse) {
+        let response = response.sign(node).unwrap();
+        let peer_id = node.public_key().to_p2p().to_peer_id();
+        self.to_orch
+            .send(FromETP::Receive(
+                peer_id,
+                EveMessage::Node(NodeMessage::AiResponse {
+                    id,
+                    response: Ok(response),
+                }),
+            ))
+            .await
+            .unwrap();
+    }
+
+    pub async fn send_chunk(&mut self, node: &PrivateKey, id: QueryId, offset: u64, chunk: &str) {
+        let peer_id = node.public_key().to_p2p().to_peer_id();
+        self.to_orch
+            .send(FromETP::Receive(
+                peer_id,
+                EveMessage::Node(NodeMessage::AiResponseChunk {
+                    id,
+                    offset,
+                    chunk: chunk.to_string(),
+                }),
+            ))
+            .await
+            .unwrap();
+    }
+
+    pub async fn respond_error(&mut self, node: &PrivateKey, id: QueryId, error: &str) {
+        let peer_id = node.public_key().to_p2p().to_peer_id();
+        self.to_orch
+            .send(FromETP::Receive(
+                peer_id,
+                EveMessage::Node(NodeMessage::AiResponse {
+                    id,
+                    response: Err(error.to_string()),
+                }),
+            ))
+            .await
+            .unwrap();
+    }
+
+    pub async fn wait_query(&self, id: QueryId, until: impl Fn(&Query) -> bool) -> Query {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let query = self.storage.query_table.get_query(&id).unwrap();
+                if let Some(query) = query.filter(&until) {
+                    return query;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("query is not updated")
+    }
+}
@@ -32,24 +32,4 @@ impl AccountsTable {
     pub fn get(&self, pubkey: &PublicKey) -> Result<Option<EveAccount>, StorageError> {
         self.accounts.get(pubkey)
     }
-
-    /// todo make it transactional
-    pub fn update_balance(
-        &self,
-        public_key: PublicKey,
-        sum: i64,
-        ws: &mut WriteSet,
-    ) -> Result<(), StorageError> {
-        let mut acc = self.get(&public_key)?.unwrap_or_default();
-
-        if sum > 0 {
-            acc.balance = acc.balance.saturating_add(sum as u64);
-        } else {
-            // todo: check if balance is enough
-            acc.balance = acc.balance.saturating_sub(sum.unsigned_abs());
-        }
-
-        self.accounts.put(&public_key, &acc, ws)?;
-        Ok(())
-    }
 }
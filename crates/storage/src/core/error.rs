@@ -15,6 +15,12 @@ pub enum StorageError {
     CorruptedData,
     #[error("Already exists")]
     AlreadyExists,
+    #[error("Insufficient balance: {balance}, required: {required}")]
+    InsufficientBalance { balance: u64, required: u64 },
+    #[error("Balance overflow")]
+    BalanceOverflow,
+    #[error("Storage version {0} is newer than the supported one")]
+    UnsupportedVersion(u32),
 }
 
 #[cfg(feature = "err_poem")]
@@ -27,9 +33,12 @@ impl ResponseError for StorageError {
             StorageError::RocksDb(_)
             | StorageError::Serde(_)
             | StorageError::ColumnFamilyNotFound(_)
-            | StorageError::CorruptedData => StatusCode::INTERNAL_SERVER_ERROR,
+            | StorageError::CorruptedData
+            | StorageError::UnsupportedVersion(_) => StatusCode::INTERNAL_SERVER_ERROR,
             StorageError::QueryNotFound(_) => StatusCode::BAD_REQUEST,
             StorageError::AlreadyExists => StatusCode::INTERNAL_SERVER_ERROR,
+            StorageError::InsufficientBalance { .. } => StatusCode::PAYMENT_REQUIRED,
+            StorageError::BalanceOverflow => StatusCode::BAD_REQUEST,
         }
     }
 }
@@ -0,0 +1,34 @@
+use crate::{
+    core::{error::StorageError, table::Table},
+    WriteSet,
+};
+use types::{account::Escrow, ai::query::QueryId};
+
+pub const ESCROW_TABLE_NAME: &str = "escrow-table";
+
+pub struct EscrowTable {
+    escrow: Table<QueryId, Escrow>,
+}
+
+impl EscrowTable {
+    pub fn new(escrow: Table<QueryId, Escrow>) -> Self {
+        Self { escrow }
+    }
+
+    pub fn get(&self, id: &QueryId) -> Result<Option<Escrow>, StorageError> {
+        self.escrow.get(id)
+    }
+
+    pub fn put(
+        &self,
+        id: &QueryId,
+        escrow: &Escrow,
+        ws: &mut WriteSet,
+    ) -> Result<(), StorageError> {
+        self.escrow.put(id, escrow, ws)
+    }
+
+    pub fn remove(&self, id: &QueryId, ws: &mut WriteSet) -> Result<(), StorageError> {
+        self.escrow.delete(id, ws)
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    account::AccountsTable,
+    core::{db::EveDB, error::StorageError, table::Table},
+    WriteSet,
+};
+use crypto::ed25519::public::PublicKey;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use types::{
+    account::{EveAccount, Transaction, TransactionKind},
+    ai::query::QueryId,
+};
+
+pub const LEDGER_TABLE_NAME: &str = "ledger";
+pub const LEDGER_SEQUENCE_TABLE_NAME: &str = "ledger-sequence";
+
+/// Account and the number of the transaction within the account.
+pub type LedgerKey = (PublicKey, u64);
+
+/// Balance change of an account.
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub account: PublicKey,
+    pub kind: TransactionKind,
+    pub amount: u64,
+    pub query: Option<QueryId>,
+}
+
+/// Serialized writer of the account balances.
+/// Every balance change is recorded in the append-only ledger.
+pub struct Ledger {
+    db: Arc<EveDB>,
+    accounts: AccountsTable,
+    transactions: Table<LedgerKey, Transaction>,
+    sequence: Table<PublicKey, u64>,
+    lock: Mutex<()>,
+}
+
+impl Ledger {
+    pub fn new(
+        db: Arc<EveDB>,
+        accounts: AccountsTable,
+        transactions: Table<LedgerKey, Transaction>,
+        sequence: Table<PublicKey, u64>,
+    ) -> Self {
+        Self {
+            db,
+            accounts,
+            transactions,
+            sequence,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Applies the transfers and commits them together with `ws`.
+    /// Nothing is changed if any balance becomes negative or overflows.
+    pub fn apply(&self, transfers: &[Transfer], mut ws: WriteSet) -> Result<(), StorageError> {
+        let _guard = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let mut accounts: HashMap<PublicKey, (EveAccount, u64)> = HashMap::new();
+        for transfer in transfers {
+            let (account, sequence) = match accounts.get_mut(&transfer.account) {
+                Some(entry) => entry,
+                None => {
+                    let account = self.accounts.get(&transfer.account)?.unwrap_or_default();
+                    let sequence = self.sequence.get(&transfer.account)?.unwrap_or(0);
+                    accounts
+                        .entry(transfer.account)
+                        .or_insert((account, sequence))
+                }
+            };
+
+            account.balance = if transfer.kind.is_credit() {
+                account
+                    .balance
+                    .checked_add(transfer.amount)
+                    .ok_or(StorageError::BalanceOverflow)?
+            } else {
+                account.balance.checked_sub(transfer.amount).ok_or(
+                    StorageError::InsufficientBalance {
+                        balance: account.balance,
+                        required: transfer.amount,
+                    },
+                )?
+            };
+            *sequence += 1;
+
+            self.transactions.put(
+                &(transfer.account, *sequence),
+                &Transaction {
+                    kind: transfer.kind,
+                    amount: transfer.amount,
+                    balance: account.balance,
+                    query: transfer.query,
+                    timestamp,
+                },
+                &mut ws,
+            )?;
+        }
+
+        for (key, (account, sequence)) in accounts {
+            self.accounts.create(key, &account, &mut ws)?;
+            self.sequence.put(&key, &sequence, &mut ws)?;
+        }
+        self.db.commit(ws)
+    }
+
+    /// Returns the transactions of the account, the oldest first.
+    pub fn transactions(
+        &self,
+        pubkey: &PublicKey,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Transaction>, StorageError> {
+        self.transactions
+            .scan(pubkey)?
+            .skip(offset)
+            .take(limit)
+            .map(|entry| entry.map(|(_, transaction)| transaction))
+            .collect()
+    }
+}
@@ -1,8 +1,13 @@
 pub mod account;
 pub mod cluster;
 mod core;
+pub mod escrow;
+pub mod ledger;
+mod migration;
 pub mod query;
+pub mod reputation;
 pub mod sequence;
+pub mod webhook;
 
 use account::ACCOUNT_TABLE_NAME;
 use cluster::{CLUSTER_ADDRESS_TABLE_NAME, CLUSTER_TABLE_NAME};
@@ -11,11 +16,17 @@ use core::{
     table::{family_descriptor, Table},
 };
 pub use core::{error::StorageError, tx::WriteSet};
+use escrow::ESCROW_TABLE_NAME;
 use eyre::Result;
+use ledger::{LEDGER_SEQUENCE_TABLE_NAME, LEDGER_TABLE_NAME};
+use migration::META_TABLE_NAME;
+pub use migration::STORAGE_VERSION;
 use node_config::db::RocksdbConfig;
 use query::{QUERY_BY_PUB_KEY, QUERY_IN_PROGRESS, QUERY_TABLE_NAME};
+use reputation::REPUTATION_TABLE_NAME;
 use sequence::SEQUENCE_TABLE_NAME;
 use std::{path::Path, sync::Arc};
+use webhook::{WEBHOOK_PENDING_TABLE_NAME, WEBHOOK_TABLE_NAME};
 
 pub struct EveStorage {
     db: Arc<EveDB>,
@@ -23,6 +34,10 @@ pub struct EveStorage {
     pub sequence_table: sequence::SequenceTable,
     pub cluster_table: cluster::ClusterTable,
     pub account_table: account::AccountsTable,
+    pub reputation_table: reputation::ReputationTable,
+    pub escrow_table: escrow::EscrowTable,
+    pub ledger: ledger::Ledger,
+    pub webhook_table: webhook::WebhookTable,
 }
 
 impl EveStorage {
@@ -38,6 +53,13 @@ impl EveStorage {
                 family_descriptor(CLUSTER_TABLE_NAME, cfg, None),
                 family_descriptor(CLUSTER_ADDRESS_TABLE_NAME, cfg, None),
                 family_descriptor(ACCOUNT_TABLE_NAME, cfg, None),
+                family_descriptor(REPUTATION_TABLE_NAME, cfg, None),
+                family_descriptor(ESCROW_TABLE_NAME, cfg, None),
+                family_descriptor(LEDGER_TABLE_NAME, cfg, Some(32)),
+                family_descriptor(LEDGER_SEQUENCE_TABLE_NAME, cfg, None),
+                family_descriptor(WEBHOOK_TABLE_NAME, cfg, None),
+                family_descriptor(WEBHOOK_PENDING_TABLE_NAME, cfg, None),
+                family_descriptor(META_TABLE_NAME, cfg, None),
             ],
         )?);
 
@@ -46,6 +68,12 @@ impl EveStorage {
             Table::new(db.clone(), QUERY_IN_PROGRESS)?,
             Table::new(db.clone(), QUERY_BY_PUB_KEY)?,
         );
+        migration::migrate(
+            &db,
+            &Table::new(db.clone(), META_TABLE_NAME)?,
+            &Table::new(db.clone(), QUERY_TABLE_NAME)?,
+            &query_table,
+        )?;
 
         let sequence_table =
             sequence::SequenceTable::new(Table::new(db.clone(), SEQUENCE_TABLE_NAME)?);
@@ -58,12 +86,33 @@ impl EveStorage {
         let account_table =
             account::AccountsTable::new(Table::new(db.clone(), ACCOUNT_TABLE_NAME)?);
 
+        let reputation_table =
+            reputation::ReputationTable::new(Table::new(db.clone(), REPUTATION_TABLE_NAME)?);
+
+        let escrow_table = escrow::EscrowTable::new(Table::new(db.clone(), ESCROW_TABLE_NAME)?);
+
+        let ledger = ledger::Ledger::new(
+            db.clone(),
+            account::AccountsTable::new(Table::new(db.clone(), ACCOUNT_TABLE_NAME)?),
+            Table::new(db.clone(), LEDGER_TABLE_NAME)?,
+            Table::new(db.clone(), LEDGER_SEQUENCE_TABLE_NAME)?,
+        );
+
+        let webhook_table = webhook::WebhookTable::new(
+            Table::new(db.clone(), WEBHOOK_TABLE_NAME)?,
+            Table::new(db.clone(), WEBHOOK_PENDING_TABLE_NAME)?,
+        );
+
         Ok(Self {
             db,
             query_table,
             sequence_table,
             cluster_table,
             account_table,
+            reputation_table,
+            escrow_table,
+            ledger,
+            webhook_table,
         })
     }
 
@@ -0,0 +1,68 @@
+use crate::{
+    core::{db::EveDB, error::StorageError, table::Table, tx::WriteSet},
+    query::QueryTable,
+};
+use tracing::info;
+use types::ai::{
+    legacy,
+    query::{NodeResult, Query, QueryId},
+};
+
+pub const META_TABLE_NAME: &str = "meta";
+const VERSION_KEY: &str = "version";
+
+/// Version of the format of the stored values, bumped when the stored types change.
+/// The databases of the first release have no version and use the `legacy` formats.
+pub const STORAGE_VERSION: u32 = 1;
+
+/// Why the migrated responses that were not judged are left unverified.
+const MIGRATED: &str = "The response was stored by the previous version";
+
+/// Brings the database written by the previous versions to the current format.
+/// All the queries are migrated in a single batch, the database is left untouched on failure.
+pub(crate) fn migrate(
+    db: &EveDB,
+    meta: &Table<String, u32>,
+    legacy_queries: &Table<QueryId, legacy::Query>,
+    queries: &QueryTable,
+) -> Result<(), StorageError> {
+    let version = meta.get(&VERSION_KEY.to_string())?;
+    match version {
+        Some(STORAGE_VERSION) => return Ok(()),
+        Some(version) if version > STORAGE_VERSION => {
+            return Err(StorageError::UnsupportedVersion(version));
+        }
+        _ => {}
+    }
+
+    let mut ws = WriteSet::default();
+    let mut migrated = 0;
+    for entry in legacy_queries.iter(None)? {
+        let (_, query) = entry?;
+        queries.put_query(&finalize(query.into()), &mut ws)?;
+        migrated += 1;
+    }
+    meta.put(&VERSION_KEY.to_string(), &STORAGE_VERSION, &mut ws)?;
+    db.commit(ws)?;
+    if migrated > 0 {
+        info!("Migrated {migrated} queries to the storage version {STORAGE_VERSION}");
+    }
+    Ok(())
+}
+
+/// Completes the migrated query that was in progress.
+/// The signature of the request covers the legacy encoding, so the nodes would reject it:
+/// the requests without a response time out and the responses are left unverified.
+fn finalize(mut query: Query) -> Query {
+    for result in query.response.iter_mut() {
+        let finalized = match result {
+            NodeResult::SentRequest(_) => NodeResult::Timeout(Box::new(result.clone())),
+            NodeResult::NodeResponse(response) => {
+                NodeResult::Unverified(response.clone(), MIGRATED.to_string())
+            }
+            _ => continue,
+        };
+        *result = finalized;
+    }
+    query
+}
@@ -0,0 +1,36 @@
+use crate::{
+    core::{error::StorageError, table::Table},
+    WriteSet,
+};
+use crypto::ed25519::public::PublicKey;
+use types::cluster::Reputation;
+
+pub const REPUTATION_TABLE_NAME: &str = "reputation-table";
+
+pub struct ReputationTable {
+    reputation: Table<PublicKey, Reputation>,
+}
+
+impl ReputationTable {
+    pub fn new(reputation: Table<PublicKey, Reputation>) -> Self {
+        Self { reputation }
+    }
+
+    /// Returns the reputation of the node, or the default one if the node has no tasks yet.
+    pub fn get(&self, key: &PublicKey) -> Result<Reputation, StorageError> {
+        Ok(self.reputation.get(key)?.unwrap_or_default())
+    }
+
+    pub fn put(
+        &self,
+        key: &PublicKey,
+        reputation: &Reputation,
+        ws: &mut WriteSet,
+    ) -> Result<(), StorageError> {
+        self.reputation.put(key, reputation, ws)
+    }
+
+    pub fn remove(&self, key: &PublicKey, ws: &mut WriteSet) -> Result<(), StorageError> {
+        self.reputation.delete(key, ws)
+    }
+}
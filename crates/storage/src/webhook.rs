@@ -0,0 +1,62 @@
+use crate::{
+    core::{error::StorageError, table::Table},
+    WriteSet,
+};
+use types::ai::{query::QueryId, webhook::WebhookDelivery};
+
+pub const WEBHOOK_TABLE_NAME: &str = "webhook-table";
+pub const WEBHOOK_PENDING_TABLE_NAME: &str = "webhook-pending";
+
+/// Delivery log of the callbacks, by query.
+pub struct WebhookTable {
+    deliveries: Table<QueryId, Vec<WebhookDelivery>>,
+    /// Callback URLs of the queries not delivered yet.
+    pending: Table<QueryId, String>,
+}
+
+impl WebhookTable {
+    pub fn new(
+        deliveries: Table<QueryId, Vec<WebhookDelivery>>,
+        pending: Table<QueryId, String>,
+    ) -> Self {
+        Self {
+            deliveries,
+            pending,
+        }
+    }
+
+    pub fn get(&self, id: &QueryId) -> Result<Vec<WebhookDelivery>, StorageError> {
+        Ok(self.deliveries.get(id)?.unwrap_or_default())
+    }
+
+    /// Appends the attempt to the log of the query.
+    pub fn push(
+        &self,
+        id: &QueryId,
+        delivery: WebhookDelivery,
+        ws: &mut WriteSet,
+    ) -> Result<(), StorageError> {
+        let mut deliveries = self.get(id)?;
+        deliveries.push(delivery);
+        self.deliveries.put(id, &deliveries, ws)
+    }
+
+    /// Marks the query to be delivered to the callback URL.
+    pub fn put_pending(
+        &self,
+        id: &QueryId,
+        url: &str,
+        ws: &mut WriteSet,
+    ) -> Result<(), StorageError> {
+        self.pending.put(id, &url.to_owned(), ws)
+    }
+
+    pub fn remove_pending(&self, id: &QueryId, ws: &mut WriteSet) -> Result<(), StorageError> {
+        self.pending.delete(id, ws)
+    }
+
+    /// Returns the queries not delivered yet with their callback URLs.
+    pub fn pending(&self) -> Result<Vec<(QueryId, String)>, StorageError> {
+        self.pending.iter(None)?.collect()
+    }
+}
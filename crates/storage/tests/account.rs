@@ -0,0 +1,217 @@
+mod common;
+
+use crypto::{
+    ed25519::{private::PrivateKey, public::PublicKey},
+    hash::sha3,
+};
+use rand::Rng as _;
+use std::sync::Arc;
+use storage::{ledger::Transfer, EveStorage, StorageError, WriteSet};
+use types::account::{Escrow, TransactionKind};
+
+fn transfer(account: PublicKey, kind: TransactionKind, amount: u64) -> Transfer {
+    Transfer {
+        account,
+        kind,
+        amount,
+        query: None,
+    }
+}
+
+fn balance(store: &EveStorage, key: &PublicKey) -> u64 {
+    store
+        .account_table
+        .get(key)
+        .unwrap()
+        .unwrap_or_default()
+        .balance
+}
+
+#[test]
+pub fn test_ledger() {
+    let (_, store) = common::test_storage();
+    let key = PrivateKey::generate().public_key();
+    let node = PrivateKey::generate().public_key();
+    let id = sha3(&1);
+
+    store
+        .ledger
+        .apply(
+            &[transfer(key, TransactionKind::Airdrop, 100)],
+            WriteSet::default(),
+        )
+        .unwrap();
+
+    let err = store
+        .ledger
+        .apply(
+            &[transfer(key, TransactionKind::Charge, 101)],
+            WriteSet::default(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        StorageError::InsufficientBalance {
+            balance: 100,
+            required: 101
+        }
+    ));
+
+    let err = store
+        .ledger
+        .apply(
+            &[transfer(key, TransactionKind::Refund, u64::MAX)],
+            WriteSet::default(),
+        )
+        .unwrap_err();
+    assert!(matches!(err, StorageError::BalanceOverflow));
+
+    // the failed transfers are not applied partially
+    let err = store
+        .ledger
+        .apply(
+            &[
+                transfer(node, TransactionKind::Payout, 10),
+                transfer(key, TransactionKind::Charge, 200),
+            ],
+            WriteSet::default(),
+        )
+        .unwrap_err();
+    assert!(matches!(err, StorageError::InsufficientBalance { .. }));
+    assert_eq!(balance(&store, &node), 0);
+    assert!(store.ledger.transactions(&node, 10, 0).unwrap().is_empty());
+
+    store
+        .ledger
+        .apply(
+            &[
+                Transfer {
+                    query: Some(id),
+                    ..transfer(key, TransactionKind::Charge, 60)
+                },
+                Transfer {
+                    query: Some(id),
+                    ..transfer(node, TransactionKind::Payout, 50)
+                },
+                Transfer {
+                    query: Some(id),
+                    ..transfer(key, TransactionKind::Refund, 10)
+                },
+            ],
+            WriteSet::default(),
+        )
+        .unwrap();
+    assert_eq!(balance(&store, &key), 50);
+    assert_eq!(balance(&store, &node), 50);
+
+    let history = store.ledger.transactions(&key, 10, 0).unwrap();
+    let history = history
+        .iter()
+        .map(|tx| (tx.kind, tx.amount, tx.balance, tx.query))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        history,
+        vec![
+            (TransactionKind::Airdrop, 100, 100, None),
+            (TransactionKind::Charge, 60, 40, Some(id)),
+            (TransactionKind::Refund, 10, 50, Some(id)),
+        ]
+    );
+
+    let page = store.ledger.transactions(&key, 1, 1).unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].kind, TransactionKind::Charge);
+    assert!(store.ledger.transactions(&key, 10, 3).unwrap().is_empty());
+}
+
+/// Concurrent transfers between the accounts must not create or lose funds.
+#[test]
+pub fn test_ledger_concurrency() {
+    const ACCOUNTS: usize = 8;
+    const THREADS: usize = 8;
+    const TRANSFERS: usize = 200;
+    const INITIAL: u64 = 1_000;
+
+    let (_, store) = common::test_storage();
+    let store = Arc::new(store);
+    let keys = (0..ACCOUNTS)
+        .map(|_| PrivateKey::generate().public_key())
+        .collect::<Vec<_>>();
+    for key in &keys {
+        store
+            .ledger
+            .apply(
+                &[transfer(*key, TransactionKind::Airdrop, INITIAL)],
+                WriteSet::default(),
+            )
+            .unwrap();
+    }
+
+    let threads = (0..THREADS)
+        .map(|_| {
+            let store = store.clone();
+            let keys = keys.clone();
+            std::thread::spawn(move || {
+                let mut rng = rand::thread_rng();
+                for _ in 0..TRANSFERS {
+                    let from = keys[rng.gen_range(0..ACCOUNTS)];
+                    let to = keys[rng.gen_range(0..ACCOUNTS)];
+                    let amount = rng.gen_range(1..=INITIAL / 2);
+                    let result = store.ledger.apply(
+                        &[
+                            transfer(from, TransactionKind::Charge, amount),
+                            transfer(to, TransactionKind::Payout, amount),
+                        ],
+                        WriteSet::default(),
+                    );
+                    match result {
+                        Ok(()) | Err(StorageError::InsufficientBalance { .. }) => {}
+                        Err(err) => panic!("unexpected error: {err}"),
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let total: u64 = keys.iter().map(|key| balance(&store, key)).sum();
+    assert_eq!(total, INITIAL * ACCOUNTS as u64);
+
+    // the ledger of every account is consistent with its balance
+    for key in &keys {
+        let history = store.ledger.transactions(key, usize::MAX, 0).unwrap();
+        let mut expected = 0u64;
+        for tx in &history {
+            expected = if tx.kind.is_credit() {
+                expected + tx.amount
+            } else {
+                expected - tx.amount
+            };
+            assert_eq!(tx.balance, expected);
+        }
+        assert_eq!(expected, balance(&store, key));
+    }
+}
+
+#[test]
+pub fn test_escrow() {
+    let (_, store) = common::test_storage();
+    let id = sha3(&1);
+    let escrow = Escrow {
+        payer: PrivateKey::generate().public_key(),
+        amount: 100,
+    };
+    assert_eq!(store.escrow_table.get(&id).unwrap(), None);
+
+    let mut ws = storage::WriteSet::default();
+    store.escrow_table.put(&id, &escrow, &mut ws).unwrap();
+    store.commit(ws).unwrap();
+    assert_eq!(store.escrow_table.get(&id).unwrap(), Some(escrow));
+
+    let mut ws = storage::WriteSet::default();
+    store.escrow_table.remove(&id, &mut ws).unwrap();
+    store.commit(ws).unwrap();
+    assert_eq!(store.escrow_table.get(&id).unwrap(), None);
+}
@@ -0,0 +1,145 @@
+use bincode::Options as _;
+use crypto::{ed25519::private::PrivateKey, hash::sha3};
+use rocksdb::{ColumnFamilyDescriptor, Options, WriteBatch, DB};
+use storage::{
+    query::{QUERY_IN_PROGRESS, QUERY_TABLE_NAME},
+    EveStorage,
+};
+use tempdir::TempDir;
+use types::ai::{legacy, query::NodeResult};
+
+#[test]
+pub fn test_migrate_legacy_queries() {
+    let tmp_dir = TempDir::new("rocksdb").unwrap();
+    let alice = PrivateKey::generate();
+    let node = PrivateKey::generate();
+    let inspector = PrivateKey::generate();
+
+    let request = legacy_request("hello", &alice);
+    let response = legacy_response(&request, &node);
+    let complete = legacy::Query {
+        id: sha3(&(1u64, &request)),
+        sequence: 1,
+        request,
+        response: vec![legacy::NodeResult::Verified(Box::new(
+            legacy::SignedVerificationResult {
+                result: legacy::VerificationResult {
+                    material: response,
+                    inspector: inspector.public_key(),
+                    relevance: 90.try_into().unwrap(),
+                    description: "good".to_string(),
+                },
+                signature: inspector.sign(b"verification"),
+            },
+        ))],
+    };
+    let request = legacy_request("world", &alice);
+    let in_progress = legacy::Query {
+        id: sha3(&(2u64, &request)),
+        sequence: 2,
+        request: request.clone(),
+        response: vec![
+            legacy::NodeResult::SentRequest(inspector.public_key()),
+            legacy::NodeResult::NodeResponse(legacy_response(&request, &node)),
+        ],
+    };
+
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf_descriptors(
+            &opts,
+            tmp_dir.path(),
+            vec![
+                ColumnFamilyDescriptor::new(QUERY_TABLE_NAME, Options::default()),
+                ColumnFamilyDescriptor::new(QUERY_IN_PROGRESS, Options::default()),
+            ],
+        )
+        .unwrap();
+        let key_options = bincode::DefaultOptions::new().with_big_endian();
+        let value_options = bincode::DefaultOptions::new();
+        let mut batch = WriteBatch::default();
+        for query in [&complete, &in_progress] {
+            batch.put_cf(
+                db.cf_handle(QUERY_TABLE_NAME).unwrap(),
+                key_options.serialize(&query.id).unwrap(),
+                value_options.serialize(query).unwrap(),
+            );
+        }
+        batch.put_cf(
+            db.cf_handle(QUERY_IN_PROGRESS).unwrap(),
+            key_options.serialize(&in_progress.id).unwrap(),
+            value_options.serialize(&in_progress.id).unwrap(),
+        );
+        db.write(batch).unwrap();
+    }
+
+    let store = EveStorage::new(tmp_dir.path(), &Default::default()).unwrap();
+    let query = store.query_table.get_query(&complete.id).unwrap().unwrap();
+    assert_eq!(query.request.query.message, "hello");
+    assert_eq!(query.request.signature(), &complete.request.signature);
+    assert!(query.answer.is_none());
+    let verified = query.best_verified().unwrap();
+    assert_eq!(verified.result.relevance.inner(), 90);
+    assert_eq!(verified.result.material.node_response.response, "answer");
+    assert!(verified.result.scores.is_empty());
+
+    let query = store
+        .query_table
+        .get_query(&in_progress.id)
+        .unwrap()
+        .unwrap();
+    assert!(query.is_complete());
+    assert!(matches!(&query.response[0], NodeResult::Timeout(result) if result.is_sent_request()));
+    assert!(query.response[1].is_unverified());
+    assert!(store
+        .query_table
+        .get_in_progress_ids(10, 0)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        store
+            .query_table
+            .users_query_ids(&alice.public_key(), 10, 0)
+            .unwrap(),
+        vec![complete.id, in_progress.id]
+    );
+    drop(store);
+
+    let store = EveStorage::new(tmp_dir.path(), &Default::default()).unwrap();
+    assert_eq!(
+        store.query_table.get_query(&in_progress.id).unwrap(),
+        Some(query)
+    );
+}
+
+fn legacy_request(msg: &str, key: &PrivateKey) -> legacy::SignedAiRequest {
+    let query = legacy::AiRequest {
+        timestamp: 1,
+        seed: 0,
+        message: msg.to_string(),
+        history: vec![],
+        pubkey: key.public_key(),
+    };
+    let signature = key.sign(&bincode::serialize(&query).unwrap());
+    legacy::SignedAiRequest { query, signature }
+}
+
+fn legacy_response(
+    request: &legacy::SignedAiRequest,
+    key: &PrivateKey,
+) -> legacy::SignedAiResponse {
+    let node_response = legacy::AiResponse {
+        timestamp: 2,
+        response: "answer".to_string(),
+        pubkey: key.public_key(),
+        request_signature: request.signature.clone(),
+        cost: 10,
+    };
+    let signature = key.sign(&bincode::serialize(&node_response).unwrap());
+    legacy::SignedAiResponse {
+        node_response,
+        signature,
+    }
+}
@@ -156,5 +156,6 @@ fn test_query(sequence: u64, msg: &str, key: &PrivateKey) -> Query {
         request,
         response: vec![],
         sequence,
+        answer: None,
     }
 }
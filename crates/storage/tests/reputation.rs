@@ -0,0 +1,53 @@
+mod common;
+
+use crypto::ed25519::private::PrivateKey;
+use types::cluster::{Reputation, TaskOutcome};
+
+#[test]
+pub fn test_reputation() {
+    let (_, store) = common::test_storage();
+
+    let good = PrivateKey::generate().public_key();
+    let bad = PrivateKey::generate().public_key();
+    assert_eq!(
+        store.reputation_table.get(&good).unwrap(),
+        Reputation::default()
+    );
+
+    let mut good_reputation = store.reputation_table.get(&good).unwrap();
+    let mut bad_reputation = store.reputation_table.get(&bad).unwrap();
+    for _ in 0..10 {
+        good_reputation.record(TaskOutcome::Verified {
+            relevance: 90,
+            latency: 5.0,
+        });
+        bad_reputation.record(TaskOutcome::Timeout);
+        bad_reputation.record(TaskOutcome::Error);
+    }
+
+    let mut ws = storage::WriteSet::default();
+    store
+        .reputation_table
+        .put(&good, &good_reputation, &mut ws)
+        .unwrap();
+    store
+        .reputation_table
+        .put(&bad, &bad_reputation, &mut ws)
+        .unwrap();
+    store.commit(ws).unwrap();
+
+    let good_reputation = store.reputation_table.get(&good).unwrap();
+    let bad_reputation = store.reputation_table.get(&bad).unwrap();
+    assert_eq!(good_reputation.tasks, 10);
+    assert_eq!(bad_reputation.tasks, 20);
+    assert!(good_reputation.weight() > Reputation::default().weight());
+    assert!(bad_reputation.weight() < Reputation::default().weight());
+
+    let mut ws = storage::WriteSet::default();
+    store.reputation_table.remove(&bad, &mut ws).unwrap();
+    store.commit(ws).unwrap();
+    assert_eq!(
+        store.reputation_table.get(&bad).unwrap(),
+        Reputation::default()
+    );
+}
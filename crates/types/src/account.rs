@@ -1,6 +1,47 @@
+use crate::ai::query::QueryId;
+use crypto::ed25519::public::PublicKey;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Default)]
 pub struct EveAccount {
     pub balance: u64,
 }
+
+/// Funds reserved from the payer's balance for a query in progress.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Escrow {
+    pub payer: PublicKey,
+    /// The amount that is not paid to the nodes yet.
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    Airdrop,
+    /// The query cost is reserved from the user's balance.
+    Charge,
+    /// The node is paid for its response.
+    Payout,
+    /// The unspent reservation is returned to the user.
+    Refund,
+}
+
+impl TransactionKind {
+    /// Whether the transaction increases the balance.
+    pub fn is_credit(&self) -> bool {
+        !matches!(self, TransactionKind::Charge)
+    }
+}
+
+/// Record of the account ledger.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Transaction {
+    pub kind: TransactionKind,
+    pub amount: u64,
+    /// The balance after the transaction.
+    pub balance: u64,
+    pub query: Option<QueryId>,
+    /// Timestamp of the transaction in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
@@ -0,0 +1,38 @@
+use crypto::ed25519::public::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// How the final answer is chosen from the verified node responses.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationStrategy {
+    /// The response with the highest relevance.
+    #[default]
+    BestOf,
+    /// The response most of the others agree with, by embedding similarity.
+    Majority,
+    /// The winner of pairwise comparisons judged by the verifier.
+    Pairwise,
+}
+
+impl Display for AggregationStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregationStrategy::BestOf => write!(f, "best_of"),
+            AggregationStrategy::Majority => write!(f, "majority"),
+            AggregationStrategy::Pairwise => write!(f, "pairwise"),
+        }
+    }
+}
+
+/// Canonical answer to the query.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FinalAnswer {
+    /// Strategy that produced the answer.
+    pub strategy: AggregationStrategy,
+    /// Node whose response was chosen.
+    pub node: PublicKey,
+    pub content: String,
+    /// Nodes whose responses agree with the chosen one, including its author.
+    pub supporters: Vec<PublicKey>,
+}
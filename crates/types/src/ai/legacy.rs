@@ -0,0 +1,149 @@
+//! Formats of the queries stored by the first release, before the format had a version.
+//! They are only kept to migrate the stored queries:
+//! the nodes and the orchestrator of the first release can't talk to the current ones.
+
+use super::{query, request, response, verification};
+use crate::{ai::request::History, percent::Percent};
+use crypto::ed25519::{public::PublicKey, signature::Signature};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Query {
+    pub id: query::QueryId,
+    pub sequence: u64,
+    pub request: SignedAiRequest,
+    pub response: Vec<NodeResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NodeResult {
+    SentRequest(PublicKey),
+    Timeout(Box<NodeResult>),
+    NodeResponse(SignedAiResponse),
+    Error(PublicKey, String),
+    Verified(Box<SignedVerificationResult>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedAiRequest {
+    pub query: AiRequest,
+    pub signature: Signature,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AiRequest {
+    pub timestamp: u64,
+    pub seed: i32,
+    pub message: String,
+    pub history: Vec<History>,
+    pub pubkey: PublicKey,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedAiResponse {
+    pub node_response: AiResponse,
+    pub signature: Signature,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AiResponse {
+    pub timestamp: u64,
+    pub response: String,
+    pub pubkey: PublicKey,
+    pub request_signature: Signature,
+    pub cost: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedVerificationResult {
+    pub result: VerificationResult,
+    pub signature: Signature,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerificationResult {
+    pub material: SignedAiResponse,
+    pub inspector: PublicKey,
+    pub relevance: Percent,
+    pub description: String,
+}
+
+/// The signatures are kept as is: they are over the legacy encoding
+/// and can't be verified against the migrated values.
+impl From<Query> for query::Query {
+    fn from(value: Query) -> Self {
+        Self {
+            id: value.id,
+            sequence: value.sequence,
+            request: value.request.into(),
+            response: value.response.into_iter().map(Into::into).collect(),
+            answer: None,
+        }
+    }
+}
+
+impl From<NodeResult> for query::NodeResult {
+    fn from(value: NodeResult) -> Self {
+        match value {
+            NodeResult::SentRequest(key) => Self::SentRequest(key),
+            NodeResult::Timeout(result) => Self::Timeout(Box::new((*result).into())),
+            NodeResult::NodeResponse(response) => Self::NodeResponse(response.into()),
+            NodeResult::Error(key, err) => Self::Error(key, err),
+            NodeResult::Verified(result) => Self::Verified(Box::new((*result).into())),
+        }
+    }
+}
+
+impl From<SignedAiRequest> for request::SignedAiRequest {
+    fn from(value: SignedAiRequest) -> Self {
+        let request = value.query;
+        Self {
+            query: request::AiRequest {
+                timestamp: request.timestamp,
+                seed: request.seed,
+                message: request.message,
+                history: request.history,
+                pubkey: request.pubkey,
+                options: Default::default(),
+                sealed: None,
+            },
+            signature: value.signature,
+        }
+    }
+}
+
+impl From<SignedAiResponse> for response::SignedAiResponse {
+    fn from(value: SignedAiResponse) -> Self {
+        let response = value.node_response;
+        Self {
+            node_response: response::AiResponse {
+                timestamp: response.timestamp,
+                response: response.response,
+                pubkey: response.pubkey,
+                request_signature: response.request_signature,
+                cost: response.cost,
+                reasoning: None,
+                sealed: None,
+                truncation: None,
+            },
+            signature: value.signature,
+        }
+    }
+}
+
+impl From<SignedVerificationResult> for verification::SignedVerificationResult {
+    fn from(value: SignedVerificationResult) -> Self {
+        let result = value.result;
+        Self {
+            result: verification::VerificationResult {
+                material: result.material.into(),
+                inspector: result.inspector,
+                relevance: result.relevance,
+                description: result.description,
+                scores: Vec::new(),
+                verdicts: Vec::new(),
+            },
+            signature: value.signature,
+        }
+    }
+}
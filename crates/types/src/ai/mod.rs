@@ -1,5 +1,9 @@
+pub mod aggregation;
+pub mod legacy;
 pub mod models;
 pub mod query;
 pub mod request;
 pub mod response;
+pub mod stream;
 pub mod verification;
+pub mod webhook;
@@ -65,6 +65,21 @@ impl AiWebModel {
     pub fn url(&self) -> Url {
         self.into()
     }
+
+    /// Name of the downloaded GGUF file.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::DeepseekR1_1_5b => "DeepSeek-R1-Distill-Qwen-1.5B-Q2_K.gguf",
+        }
+    }
+
+    /// SHA-256 of the downloaded GGUF file in hex, `None` until the checksum of
+    /// the published file is pinned here.
+    pub fn sha256(&self) -> Option<&'static str> {
+        match self {
+            Self::DeepseekR1_1_5b => None,
+        }
+    }
 }
 
 impl Display for AiWebModel {
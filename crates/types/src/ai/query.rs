@@ -1,22 +1,28 @@
 use super::{
+    aggregation::FinalAnswer,
     request::{History, Role, SignedAiRequest},
     response::SignedAiResponse,
     verification::SignedVerificationResult,
 };
 use crate::percent::Percent;
 use crypto::{
-    ed25519::public::PublicKey,
+    ed25519::{private::PrivateKey, public::PublicKey},
     hash::{sha3, Hash},
 };
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Maximum number of characters of the message in the query summary.
+const PREVIEW_LEN: usize = 80;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Query {
     pub id: QueryId,
     pub sequence: u64,
     pub request: SignedAiRequest,
     pub response: Vec<NodeResult>,
+    /// Answer aggregated from the verified responses, once the query is complete.
+    pub answer: Option<FinalAnswer>,
 }
 
 pub fn query_id(sequence: u64, request: &SignedAiRequest) -> QueryId {
@@ -30,36 +36,76 @@ impl Query {
             sequence,
             request,
             response: Vec::new(),
+            answer: None,
         }
     }
 
     pub fn is_complete(&self) -> bool {
         self.response.iter().all(|result| match result {
             NodeResult::SentRequest(_) | NodeResult::NodeResponse(_) => false,
-            NodeResult::Verified(_) | NodeResult::Timeout(_) | NodeResult::Error(_, _) => true,
+            NodeResult::Verified(_)
+            | NodeResult::Timeout(_)
+            | NodeResult::Error(_, _)
+            | NodeResult::Unverified(_, _)
+            | NodeResult::Cancelled(_) => true,
         })
     }
 
-    pub fn as_history(&self) -> Vec<History> {
-        let mut history = self.request.query.as_history();
-        let node_response = self
-            .response
+    /// Returns the verified response with the highest relevance.
+    pub fn best_verified(&self) -> Option<&SignedVerificationResult> {
+        self.response
             .iter()
-            .filter_map(|v| match v {
-                NodeResult::Verified(v) => Some((
-                    v.result.relevance.clone(),
-                    v.result.material.node_response.response.clone(),
-                )),
-                _ => None,
-            })
-            .fold((Percent::zero(), None), |b, value| {
-                if b.1.is_none() || b.0 < value.0 {
-                    (value.0, Some(value.1))
-                } else {
-                    b
-                }
+            .filter_map(NodeResult::verified)
+            .fold(None, |best, value| match best {
+                Some(best) if best.result.relevance >= value.result.relevance => Some(best),
+                _ => Some(value),
             })
-            .1;
+    }
+
+    /// Removes the reasoning of the models from the responses.
+    /// The signatures of the responses can't be verified without it.
+    pub fn hide_reasoning(&mut self) {
+        self.response
+            .iter_mut()
+            .for_each(NodeResult::hide_reasoning);
+    }
+
+    /// Decrypts the confidential request and the responses with the key of the requester.
+    /// The responses the key can't open are left sealed.
+    /// The signatures of the request and the responses can't be verified after that.
+    pub fn open(&mut self, key: &PrivateKey) -> eyre::Result<()> {
+        self.request.query.open(key)?;
+        for result in self.response.iter_mut() {
+            if let Some(material) = result.material_mut() {
+                let _ = material.node_response.open(key);
+            }
+        }
+
+        let Some(answer) = &mut self.answer else {
+            return Ok(());
+        };
+        if answer.content.is_empty() {
+            if let Some(material) = self
+                .response
+                .iter_mut()
+                .filter_map(NodeResult::material_mut)
+                .find(|material| material.node_key() == answer.node)
+            {
+                answer.content = material.node_response.response.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /// The reasoning of the models is not a part of the history.
+    pub fn as_history(&self) -> Vec<History> {
+        let mut history = self.request.query.as_history();
+        let node_response = match &self.answer {
+            Some(answer) => Some(answer.content.clone()),
+            None => self
+                .best_verified()
+                .map(|v| v.result.material.node_response.response.clone()),
+        };
         if let Some(node_response) = node_response {
             history.push(History {
                 content: node_response,
@@ -71,6 +117,40 @@ impl Query {
     }
 }
 
+/// Short description of a query for the listings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuerySummary {
+    pub id: QueryId,
+    pub sequence: u64,
+    pub timestamp: u64,
+    /// Beginning of the user message.
+    pub message: String,
+    pub complete: bool,
+    /// Relevance of the best verified response.
+    pub relevance: Option<Percent>,
+}
+
+impl From<&Query> for QuerySummary {
+    fn from(query: &Query) -> Self {
+        Self {
+            id: query.id,
+            sequence: query.sequence,
+            timestamp: query.request.query.timestamp,
+            message: query
+                .request
+                .query
+                .message
+                .chars()
+                .take(PREVIEW_LEN)
+                .collect(),
+            complete: query.is_complete(),
+            relevance: query
+                .best_verified()
+                .map(|verified| verified.result.relevance.clone()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum NodeResult {
     SentRequest(PublicKey),
@@ -78,6 +158,11 @@ pub enum NodeResult {
     NodeResponse(SignedAiResponse),
     Error(PublicKey, String),
     Verified(Box<SignedVerificationResult>),
+    /// The response the orchestrator failed to judge, with the error.
+    Unverified(SignedAiResponse, String),
+    /// The node was generating the response when the user cancelled the query,
+    /// or when the hedged requests had completed it.
+    Cancelled(PublicKey),
 }
 
 impl NodeResult {
@@ -93,6 +178,14 @@ impl NodeResult {
         matches!(self, NodeResult::Verified(_))
     }
 
+    pub fn is_unverified(&self) -> bool {
+        matches!(self, NodeResult::Unverified(_, _))
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, NodeResult::Cancelled(_))
+    }
+
     pub fn verified(&self) -> Option<&SignedVerificationResult> {
         match self {
             NodeResult::Verified(result) => Some(result),
@@ -107,6 +200,8 @@ impl NodeResult {
             NodeResult::Verified(approval) => approval.result.material.node_response.pubkey,
             NodeResult::Timeout(inner) => inner.node_key(),
             NodeResult::Error(public_key, _) => *public_key,
+            NodeResult::Unverified(response, _) => response.node_response.pubkey,
+            NodeResult::Cancelled(public_key) => *public_key,
         }
     }
 
@@ -125,6 +220,29 @@ impl NodeResult {
         }
     }
 
+    /// The response of the node, if any.
+    fn material_mut(&mut self) -> Option<&mut SignedAiResponse> {
+        match self {
+            NodeResult::NodeResponse(response) | NodeResult::Unverified(response, _) => {
+                Some(response)
+            }
+            NodeResult::Verified(result) => Some(&mut result.result.material),
+            NodeResult::Timeout(inner) => inner.material_mut(),
+            NodeResult::SentRequest(_) | NodeResult::Error(_, _) | NodeResult::Cancelled(_) => None,
+        }
+    }
+
+    fn hide_reasoning(&mut self) {
+        match self {
+            NodeResult::NodeResponse(response) | NodeResult::Unverified(response, _) => {
+                response.node_response.reasoning = None
+            }
+            NodeResult::Verified(result) => result.result.material.node_response.reasoning = None,
+            NodeResult::Timeout(inner) => inner.hide_reasoning(),
+            NodeResult::SentRequest(_) | NodeResult::Error(_, _) | NodeResult::Cancelled(_) => {}
+        }
+    }
+
     pub fn as_verified_response(&self) -> Option<&SignedVerificationResult> {
         match self {
             NodeResult::Verified(response) => Some(response),
@@ -150,16 +268,27 @@ impl Ord for NodeResult {
                 NodeResult::Error(..) => Ordering::Less,
                 _ => Ordering::Greater,
             },
-            NodeResult::Timeout(..) => match other {
+            NodeResult::Cancelled(..) => match other {
                 NodeResult::Error(..) | NodeResult::SentRequest(..) => Ordering::Less,
                 _ => Ordering::Greater,
             },
-            NodeResult::NodeResponse(..) => match other {
-                NodeResult::Error(..) | NodeResult::SentRequest(..) | NodeResult::Timeout(..) => {
+            NodeResult::Timeout(..) => match other {
+                NodeResult::Error(..) | NodeResult::SentRequest(..) | NodeResult::Cancelled(..) => {
                     Ordering::Less
                 }
                 _ => Ordering::Greater,
             },
+            NodeResult::NodeResponse(..) => match other {
+                NodeResult::Error(..)
+                | NodeResult::SentRequest(..)
+                | NodeResult::Timeout(..)
+                | NodeResult::Cancelled(..) => Ordering::Less,
+                _ => Ordering::Greater,
+            },
+            NodeResult::Unverified(..) => match other {
+                NodeResult::Verified(..) => Ordering::Greater,
+                _ => Ordering::Less,
+            },
             NodeResult::Verified(a) => match other {
                 NodeResult::Verified(b) => a.result.relevance.cmp(&b.result.relevance),
                 _ => Ordering::Less,
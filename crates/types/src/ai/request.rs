@@ -1,13 +1,15 @@
-use super::verification::Verified;
-use crypto::ed25519::{private::PrivateKey, public::PublicKey, signature::Signature};
-use eyre::Result;
+use super::{query::QueryId, verification::Verified};
+use crypto::ed25519::{
+    private::PrivateKey, public::PublicKey, sealed::SealedMessage, signature::Signature,
+};
+use eyre::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SignedAiRequest {
     pub query: AiRequest,
-    signature: Signature,
+    pub(crate) signature: Signature,
 }
 
 impl SignedAiRequest {
@@ -22,7 +24,7 @@ impl SignedAiRequest {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AiRequest {
     /// Timestamp of the request in seconds since the Unix epoch.
     pub timestamp: u64,
@@ -30,6 +32,21 @@ pub struct AiRequest {
     pub message: String,
     pub history: Vec<History>,
     pub pubkey: PublicKey,
+    #[serde(default)]
+    pub options: AiRequestOptions,
+    /// Message and history encrypted to the nodes allowed to answer,
+    /// `message` and `history` are empty then.
+    /// The responses are judged by the other recipients only,
+    /// the orchestrator can't read them and leaves them unverified otherwise.
+    #[serde(default)]
+    pub sealed: Option<SealedMessage>,
+}
+
+/// Content of the sealed confidential request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct Prompt {
+    message: String,
+    history: Vec<History>,
 }
 
 impl AiRequest {
@@ -40,7 +57,52 @@ impl AiRequest {
             history,
             pubkey,
             seed: rand::random(),
+            options: AiRequestOptions::default(),
+            sealed: None,
+        }
+    }
+
+    /// Request readable only by the `nodes` and the requester,
+    /// the orchestrator sends it to the `nodes` only.
+    pub fn confidential(
+        message: String,
+        history: Vec<History>,
+        pubkey: PublicKey,
+        nodes: &[PublicKey],
+    ) -> Result<Self> {
+        if nodes.is_empty() {
+            bail!("No nodes to read the request");
+        }
+        let prompt = bincode::serialize(&Prompt { message, history })?;
+        let recipients = nodes
+            .iter()
+            .copied()
+            .filter(|node| *node != pubkey)
+            .chain([pubkey])
+            .collect::<Vec<_>>();
+        let mut request = Self::new(String::new(), vec![], pubkey);
+        request.sealed = Some(SealedMessage::seal(&prompt, &recipients)?);
+        Ok(request)
+    }
+
+    pub fn is_confidential(&self) -> bool {
+        self.sealed.is_some()
+    }
+
+    /// Decrypts the message and the history of the confidential request.
+    /// The signature of the request can't be verified after that.
+    pub fn open(&mut self, key: &PrivateKey) -> Result<()> {
+        if let Some(sealed) = &self.sealed {
+            let prompt: Prompt = bincode::deserialize(&sealed.open(key)?)?;
+            self.message = prompt.message;
+            self.history = prompt.history;
         }
+        Ok(())
+    }
+
+    pub fn with_options(mut self, options: AiRequestOptions) -> Self {
+        self.options = options;
+        self
     }
 
     pub fn sign(self, private_key: &PrivateKey) -> Result<SignedAiRequest> {
@@ -62,6 +124,118 @@ impl AiRequest {
     }
 }
 
+/// Request of the user to stop the query.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CancelRequest {
+    pub id: QueryId,
+    /// The key the query is signed with.
+    pub pubkey: PublicKey,
+}
+
+impl CancelRequest {
+    pub fn new(id: QueryId, pubkey: PublicKey) -> Self {
+        Self { id, pubkey }
+    }
+
+    pub fn sign(self, private_key: &PrivateKey) -> Result<SignedCancelRequest> {
+        let request = bincode::serialize(&self)?;
+        let signature = private_key.sign(&request);
+        Ok(SignedCancelRequest {
+            request: self,
+            signature,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedCancelRequest {
+    pub request: CancelRequest,
+    signature: Signature,
+}
+
+impl SignedCancelRequest {
+    pub fn verify(self) -> Result<Verified<SignedCancelRequest>> {
+        let request = bincode::serialize(&self.request)?;
+        self.request.pubkey.verify(&request, &self.signature)?;
+        Ok(Verified::new(self))
+    }
+}
+
+/// Header with the public key of the signed read challenge.
+pub const AUTH_PUBKEY_HEADER: &str = "x-eve-auth-pubkey";
+/// Header with the timestamp of the signed read challenge.
+pub const AUTH_TIMESTAMP_HEADER: &str = "x-eve-auth-timestamp";
+/// Header with the signature of the read challenge.
+pub const AUTH_SIGNATURE_HEADER: &str = "x-eve-auth-signature";
+
+/// Proof of the requester that the read of the path is requested by them.
+/// The challenge is sent in the `x-eve-auth-*` headers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReadChallenge {
+    /// Path of the requested resource, e.g. `/answer/<query_id>`.
+    pub path: String,
+    /// Timestamp of the challenge in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub pubkey: PublicKey,
+}
+
+impl ReadChallenge {
+    pub fn new(path: String, pubkey: PublicKey) -> Self {
+        Self {
+            path,
+            timestamp: now(),
+            pubkey,
+        }
+    }
+
+    pub fn sign(self, private_key: &PrivateKey) -> Result<SignedReadChallenge> {
+        let challenge = bincode::serialize(&self)?;
+        let signature = private_key.sign(&challenge);
+        Ok(SignedReadChallenge {
+            challenge: self,
+            signature,
+        })
+    }
+
+    /// Whether the challenge is more than `max_age` seconds away from now,
+    /// in either direction to tolerate the clock skew.
+    pub fn is_expired(&self, max_age: u64) -> bool {
+        now().abs_diff(self.timestamp) > max_age
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedReadChallenge {
+    pub challenge: ReadChallenge,
+    pub signature: Signature,
+}
+
+impl SignedReadChallenge {
+    pub fn verify(self) -> Result<Verified<SignedReadChallenge>> {
+        let challenge = bincode::serialize(&self.challenge)?;
+        self.challenge.pubkey.verify(&challenge, &self.signature)?;
+        Ok(Verified::new(self))
+    }
+}
+
+/// Generation settings requested by the user.
+/// Unset fields are chosen by the orchestrator and the nodes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AiRequestOptions {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    /// Maximum number of tokens in the answer.
+    pub max_tokens: Option<u32>,
+    /// Model family the answer is requested from, e.g. `deepseek-r1`.
+    pub model: Option<String>,
+    /// Minimum context window of the model in tokens.
+    pub min_context: Option<u64>,
+    /// Number of nodes the request is sent to.
+    pub replication: Option<u64>,
+    /// URL the completed query is posted to.
+    pub callback: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct History {
     pub content: String,
@@ -1,5 +1,7 @@
 use super::verification::Verified;
-use crypto::ed25519::{public::PublicKey, signature::Signature};
+use crypto::ed25519::{
+    private::PrivateKey, public::PublicKey, sealed::SealedMessage, signature::Signature,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -10,9 +12,60 @@ pub struct AiResponse {
     pub pubkey: PublicKey,
     pub request_signature: Signature,
     pub cost: u64,
+    /// Reasoning of the model preceding the response, if the model reasons.
+    #[serde(default)]
+    pub reasoning: Option<String>,
+    /// Response and reasoning encrypted to the requester and the inspectors
+    /// of the confidential request, `response` and `reasoning` are empty then.
+    #[serde(default)]
+    pub sealed: Option<SealedMessage>,
+    /// Set if the history of the request was cut to fit the context of the model.
+    #[serde(default)]
+    pub truncation: Option<Truncation>,
+}
+
+/// How the history of a request was cut to fit the context of the model.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Truncation {
+    /// Number of the oldest turns removed from the history.
+    pub removed_turns: u32,
+    /// The removed turns were replaced with their summary.
+    pub summarized: bool,
+    /// Approximate number of tokens of the request before the cut.
+    pub tokens_before: u64,
+    /// Approximate number of tokens of the request after the cut.
+    pub tokens_after: u64,
+}
+
+/// Content of the sealed response.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct Reply {
+    response: String,
+    reasoning: Option<String>,
 }
 
 impl AiResponse {
+    /// Encrypts the response and the reasoning to the `recipients`.
+    pub fn seal(&mut self, recipients: &[PublicKey]) -> Result<(), eyre::Error> {
+        let reply = bincode::serialize(&Reply {
+            response: std::mem::take(&mut self.response),
+            reasoning: self.reasoning.take(),
+        })?;
+        self.sealed = Some(SealedMessage::seal(&reply, recipients)?);
+        Ok(())
+    }
+
+    /// Decrypts the sealed response.
+    /// The signature of the response can't be verified after that.
+    pub fn open(&mut self, key: &PrivateKey) -> Result<(), eyre::Error> {
+        if let Some(sealed) = &self.sealed {
+            let reply: Reply = bincode::deserialize(&sealed.open(key)?)?;
+            self.response = reply.response;
+            self.reasoning = reply.reasoning;
+        }
+        Ok(())
+    }
+
     pub fn sign(
         &self,
         private_key: &crypto::ed25519::private::PrivateKey,
@@ -29,7 +82,7 @@ impl AiResponse {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SignedAiResponse {
     pub node_response: AiResponse,
-    signature: Signature,
+    pub(crate) signature: Signature,
 }
 
 impl SignedAiResponse {
@@ -0,0 +1,65 @@
+use super::query::{NodeResult, Query, QueryId};
+use crypto::ed25519::public::PublicKey;
+use serde::{Deserialize, Serialize};
+
+/// Event of the answer stream.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum StreamEvent {
+    /// Part of the answer generated by the node.
+    /// `offset` is the position of the text in the answer, in bytes.
+    Chunk {
+        node: PublicKey,
+        offset: u64,
+        text: String,
+    },
+    /// The current state of the query.
+    Query(Query),
+}
+
+impl StreamEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamEvent::Chunk { .. } => "chunk",
+            StreamEvent::Query(_) => "query",
+        }
+    }
+}
+
+/// Event of the feed of the query state transitions.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum QueryEvent {
+    /// The state of a node in the query has changed.
+    Transition {
+        id: QueryId,
+        requester: PublicKey,
+        result: NodeResult,
+    },
+    /// The processing of the query is finished, its final state is stored.
+    /// Sent even if the task failed and the query is not complete.
+    Complete { id: QueryId, requester: PublicKey },
+}
+
+impl QueryEvent {
+    pub fn id(&self) -> &QueryId {
+        match self {
+            QueryEvent::Transition { id, .. } | QueryEvent::Complete { id, .. } => id,
+        }
+    }
+
+    pub fn requester(&self) -> &PublicKey {
+        match self {
+            QueryEvent::Transition { requester, .. } | QueryEvent::Complete { requester, .. } => {
+                requester
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueryEvent::Transition { .. } => "transition",
+            QueryEvent::Complete { .. } => "complete",
+        }
+    }
+}
@@ -26,7 +26,7 @@ impl<T> AsRef<T> for Verified<T> {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SignedVerificationResult {
     pub result: VerificationResult,
-    signature: Signature,
+    pub(crate) signature: Signature,
 }
 
 impl SignedVerificationResult {
@@ -43,9 +43,63 @@ pub struct VerificationResult {
     pub inspector: PublicKey,
     pub relevance: Percent,
     pub description: String,
+    /// Scores of the individual criteria the relevance is combined from.
+    #[serde(default)]
+    pub scores: Vec<CriterionScore>,
+    /// Verdicts of the other inspectors the relevance is combined from.
+    #[serde(default)]
+    pub verdicts: Vec<InspectorVerdict>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CriterionScore {
+    pub criterion: String,
+    pub score: Percent,
+}
+
+/// Result of an inspector on the material of the enclosing verification result.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InspectorVerdict {
+    pub inspector: PublicKey,
+    pub relevance: Percent,
+    pub description: String,
+    pub scores: Vec<CriterionScore>,
+    signature: Signature,
+}
+
+impl InspectorVerdict {
+    /// Checks the signature of the inspector over its result on the material.
+    pub fn verify(&self, material: &SignedAiResponse) -> Result<()> {
+        SignedVerificationResult {
+            result: VerificationResult {
+                material: material.clone(),
+                inspector: self.inspector,
+                relevance: self.relevance.clone(),
+                description: self.description.clone(),
+                scores: self.scores.clone(),
+                verdicts: vec![],
+            },
+            signature: self.signature.clone(),
+        }
+        .verify()
+    }
+}
+
+impl From<SignedVerificationResult> for InspectorVerdict {
+    /// The verdicts of the result are dropped: an inspector judges the material alone.
+    fn from(value: SignedVerificationResult) -> Self {
+        Self {
+            inspector: value.result.inspector,
+            relevance: value.result.relevance,
+            description: value.result.description,
+            scores: value.result.scores,
+            signature: value.signature,
+        }
+    }
 }
 
 impl VerificationResult {
+    #[allow(clippy::result_large_err)]
     pub fn sign(
         self,
         private_key: &PrivateKey,
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Header with the hex ed25519 signature of the body by the orchestrator key.
+///
+/// It is used in place of an HMAC: the orchestrator key is not a shared secret, so
+/// the receiver verifies the signature of the raw body with the public key of the
+/// orchestrator, published in the cluster info.
+pub const SIGNATURE_HEADER: &str = "x-eve-signature";
+/// Header with the id of the delivered query.
+pub const QUERY_HEADER: &str = "x-eve-query";
+
+/// An attempt to deliver the completed query to the callback URL of the requester.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookDelivery {
+    pub attempt: u32,
+    pub timestamp: u64,
+    /// HTTP status of the response, if any.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl WebhookDelivery {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
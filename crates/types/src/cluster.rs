@@ -1,10 +1,16 @@
+use crate::ai::request::AiRequestOptions;
 use crypto::ed25519::{private::PrivateKey, public::PublicKey};
 use eyre::Error;
 use multiaddr::{multihash::Multihash, Multiaddr, PeerId};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::{collections::HashMap, str::FromStr};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+/// Weight of the node with the worst reputation, so that it still gets a chance to recover.
+const MIN_WEIGHT: f64 = 0.05;
+/// Weight of the last result in the moving averages of the reputation.
+const REPUTATION_SMOOTHING: f64 = 0.1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Node {
     pub key: PublicKey,
     #[serde(serialize_with = "serialize_peer")]
@@ -12,6 +18,11 @@ pub struct Node {
     pub peer_id: PeerId,
     pub connected: bool,
     pub address: Option<Multiaddr>,
+    #[serde(default)]
+    pub reputation: Reputation,
+    /// Reported by the node after the connection.
+    #[serde(default)]
+    pub capabilities: Option<Capabilities>,
 }
 
 impl Node {
@@ -21,6 +32,8 @@ impl Node {
             peer_id,
             connected: false,
             address,
+            reputation: Reputation::default(),
+            capabilities: None,
         }
     }
 
@@ -33,6 +46,147 @@ impl Node {
     }
 }
 
+/// What the node is able to serve.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Name of the model, e.g. `deepseek-r1:7b`.
+    pub model: String,
+    /// Context window of the model in tokens.
+    pub context_length: u64,
+    /// Number of requests the node processes in parallel.
+    pub max_jobs: u32,
+    pub hardware: HardwareClass,
+}
+
+impl Capabilities {
+    /// Whether the node can answer the request with the given options.
+    pub fn supports(&self, options: &AiRequestOptions) -> bool {
+        let model = options
+            .model
+            .as_ref()
+            .map_or(true, |model| self.serves_model(model));
+        let context = options
+            .min_context
+            .map_or(true, |min_context| self.context_length >= min_context);
+        model && context
+    }
+
+    /// Matches either the full model name or its family, i.e. the name without the tag.
+    fn serves_model(&self, model: &str) -> bool {
+        let family = self.model.split(':').next().unwrap_or_default();
+        self.model.eq_ignore_ascii_case(model) || family.eq_ignore_ascii_case(model)
+    }
+}
+
+/// Current load of the node, reported when it changes.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LoadReport {
+    /// Requests being processed, including the queued ones.
+    pub in_flight: u32,
+    /// Requests waiting for a free slot of the model.
+    pub queued: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HardwareClass {
+    #[default]
+    Unknown,
+    Cpu,
+    Gpu,
+    Browser,
+}
+
+/// Reputation of the node, based on the results of its previous tasks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Reputation {
+    /// Moving average of the verified relevance, from 0 to 100.
+    pub relevance: f64,
+    /// Moving average of the timed out tasks share, from 0 to 1.
+    pub timeout_rate: f64,
+    /// Moving average of the failed tasks share, from 0 to 1.
+    pub error_rate: f64,
+    /// Moving average of the response time in seconds.
+    pub latency: f64,
+    /// Number of finished tasks.
+    pub tasks: u64,
+}
+
+/// Result of a task, as seen by the reputation.
+#[derive(Debug, Clone, Copy)]
+pub enum TaskOutcome {
+    Verified {
+        relevance: u8,
+        latency: f64,
+    },
+    /// The node responded, but the orchestrator failed to judge the response.
+    Unverified {
+        latency: f64,
+    },
+    /// The node judged the response of another node.
+    Inspected {
+        latency: f64,
+    },
+    Timeout,
+    Error,
+}
+
+impl Reputation {
+    pub fn record(&mut self, outcome: TaskOutcome) {
+        let avg = |avg: f64, value: f64| (value - avg).mul_add(REPUTATION_SMOOTHING, avg);
+
+        match outcome {
+            TaskOutcome::Verified { relevance, latency } => {
+                self.relevance = avg(self.relevance, relevance as f64);
+                self.latency = if self.latency == 0.0 {
+                    latency
+                } else {
+                    avg(self.latency, latency)
+                };
+                self.timeout_rate = avg(self.timeout_rate, 0.0);
+                self.error_rate = avg(self.error_rate, 0.0);
+            }
+            TaskOutcome::Unverified { latency } | TaskOutcome::Inspected { latency } => {
+                self.latency = if self.latency == 0.0 {
+                    latency
+                } else {
+                    avg(self.latency, latency)
+                };
+                self.timeout_rate = avg(self.timeout_rate, 0.0);
+                self.error_rate = avg(self.error_rate, 0.0);
+            }
+            TaskOutcome::Timeout => {
+                self.timeout_rate = avg(self.timeout_rate, 1.0);
+                self.error_rate = avg(self.error_rate, 0.0);
+            }
+            TaskOutcome::Error => {
+                self.timeout_rate = avg(self.timeout_rate, 0.0);
+                self.error_rate = avg(self.error_rate, 1.0);
+            }
+        }
+        self.tasks += 1;
+    }
+
+    /// Relative chance of the node to be selected for a task.
+    pub fn weight(&self) -> f64 {
+        let quality = self.relevance / 100.0 * (1.0 - self.timeout_rate) * (1.0 - self.error_rate);
+        let speed = 1.0 / (1.0 + self.latency / 60.0);
+        (quality * speed).max(MIN_WEIGHT)
+    }
+}
+
+impl Default for Reputation {
+    fn default() -> Self {
+        Self {
+            relevance: 50.0,
+            timeout_rate: 0.0,
+            error_rate: 0.0,
+            latency: 0.0,
+            tasks: 0,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NodeInfo {
     pub address: Option<Multiaddr>,
@@ -40,9 +194,13 @@ pub struct NodeInfo {
     #[serde(deserialize_with = "deserialize_peer")]
     pub peer_id: PeerId,
     pub is_connected: bool,
+    #[serde(default)]
+    pub reputation: Reputation,
+    #[serde(default)]
+    pub capabilities: Option<Capabilities>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct ClusterInfoWithNodes {
     pub cluster_info: ClusterInfo,
     pub nodes: HashMap<PeerId, Node>,
@@ -113,8 +271,14 @@ pub struct MetricsInfo {
     pub errors: u64,
     /// timeouts
     pub timeouts: u64,
+    /// requests sent to additional nodes
+    #[serde(default)]
+    pub hedges: u64,
     /// avg latency
     pub latency: f64,
+    /// requests sent to the nodes and not answered yet, by node
+    #[serde(default)]
+    pub node_in_flight: HashMap<String, i64>,
 }
 
 pub fn deserialize_peer<'de, D>(deserializer: D) -> Result<PeerId, D::Error>
@@ -1,4 +1,10 @@
-use crate::ai::{query::QueryId, request::SignedAiRequest, response::SignedAiResponse};
+use crate::{
+    ai::{
+        query::QueryId, request::SignedAiRequest, response::SignedAiResponse,
+        verification::SignedVerificationResult,
+    },
+    cluster::{Capabilities, LoadReport},
+};
 use crypto::ed25519::public::PublicKey;
 use multiaddr::Multiaddr;
 use serde::{Deserialize, Serialize};
@@ -9,20 +15,46 @@ pub enum EveMessage {
     Node(NodeMessage),
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum OrchMessage {
     AiRequest {
         id: QueryId,
         request: SignedAiRequest,
     },
+    /// Asks the node to judge the response of another node to the request.
+    VerifyRequest {
+        id: QueryId,
+        request: SignedAiRequest,
+        response: SignedAiResponse,
+    },
+    /// The user cancelled the query: the node stops generating the response.
+    Cancel { id: QueryId },
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize, Deserialize)]
 pub enum NodeMessage {
     AiResponse {
         id: QueryId,
         response: Result<SignedAiResponse, String>,
     },
+    /// Part of the answer that is still being generated.
+    /// `offset` is the position of the chunk in the answer text, in bytes.
+    AiResponseChunk {
+        id: QueryId,
+        offset: u64,
+        chunk: String,
+    },
+    /// Sent to the orchestrator after the connection.
+    Capabilities(Capabilities),
+    Load(LoadReport),
+    /// Verdict on the response of the node `node`.
+    VerifyResponse {
+        id: QueryId,
+        node: PublicKey,
+        result: Result<SignedVerificationResult, String>,
+    },
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
@@ -1,10 +1,14 @@
 use crate::init_logger;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use color_eyre::eyre::{eyre, Context, Error, Result};
 use crypto::ed25519::private::PrivateKey;
 use multiaddr::Multiaddr;
 use node_config::{
-    base::BaseConfig, llm::OllamaConfig, node::NodeConfig, p2p::NodeP2PConfig, Config,
+    base::BaseConfig,
+    llm::{GgufConfig, LlmConfig, MockConfig, OllamaConfig, OpenAiConfig},
+    node::NodeConfig,
+    p2p::NodeP2PConfig,
+    Config,
 };
 use std::{
     fs::{self, create_dir_all},
@@ -22,11 +26,35 @@ pub(crate) struct CfgNode {
     #[arg(default_value = NODE_PATH_DEFAULT)]
     path: PathBuf,
 
+    /// LLM backend serving the model. Default is ollama
+    #[arg(short, long, value_enum, default_value_t = LlmBackend::Ollama)]
+    backend: LlmBackend,
+
     /// URL to the ollama. Default is http://localhost:11434
     #[arg(short = 'l', long, default_value = "http://localhost:11434")]
     ollama_url: Url,
 
-    /// Model to use for the ollama. Default is deepseek-r1:1.5b
+    /// Base URL of the OpenAI-compatible API. Default is http://localhost:8000/v1
+    #[arg(long, default_value = "http://localhost:8000/v1")]
+    openai_url: Url,
+
+    /// API key of the OpenAI-compatible server
+    #[arg(long)]
+    api_key: Option<String>,
+
+    /// GGUF file of the model. Default is the downloaded DeepSeek-R1 1.5B in the node directory
+    #[arg(long)]
+    gguf_path: Option<PathBuf>,
+
+    /// SHA-256 of the downloaded GGUF file. Default is the checksum of DeepSeek-R1 1.5B
+    #[arg(long)]
+    sha256: Option<String>,
+
+    /// Download the GGUF file without a checksum
+    #[arg(long)]
+    allow_unverified: bool,
+
+    /// Model to use. Default is deepseek-r1:1.5b
     #[arg(short, long, default_value = "deepseek-r1:1.5b")]
     ai_model: String,
 
@@ -49,11 +77,7 @@ impl CfgNode {
 
         let self_key = PrivateKey::generate();
 
-        let llm = OllamaConfig {
-            url: self.ollama_url.clone(),
-            model: self.ai_model.clone(),
-            ..Default::default()
-        };
+        let llm = self.llm_config();
 
         let cfg = NodeConfig {
             base: BaseConfig {
@@ -62,6 +86,7 @@ impl CfgNode {
                 orch_pub_key: info.orch_pubkey,
             },
             llm,
+            context: Default::default(),
             logger: Default::default(),
             p2p: NodeP2PConfig {
                 address: self.p2p_address,
@@ -88,6 +113,39 @@ impl CfgNode {
         Ok(())
     }
 
+    fn llm_config(&self) -> LlmConfig {
+        match self.backend {
+            LlmBackend::Ollama => LlmConfig::Ollama(OllamaConfig {
+                url: self.ollama_url.clone(),
+                model: self.ai_model.clone(),
+                ..Default::default()
+            }),
+            LlmBackend::Openai => LlmConfig::OpenAi(OpenAiConfig {
+                url: self.openai_url.clone(),
+                model: self.ai_model.clone(),
+                api_key: self.api_key.clone(),
+                ..Default::default()
+            }),
+            LlmBackend::Gguf => {
+                let default = GgufConfig::default();
+                LlmConfig::Gguf(GgufConfig {
+                    path: self
+                        .gguf_path
+                        .clone()
+                        .unwrap_or_else(|| self.path.join(&default.path)),
+                    sha256: self.sha256.clone().or_else(|| default.sha256.clone()),
+                    allow_unverified: self.allow_unverified,
+                    model: self.ai_model.clone(),
+                    ..default
+                })
+            }
+            LlmBackend::Mock => LlmConfig::Mock(MockConfig {
+                model: self.ai_model.clone(),
+                ..Default::default()
+            }),
+        }
+    }
+
     async fn load_cluster_info(&self) -> Result<ClusterInfo, Error> {
         let client = orchestrator_client::Client::new(self.orch.clone())
             .context("Failed to create orchestrator client")?;
@@ -97,3 +155,16 @@ impl CfgNode {
             .context("Failed to load cluster info")
     }
 }
+
+/// LLM backends of the node.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum LlmBackend {
+    /// Ollama daemon
+    Ollama,
+    /// Server with the OpenAI-compatible API: vLLM, llama.cpp server, LM Studio, TGI
+    Openai,
+    /// GGUF model run by llama.cpp in the node, needs the `gguf` feature
+    Gguf,
+    /// Echo of the questions, for the offline clusters
+    Mock,
+}
@@ -7,7 +7,7 @@ use multiaddr::Multiaddr;
 use node_config::{
     api::ApiConfig,
     base::BaseConfig,
-    llm::OllamaConfig,
+    llm::{LlmConfig, OllamaConfig},
     node::NodeConfig,
     orch::OrchConfig,
     p2p::{NodeP2PConfig, OrchP2PConfig},
@@ -85,6 +85,7 @@ impl Init {
                     orch_pub_key: orch_key.public_key(),
                 },
                 llm: llm.clone(),
+                context: Default::default(),
                 logger: Default::default(),
                 p2p: NodeP2PConfig {
                     address: vec![peer.address.clone().unwrap()],
@@ -120,6 +121,7 @@ impl Init {
             db: Default::default(),
             rpc: RpcConfig { address: rpc },
             api: ApiConfig::default(),
+            verifier: Default::default(),
             p2p: OrchP2PConfig {
                 address: vec![orch_quic_addr, orch_webrtc_addr],
             },
@@ -169,11 +171,11 @@ impl Init {
             )
     }
 
-    fn llm_config(&self) -> OllamaConfig {
-        OllamaConfig {
+    fn llm_config(&self) -> LlmConfig {
+        LlmConfig::Ollama(OllamaConfig {
             url: self.ollama_url.clone(),
             model: self.ai_model.clone(),
             ..Default::default()
-        }
+        })
     }
 }
@@ -1,5 +1,5 @@
 use crate::{init_logger, CONFIG_NAME, NODE_PATH_DEFAULT};
-use ai::{ollama::Llm, Ai};
+use ai::{backend::Backend, context::ContextWindow, Ai};
 use clap::Parser;
 use color_eyre::eyre::{ensure, Context, Result};
 use node::spawn_node;
@@ -33,14 +33,15 @@ impl Run {
 
         init_logger(Some(cfg.logger()));
 
-        let llm = Llm::new(cfg.llm()).await?;
-        let ai = Arc::new(llm);
+        let llm = Backend::new(cfg.llm()).await?;
 
         match cfg {
-            node_config::Config::Orch(cfg) => start_orchestrator(*cfg, ai)
+            node_config::Config::Orch(cfg) => start_orchestrator(*cfg, Arc::new(llm))
                 .await
                 .context("Failed to start orchestrator"),
             node_config::Config::Node(cfg) => {
+                let context_length = cfg.llm.capabilities().context_length;
+                let ai = Arc::new(ContextWindow::new(llm, &cfg.context, context_length));
                 start_node(*cfg, ai).await.context("Failed to start node")
             }
         }
@@ -75,6 +76,7 @@ async fn start_orchestrator<AI: Ai + Send + Sync + 'static>(
         ai,
         cfg.base.key.clone(),
         &cfg.ai_tasks,
+        &cfg.verifier,
     )
     .await
     .context("Failed to spawn orchestrator runtime")?;
@@ -109,6 +111,7 @@ async fn start_node<AI: Ai + Send + Sync + 'static>(cfg: NodeConfig, ai: Arc<AI>
         cfg.base.orch_pub_key,
         cfg.base.key.clone(),
         cfg.p2p.orch_address.clone(),
+        cfg.llm.capabilities(),
     )
     .await
     .context("Failed to spawn node runtime")?;
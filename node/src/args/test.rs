@@ -1,5 +1,5 @@
 use crate::init_logger;
-use ai::{ollama::Llm, Ai};
+use ai::{backend::Backend, Ai};
 use clap::Parser;
 use color_eyre::eyre::{ensure, eyre, Context as _, Error, Result};
 use crypto::ed25519::{private::PrivateKey, public::PublicKey};
@@ -7,7 +7,10 @@ use jwt::JwtSecret;
 use multiaddr::Multiaddr;
 use node::spawn_node;
 use node_config::{
-    api::ApiConfig, llm::OllamaConfig, rpc::default_rpc_address, tasks::AiTasksConfig,
+    api::ApiConfig,
+    llm::{LlmConfig, MockConfig, OllamaConfig},
+    rpc::default_rpc_address,
+    tasks::AiTasksConfig,
 };
 use orchestrator::{spawn_orchestrator, OrchRequest};
 use p2p::Config;
@@ -24,7 +27,11 @@ use tokio::{
     task::JoinHandle,
 };
 use tracing::{error, info, instrument};
-use types::{cluster::ClusterInfoWithNodes, p2p::Peer, AiModel};
+use types::{
+    cluster::{Capabilities, ClusterInfoWithNodes},
+    p2p::Peer,
+    AiModel,
+};
 use url::Url;
 
 const LISTENING_QUIC_ADDRESS: &str = "/ip4/127.0.0.1/udp/0/quic-v1";
@@ -55,6 +62,10 @@ pub(crate) struct TestRun {
     /// Model to use for the ollama
     #[arg(short, long, default_value_t = AiModel::DeepseekR1_8b)]
     ai_model: AiModel,
+
+    /// Answer with the mock backend instead of the ollama, the questions are echoed
+    #[arg(short, long)]
+    mock: bool,
 }
 
 impl TestRun {
@@ -65,15 +76,16 @@ impl TestRun {
 
         let (_, store) = storage(self.path.as_ref())?;
 
-        let ai = Arc::new(
-            Llm::new(&OllamaConfig {
+        let llm = if self.mock {
+            LlmConfig::Mock(MockConfig::default())
+        } else {
+            LlmConfig::Ollama(OllamaConfig {
                 url: self.ollama_url,
                 model: self.ai_model.to_string(),
                 ..Default::default()
             })
-            .await
-            .unwrap(),
-        );
+        };
+        let ai = Arc::new(Backend::new(&llm).await?);
 
         let orch_p2p = orch_p2p().await?;
         info!("Orchestrator key: {:?}", orch_p2p.key.public_key());
@@ -102,8 +114,14 @@ impl TestRun {
             })
             .await?;
 
-            let hndl =
-                start_node(ai.clone(), orch_key.public_key(), node, orch_addr.clone()).await?;
+            let hndl = start_node(
+                ai.clone(),
+                orch_key.public_key(),
+                node,
+                orch_addr.clone(),
+                llm.capabilities(),
+            )
+            .await?;
             handlers.extend(hndl);
         }
 
@@ -271,6 +289,7 @@ async fn spawn_orch<AI: Ai + Send + Sync + 'static>(
         whitelist: peers,
         replication_factor: 3,
         task_timeout_secs: 60,
+        ..Default::default()
     };
 
     let orch_handles = spawn_orchestrator(
@@ -280,6 +299,7 @@ async fn spawn_orch<AI: Ai + Send + Sync + 'static>(
         ai,
         orch.key,
         &cfg,
+        &Default::default(),
     )
     .await?;
     Ok((
@@ -287,6 +307,7 @@ async fn spawn_orch<AI: Ai + Send + Sync + 'static>(
             api_handles,
             orch.p2p.handler,
             orch_handles.ev,
+            orch_handles.agg,
             orch_handles.orch,
         ],
         api_tx,
@@ -299,6 +320,7 @@ async fn start_node<AI: Ai + Send + Sync + 'static>(
     orch_key: PublicKey,
     node: Node,
     orch_address: Multiaddr,
+    capabilities: Capabilities,
 ) -> Result<Vec<JoinHandle<()>>> {
     let node_handler = spawn_node(
         node.p2p_sender,
@@ -307,6 +329,7 @@ async fn start_node<AI: Ai + Send + Sync + 'static>(
         orch_key,
         node.key,
         orch_address,
+        capabilities,
     )
     .await
     .context("Failed to spawn node runtime")?;
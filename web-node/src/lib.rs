@@ -19,11 +19,18 @@ use p2p::{spawn, sys::sleep, Config};
 use std::sync::Arc;
 use tracing::Level;
 use tracing_wasm::WASMLayerConfigBuilder;
-use types::p2p::EveMessage;
+use types::{
+    ai::models::AiWebModel,
+    cluster::{Capabilities, HardwareClass},
+    p2p::EveMessage,
+};
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 use web_sys::Storage;
 use wonnx::Wonnx;
 
+/// Context window of the model run in the browser, in tokens.
+const WEB_CONTEXT_LENGTH: u64 = 2048;
+
 // Called when the Wasm module is instantiated
 #[wasm_bindgen(start)]
 fn main() {
@@ -121,6 +128,12 @@ impl EveNode {
             info.orch_pubkey,
             self.settings.private_key.clone().unwrap(),
             webrtc,
+            Capabilities {
+                model: AiWebModel::DeepseekR1_1_5b.to_string(),
+                context_length: WEB_CONTEXT_LENGTH,
+                max_jobs: 1,
+                hardware: HardwareClass::Browser,
+            },
         )
         .await
         .map_err(|err| format!("Error spawning node: {}", err))?;
@@ -177,6 +177,7 @@ impl TryFrom<MessageEvent> for WonnxMessage {
                         Ok(WonnxMessage::Generate(GenerateStatus::Done(Answer {
                             message: output.remove(0),
                             tokens,
+                            truncation: None,
                         })))
                     }
                     _ => Err(eyre::eyre!("Unknown progress_type: {}", tp)),